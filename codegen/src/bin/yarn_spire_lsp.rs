@@ -0,0 +1,15 @@
+use std::process::ExitCode;
+
+/// Language server for `.yarn` files, editors start it and talk to it through stdin/stdout.
+fn main() -> ExitCode {
+	let stdin = std::io::stdin();
+	let stdout = std::io::stdout();
+
+	match yarn_spire_codegen::run_language_server(stdin.lock(), stdout.lock()) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(err) => {
+			eprintln!("{err}");
+			ExitCode::FAILURE
+		},
+	}
+}
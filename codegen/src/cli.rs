@@ -0,0 +1,282 @@
+use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+use yarn_spire_codegen::ConfigOverrides;
+
+pub const DEFAULT_CONFIG_PATH: &str = "yarn_project.toml";
+
+const HELP: &str =
+	"Usage: yarn_spire_codegen [COMMAND] [OPTIONS]\n\
+	 \n\
+	 Commands:\n\
+	 \x20 init                        Write a commented default config file at the `--config` path.\n\
+	 \x20 lint                        Check the yarn files for likely mistakes, without generating anything.\n\
+	 \x20 fmt                         Format the yarn files, with `--check` only list the ones that are not formatted.\n\
+	 \n\
+	 Options:\n\
+	 \x20 -c, --config <PATH>         Path to the config file. [default: yarn_project.toml]\n\
+	 \x20 -o, --out <DIR>             Overrides `destination_os_path` from the config file.\n\
+	 \x20 -r, --yarn-root <DIR>       Overrides `yarn_root_folder(s)` from the config file.\n\
+	 \x20 -p, --project <NAME>        Only generate the project with this name. [default: all projects]\n\
+	 \x20     --check                 Verify that the generated files are up to date, without writing anything.\n\
+	 \x20 -w, --watch                 Keep running, regenerating the code whenever a yarn file changes.\n\
+	 \x20     --message-format <FMT>  How errors are printed, `human` or `json`(one object per line). [default: human]\n\
+	 \x20 -q, --quiet                 Only print errors.\n\
+	 \x20 -v, --verbose               Print every step of the generation process.\n\
+	 \x20 -h, --help                  Print this message.\n\
+	 \n\
+	 Relative paths inside the config file are resolved relative to the config file's folder,\n\
+	 paths provided as arguments are resolved relative to the current directory.\n\
+	 `--out` and `--yarn-root` require selecting a project when the config file declares several.\n\
+	 `--message-format=json` implies `--quiet`, so only JSON is printed to stdout.";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+	Quiet,
+	Normal,
+	Verbose,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Command {
+	Generate,
+	Init,
+	Lint,
+	Fmt,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MessageFormat {
+	Human,
+	Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliArgs {
+	pub command: Command,
+	pub config_path: PathBuf,
+	pub overrides: ConfigOverrides,
+	pub project: Option<String>,
+	pub verbosity: Verbosity,
+	pub message_format: MessageFormat,
+	pub check: bool,
+	pub watch: bool,
+}
+
+pub enum ParsedArgs {
+	Run(CliArgs),
+	Help,
+}
+
+impl CliArgs {
+	pub fn from_env() -> Result<ParsedArgs> {
+		Self::parse(std::env::args().skip(1))
+	}
+
+	pub fn parse(args: impl IntoIterator<Item = String>) -> Result<ParsedArgs> {
+		let mut config_path = None;
+		let mut overrides = ConfigOverrides::default();
+		let mut project = None;
+		let mut verbosity = None;
+		let mut message_format = MessageFormat::Human;
+		let mut check = false;
+		let mut watch = false;
+
+		let mut args = args.into_iter().peekable();
+		
+		let command =
+			match args.next_if(|arg| arg == "init" || arg == "lint" || arg == "fmt").as_deref() {
+				Some("init") => Command::Init,
+				Some("lint") => Command::Lint,
+				Some(_) => Command::Fmt,
+				None => Command::Generate,
+			};
+
+		while let Some(arg) = args.next() {
+			let (flag, inline_value) =
+				match arg.split_once('=') {
+					Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+					_ => (arg, None),
+				};
+
+			let mut value_of = |flag: &str| -> Result<String> {
+				inline_value
+					.clone()
+					.or_else(|| args.next())
+					.filter(|value| !value.is_empty())
+					.ok_or_else(|| anyhow!(
+						"Argument `{flag}` expects a value, but none was provided.\n\n\
+						 Help: Use `{flag} <VALUE>` or `{flag}=<VALUE>`."))
+			};
+
+			let mut set_verbosity = |to_set: Verbosity| -> Result<()> {
+				match verbosity {
+					Some(already) if already != to_set => {
+						Err(anyhow!(
+							"Arguments `--quiet` and `--verbose` cannot be used together.\n\n\
+							 Help: Pick only one of them."))
+					},
+					_ => {
+						verbosity = Some(to_set);
+						Ok(())
+					},
+				}
+			};
+
+			match flag.as_str() {
+				"-c" | "--config" => {
+					config_path = Some(PathBuf::from(value_of("--config")?));
+				},
+				"-o" | "--out" => {
+					overrides.destination_os_path = Some(PathBuf::from(value_of("--out")?));
+				},
+				"-r" | "--yarn-root" => {
+					overrides.yarn_root_folder = Some(PathBuf::from(value_of("--yarn-root")?));
+				},
+				"-p" | "--project" => {
+					project = Some(value_of("--project")?);
+				},
+				"--message-format" => {
+					message_format =
+						match value_of("--message-format")?.as_str() {
+							"human" => MessageFormat::Human,
+							"json" => MessageFormat::Json,
+							other => {
+								return Err(anyhow!(
+									"Unknown message format: `{other}`\n\n\
+									 Help: Expected either `human` or `json`."));
+							},
+						};
+				},
+				"--check" => {
+					check = true;
+				},
+				"-w" | "--watch" => {
+					watch = true;
+				},
+				"-q" | "--quiet" => {
+					set_verbosity(Verbosity::Quiet)?;
+				},
+				"-v" | "--verbose" => {
+					set_verbosity(Verbosity::Verbose)?;
+				},
+				"-h" | "--help" => {
+					return Ok(ParsedArgs::Help);
+				},
+				unknown => {
+					return Err(anyhow!(
+						"Unknown argument: `{unknown}`\n\n\
+						 {HELP}"));
+				},
+			}
+		}
+
+		if command == Command::Init && (check || watch || project.is_some() || overrides != ConfigOverrides::default()) {
+			return Err(anyhow!(
+				"Command `init` only accepts the `--config` argument.\n\n\
+				 Help: Use `init --config <PATH>` to choose where the config file is written."));
+		}
+
+		if command == Command::Lint && (check || watch) {
+			return Err(anyhow!(
+				"Command `lint` does not accept `--check` nor `--watch`.\n\n\
+				 Help: `lint` never writes anything, it only reports warnings."));
+		}
+
+		if command == Command::Fmt && watch {
+			return Err(anyhow!(
+				"Command `fmt` does not accept `--watch`.\n\n\
+				 Help: Most editors can run `fmt` when a file is saved."));
+		}
+
+		if message_format == MessageFormat::Json && verbosity == Some(Verbosity::Verbose) {
+			return Err(anyhow!(
+				"Arguments `--message-format=json` and `--verbose` cannot be used together.\n\n\
+				 Help: JSON output only contains diagnostics, progress messages would make it unparseable."));
+		}
+
+		if message_format == MessageFormat::Json {
+			verbosity = Some(Verbosity::Quiet);
+		}
+
+		if check && watch {
+			return Err(anyhow!(
+				"Arguments `--check` and `--watch` cannot be used together.\n\n\
+				 Help: `--check` never writes anything, so there is nothing to watch for."));
+		}
+
+		Ok(ParsedArgs::Run(CliArgs {
+			command,
+			config_path: config_path.unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH)),
+			overrides,
+			project,
+			verbosity: verbosity.unwrap_or(Verbosity::Normal),
+			message_format,
+			check,
+			watch,
+		}))
+	}
+}
+
+pub fn print_help() {
+	println!("{HELP}");
+}
+
+#[test]
+fn test_parse() {
+	use houtamelo_utils::own;
+	use pretty_assertions::assert_eq;
+
+	fn parse(args: &[&str]) -> CliArgs {
+		match CliArgs::parse(args.iter().map(|arg| arg.to_string())).unwrap() {
+			ParsedArgs::Run(cli_args) => cli_args,
+			ParsedArgs::Help => panic!("Expected `ParsedArgs::Run`, got `ParsedArgs::Help`."),
+		}
+	}
+
+	assert_eq!(parse(&[]), CliArgs {
+		command: Command::Generate,
+		config_path: PathBuf::from(DEFAULT_CONFIG_PATH),
+		overrides: ConfigOverrides::default(),
+		project: None,
+		verbosity: Verbosity::Normal,
+		message_format: MessageFormat::Human,
+		check: false,
+		watch: false,
+	});
+
+	assert_eq!(parse(&["--config", "game/yarn_project.toml", "-o", "src/dialogue", "--yarn-root=dialogue", "-p", "dlc", "-v"]), CliArgs {
+		command: Command::Generate,
+		config_path: PathBuf::from("game/yarn_project.toml"),
+		overrides: ConfigOverrides {
+			destination_os_path: Some(PathBuf::from("src/dialogue")),
+			yarn_root_folder: Some(PathBuf::from("dialogue")),
+		},
+		project: Some(own!("dlc")),
+		verbosity: Verbosity::Verbose,
+		message_format: MessageFormat::Human,
+		check: false,
+		watch: false,
+	});
+
+	assert_eq!(parse(&["-q", "--quiet"]).verbosity, Verbosity::Quiet);
+	assert!(parse(&["--check"]).check);
+	assert_eq!(parse(&["--message-format", "json"]).message_format, MessageFormat::Json);
+	assert_eq!(parse(&["--message-format=json"]).verbosity, Verbosity::Quiet);
+	assert!(parse(&["-w"]).watch);
+	assert_eq!(parse(&["init", "-c", "game/yarn_project.toml"]).command, Command::Init);
+	assert_eq!(parse(&["lint", "-p", "dlc"]).command, Command::Lint);
+	assert_eq!((parse(&["fmt", "--check"]).command, parse(&["fmt", "--check"]).check), (Command::Fmt, true));
+
+	assert!(matches!(CliArgs::parse(["--help".to_string()]), Ok(ParsedArgs::Help)));
+	assert!(CliArgs::parse(["--config".to_string()]).is_err());
+	assert!(CliArgs::parse(["--out=".to_string()]).is_err());
+	assert!(CliArgs::parse(["-q".to_string(), "-v".to_string()]).is_err());
+	assert!(CliArgs::parse(["--unknown".to_string()]).is_err());
+	assert!(CliArgs::parse(["--check".to_string(), "--watch".to_string()]).is_err());
+	assert!(CliArgs::parse(["--message-format=xml".to_string()]).is_err());
+	assert!(CliArgs::parse(["--message-format=json".to_string(), "-v".to_string()]).is_err());
+	assert!(CliArgs::parse(["init".to_string(), "--check".to_string()]).is_err());
+	assert!(CliArgs::parse(["-q".to_string(), "init".to_string()]).is_err());
+	assert!(CliArgs::parse(["lint".to_string(), "--watch".to_string()]).is_err());
+	assert!(CliArgs::parse(["fmt".to_string(), "-w".to_string()]).is_err());
+}
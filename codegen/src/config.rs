@@ -1,11 +1,23 @@
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use anyhow::{Result, anyhow};
 use encoding_rs_io::DecodeReaderBytesBuilder;
+use glob::Pattern;
 use serde::Deserialize;
+use crate::lint::LintConfig;
+
+/// Values that take priority over the ones in the config file, usually provided in the command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigOverrides {
+	pub destination_os_path: Option<PathBuf>,
+	pub yarn_root_folder: Option<PathBuf>,
+}
 
 pub struct YarnConfig {
+	/// Used to tell projects apart in messages, defaults to `destination_module_path`.
+	pub name: String,
+	pub config_path: PathBuf,
 	pub storage_qualified: String,
 	pub storage_direct: String,
 	pub command_qualified: String,
@@ -15,40 +27,99 @@ pub struct YarnConfig {
 	pub allow_overwrite: bool,
 	pub generate_storage: bool,
 	pub destination_os_path: PathBuf,
-	pub yarn_root_folder: PathBuf,
+	pub yarn_root_folders: Vec<PathBuf>,
+	/// Patterns relative to each root, a file must match at least one of them to be parsed.
+	pub include_patterns: Vec<Pattern>,
+	/// Patterns relative to each root, files matching any of them are skipped.
+	pub exclude_patterns: Vec<Pattern>,
 	pub exclude_yarn_folders: Vec<PathBuf>,
+	/// How many errors are reported at most, `0` means no limit.
+	pub max_errors: usize,
+	pub lints: LintConfig,
+	pub line_group_selection: LineGroupSelection,
+	pub node_group_saliency: NodeGroupSaliency,
 }
 
-#[derive(Deserialize)]
-struct DeserializableConfig {
-	storage_module_path: String,
-	storage_type_name: String,
-	command_module_path: String,
-	command_type_name: String,
-	vars_module_path: String,
-	allow_overwrite: bool,
-	generate_storage: bool,
-	destination_os_path: String,
-	destination_module_path: String,
-	yarn_root_folder: String,
-	exclude_yarn_folders: Vec<String>,
-}
-
-fn read_file() -> Result<String> {
-	let path_buf =
-		PathBuf::from_str("yarn_project.toml")
-			.map_err(|err| anyhow!(
-				"Could not parse `Config` file path into `PathBuf`.\n\
-				 Path: `yarn_project.toml`\n\
-				 Error: `{err}`\n\n\
-				 Help: Ensure the `Config` file path is a valid OS file-system path. (like `/src/dialogues/nodes/config.toml`)")
-			)?;
+/// How one line of a `=>` line group is picked among the ones whose condition passed.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineGroupSelection {
+	/// Uniformly random, using the storage's `dice`.
+	#[default]
+	Random,
+	/// The first eligible line, in the order they were written.
+	First,
+	/// Calls `storage.select_line(candidates)`, which must return an index of `candidates`.
+	Custom,
+}
+
+/// How one node of a node group (nodes sharing a title, with `when:` headers) is picked among the eligible ones.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeGroupSaliency {
+	/// The first eligible node, in the order they were declared.
+	#[default]
+	First,
+	/// Uniformly random, using the storage's `dice`.
+	Random,
+	/// The eligible node that was picked the longest time ago, never picked ones first.
+	LeastRecentlySeen,
+	/// Calls `storage.select_node(candidates)`, which must return an index of `candidates`.
+	Custom,
+}
+
+/// The raw contents of a config file, before any validation or path resolution.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeserializableConfig {
+	#[serde(default)]
+	pub name: String,
+	pub storage_module_path: String,
+	pub storage_type_name: String,
+	pub command_module_path: String,
+	pub command_type_name: String,
+	pub vars_module_path: String,
+	pub allow_overwrite: bool,
+	pub generate_storage: bool,
+	#[serde(default)]
+	pub destination_os_path: String,
+	pub destination_module_path: String,
+	#[serde(default)]
+	pub yarn_root_folder: String,
+	#[serde(default)]
+	pub yarn_root_folders: Vec<String>,
+	#[serde(default)]
+	pub include: Vec<String>,
+	#[serde(default)]
+	pub exclude: Vec<String>,
+	#[serde(default)]
+	pub exclude_yarn_folders: Vec<String>,
+	#[serde(default)]
+	pub max_errors: Option<usize>,
+	#[serde(default)]
+	pub lints: LintConfig,
+	#[serde(default)]
+	pub line_group_selection: LineGroupSelection,
+	#[serde(default)]
+	pub node_group_saliency: NodeGroupSaliency,
+}
 
+impl DeserializableConfig {
+	fn project_name(&self) -> &str {
+		if self.name.is_empty() {
+			&self.destination_module_path
+		} else {
+			&self.name
+		}
+	}
+}
+
+fn read_file(path: &Path) -> Result<String> {
 	let file =
-		std::fs::File::open(path_buf)
+		std::fs::File::open(path)
 			.map_err(|err| anyhow!(
-				"Could not open `Config` file.\
-				 Path: `yarn_project.toml`\n\
+				"Could not open `Config` file.\n\
+				 Path: {path:?}\n\
 				 Error: `{err}`\n\n\
 				 Help: This program might need additional permissions to access this file.\n\
 				 Help: Try running the program as an administrator.")
@@ -66,8 +137,8 @@ fn read_file() -> Result<String> {
 
 	reader.read_to_string(&mut buffer)
 	      .map_err(|err| anyhow!(
-			"Could not read `Config` file.\
-			 Path: `yarn_project.toml`\n\
+			"Could not read `Config` file.\n\
+			 Path: {path:?}\n\
 			 Error: `{err}`\n\n\
 			 Help: This program might need additional permissions to access this file.\n\
 			 Help: Try running the program as an administrator.")
@@ -76,39 +147,172 @@ fn read_file() -> Result<String> {
 	Ok(buffer)
 }
 
-fn deserialize(toml_input: String) -> Result<DeserializableConfig> {
-	toml::from_str(&toml_input)
-		.map_err(|err| anyhow!(
-			"Could not parse `Config` file as `DeserializableConfig`.\n\
-			 Input: `{toml_input}`\n\
-			 Error: `{err}`\n\n\
-			 Ensure the file is in a valid format, example:\n\
-			 ```toml\n\
-			 # Storage variable's module path without the type name.
-			  (This should be empty if `generate_storage` is `true`)
-			 storage_path = \"crate::dialogues\"\n\
-			 # Storage variable's type name. (do not include the path)
-			 storage_type_name = \"MyVariablesStorage\"\n\
-			 # Command's module path without the type name.
-			 command_path = \"crate::yarn_command\"\n\
-			 # Command's type name. (do not include the path)
-			 command_type_name = \"MyYarnCommand\"\n\
-			 # The module path of the structs that implement `IVar`.
-			 vars_module_path = \"crate::dialogues::yarn_nodes::vars\"\n\
-			 # If true, the program will overwrite files in the destination folder.
-			 allow_overwrite = true\n\
-			 # If true, the program will generate a Storage struct for you, using the variable declarations provided.
-			 # The name of the generated struct will be the same as the `storage_type_name`.
-			 generate_storage = true\n\
-			 # The OS(Operational System) destination folder for the generated files.
-			 destination_os_path = \"/src/nodes/quoting\"\n\
-			 # The Rust module path of the destination folder.
-			 destination_module_path = \"crate::dialogue::yarn_nodes\"\n\
-			 # The root folder of the Yarn scripts this program will attempt to parse.
-			 yarn_root_folder = \"../yarn_scripts\"\n\
-			 # The folders inside `yarn_root_folder` that will be excluded from parsing.
-			 exclude_yarn_folders = [\"test\", \"yarn.lock\", \"prototype\"]\n\
-			 ```"))
+/// A fully commented config file, written by `init` and shown when the config file is invalid.
+pub const DEFAULT_CONFIG: &str = include_str!("default_config.toml");
+
+fn invalid_format(toml_input: &str, err: impl std::fmt::Display) -> anyhow::Error {
+	anyhow!(
+		"Could not parse `Config` file as `DeserializableConfig`.\n\
+		 Input: `{toml_input}`\n\
+		 Error: `{err}`\n\n\
+		 Ensure the file is in a valid format, example:\n\
+		 ```toml\n\
+		 {DEFAULT_CONFIG}\
+		 ```")
+}
+
+const FIELD_NAMES: &[&str] = &[
+	"name",
+	"storage_module_path",
+	"storage_type_name",
+	"command_module_path",
+	"command_type_name",
+	"vars_module_path",
+	"allow_overwrite",
+	"generate_storage",
+	"destination_os_path",
+	"destination_module_path",
+	"yarn_root_folder",
+	"yarn_root_folders",
+	"include",
+	"exclude",
+	"exclude_yarn_folders",
+	"max_errors",
+	"lints",
+	"line_group_selection",
+	"node_group_saliency",
+];
+
+/// Fields from older versions, and their current names.
+const RENAMED_FIELDS: &[(&str, &str)] = &[
+	("storage_path", "storage_module_path"),
+	("command_path", "command_module_path"),
+];
+
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b = b.chars().collect::<Vec<_>>();
+	let mut previous = (0..=b.len()).collect::<Vec<_>>();
+	
+	for (i, a_char) in a.chars().enumerate() {
+		let mut current = vec![i + 1];
+		
+		for (j, b_char) in b.iter().enumerate() {
+			let substitution = previous[j] + usize::from(a_char != *b_char);
+			current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+		}
+		
+		previous = current;
+	}
+	
+	previous[b.len()]
+}
+
+fn closest_field(key: &str) -> Option<&'static str> {
+	FIELD_NAMES
+		.iter()
+		.map(|field| (edit_distance(key, field), *field))
+		.min()
+		.filter(|(distance, _)| *distance <= 3)
+		.map(|(_, field)| field)
+}
+
+/// `location` describes where `table` was declared, like "the top level" or "`project` number 2".
+fn ensure_known_fields(table: &toml::Table, location: &str) -> Result<()> {
+	let Some(unknown) = table.keys().find(|key| !FIELD_NAMES.contains(&key.as_str()))
+		else { return Ok(()) };
+	
+	let hint =
+		if let Some((_, renamed)) = RENAMED_FIELDS.iter().find(|(old, _)| old == unknown) {
+			format!("Help: `{unknown}` was renamed to `{renamed}`, rename it in your config file.")
+		} else if let Some(closest) = closest_field(unknown) {
+			format!("Help: Did you mean `{closest}`?")
+		} else {
+			format!("Help: Valid fields are: `{fields}`.", fields = FIELD_NAMES.join("`, `"))
+		};
+	
+	Err(anyhow!(
+		"Config file has an unknown field `{unknown}` in {location}.\n\n\
+		 {hint}"))
+}
+
+fn deserialize_table(toml_input: &str, table: toml::Table) -> Result<DeserializableConfig> {
+	toml::Value::Table(table)
+		.try_into()
+		.map_err(|err| invalid_format(toml_input, err))
+}
+
+/// A file without `[[project]]` tables declares a single project,
+/// otherwise each table is merged on top of the fields declared outside of them.
+fn deserialize(toml_input: &str) -> Result<Vec<DeserializableConfig>> {
+	let mut shared =
+		toml::from_str::<toml::Table>(toml_input)
+			.map_err(|err| invalid_format(toml_input, err))?;
+	
+	let projects =
+		match shared.remove("project") {
+			None => {
+				ensure_known_fields(&shared, "the top level")?;
+				return Ok(vec![deserialize_table(toml_input, shared)?]);
+			},
+			Some(toml::Value::Array(projects)) if !projects.is_empty() => projects,
+			Some(_) => {
+				return Err(anyhow!(
+					"Config file has a `project` field, but it is not a non-empty array of tables.\n\n\
+					 Help: Declare each project in its own table, like this:\n\
+					 [[project]]\n\
+					 name = \"main\"\n\
+					 destination_module_path = \"crate::dialogue\"\n\
+					 ..."));
+			},
+		};
+	
+	ensure_known_fields(&shared, "the top level")?;
+	
+	projects
+		.into_iter()
+		.enumerate()
+		.map(|(index, project)| {
+			let toml::Value::Table(project) = project
+				else {
+					return Err(anyhow!(
+						"Config file's `project` number {number} is not a table.\n\n\
+						 Help: Declare each project with `[[project]]`.", number = index + 1));
+				};
+			
+			ensure_known_fields(&project, &format!("`project` number {number}", number = index + 1))?;
+			
+			let mut merged = shared.clone();
+			
+			for (key, value) in project {
+				// Tables like `lints` are merged too, so a project can override a single rule.
+				match (merged.get_mut(&key), value) {
+					(Some(toml::Value::Table(shared_table)), toml::Value::Table(table)) => {
+						shared_table.extend(table);
+					},
+					(_, value) => {
+						merged.insert(key, value);
+					},
+				}
+			}
+			
+			deserialize_table(toml_input, merged)
+				.map_err(|err| anyhow!("In `project` number {number}:\n{err}", number = index + 1))
+		}).try_collect()
+}
+
+/// Two projects writing to the same folder would delete each other's files.
+fn ensure_unique_destinations(projects: &[YarnConfig]) -> Result<()> {
+	for (index, project) in projects.iter().enumerate() {
+		if let Some(other) = projects[..index].iter().find(|other| other.destination_os_path == project.destination_os_path) {
+			return Err(anyhow!(
+				"Projects `{other}` and `{this}` have the same destination folder.\n\
+				 Path: {path:?}\n\n\
+				 Help: Each project needs its own `destination_os_path`.",
+				other = other.name, this = project.name, path = project.destination_os_path));
+		}
+	}
+	
+	Ok(())
 }
 
 fn ensure_not_empty(input: &DeserializableConfig) -> Result<(String, String)> {
@@ -126,9 +330,7 @@ fn ensure_not_empty(input: &DeserializableConfig) -> Result<(String, String)> {
 	ensure_not_empty!(input.storage_type_name, "storage_type_name", "\"MyVariablesStorage\"");
 	ensure_not_empty!(input.command_module_path, "command_module_path", "\"crate::yarn_commands\"");
 	ensure_not_empty!(input.command_type_name, "command_type_name", "\"MyYarnCommand\"");
-	ensure_not_empty!(input.destination_os_path, "destination_os_path", "\"/src/dialogues/nodes\"");
 	ensure_not_empty!(input.destination_module_path, "destination_module_path", "\"crate::dialogues::nodes\"");
-	ensure_not_empty!(input.yarn_root_folder, "yarn_root_folder", "\"../yarn_scripts\"");
 
 	match (input.storage_module_path.is_empty() || input.vars_module_path.is_empty(), input.generate_storage) {
 		(true, true) => {
@@ -155,7 +357,7 @@ fn ensure_not_empty(input: &DeserializableConfig) -> Result<(String, String)> {
 			Err(anyhow!(
 				"Config file has `generate_storage` set to `false`, but either `storage_module_path` or `vars_module_path` is empty.\n\
 				 Help: If `generate_storage` is false. Then:\n\
-				  - `storage_path` should be the path to the storage type's module(without the type)\
+				  - `storage_module_path` should be the path to the storage type's module(without the type)\n\
 				  - `vars_module_path` should be the path to the module that contains the structs that implement `IVar`."))
 		},
 		(false, true) => {
@@ -167,11 +369,143 @@ fn ensure_not_empty(input: &DeserializableConfig) -> Result<(String, String)> {
 	}
 }
 
+fn resolve_path(
+	field_name: &str,
+	from_file: &str,
+	from_args: Option<&PathBuf>,
+	config_dir: &Path,
+	example: &str,
+) -> Result<PathBuf> {
+	if let Some(path) = from_args {
+		return Ok(path.clone());
+	}
+
+	if from_file.is_empty() {
+		return Err(anyhow!(
+			"Config file is missing `{field_name}` field.\n\n\
+			 Help: You can declare the field like this:\n\
+			 {field_name} = {example}"));
+	}
+
+	let path =
+		PathBuf::from_str(from_file)
+			.map_err(|err| anyhow!(
+				"Could not parse `{field_name}` into `PathBuf`.\n\
+				 Path: `{from_file}`\n\
+				 Error: `{err}`\n\n\
+				 Help: Ensure the `{field_name}` is a valid OS file-system path. (like {example})")
+			)?;
+
+	if path.is_relative() {
+		Ok(config_dir.join(path))
+	} else {
+		Ok(path)
+	}
+}
+
+const DEFAULT_INCLUDE_PATTERN: &str = "**/*.yarn";
+const DEFAULT_MAX_ERRORS: usize = 50;
+
+fn parse_patterns(field_name: &str, patterns: &[String]) -> Result<Vec<Pattern>> {
+	patterns
+		.iter()
+		.map(|pattern|
+			Pattern::new(pattern)
+				.map_err(|err| anyhow!(
+					"Could not parse glob pattern in `{field_name}`.\n\
+					 Pattern: `{pattern}`\n\
+					 Error: `{err}`\n\n\
+					 Help: Patterns are relative to each root folder, like `\"chapters/**/*.yarn\"`.\n\
+					 Help: `*` matches any characters except `/`, `**` matches any number of folders.")))
+		.try_collect()
+}
+
+/// `yarn_root_folder` and `yarn_root_folders` are merged, at least one root must be provided.
+fn resolve_roots(toml: &DeserializableConfig, from_args: Option<&PathBuf>, config_dir: &Path) -> Result<Vec<PathBuf>> {
+	if let Some(path) = from_args {
+		return Ok(vec![path.clone()]);
+	}
+	
+	let mut roots = vec![];
+	
+	if !toml.yarn_root_folder.is_empty() || toml.yarn_root_folders.is_empty() {
+		roots.push(resolve_path("yarn_root_folder", &toml.yarn_root_folder, None, config_dir, "\"../yarn_scripts\"")?);
+	}
+	
+	for root in &toml.yarn_root_folders {
+		roots.push(resolve_path("yarn_root_folders", root, None, config_dir, "[\"../yarn_scripts\"]")?);
+	}
+	
+	Ok(roots)
+}
+
 impl YarnConfig {
-	pub fn parse_file() -> Result<YarnConfig> {
-		let toml_input = read_file()?;
-		let toml = deserialize(toml_input)?;
+	/// Reads the config file at `config_path`, which must declare a single project, 
+	/// then applies the `overrides` on top of it.
+	///
+	/// Relative paths declared in the file are resolved relative to the file's parent folder.
+	pub fn parse_file(config_path: &Path, overrides: &ConfigOverrides) -> Result<YarnConfig> {
+		let mut projects = YarnConfig::parse_projects(config_path, overrides, None)?;
+		
+		if projects.len() > 1 {
+			return Err(anyhow!(
+				"Config file declares {count} projects, but only one was expected.\n\
+				 Path: {config_path:?}\n\n\
+				 Help: Use `YarnConfig::parse_projects` to read all of them.", count = projects.len()));
+		}
+		
+		Ok(projects.remove(0))
+	}
+	
+	/// Reads the projects declared in the config file at `config_path`, see [YarnConfig::parse_file].
+	///
+	/// If `selected` is provided, only the project with that name is returned.
+	/// `overrides` only make sense for a single project, so they are rejected when several would be returned.
+	pub fn parse_projects(config_path: &Path, overrides: &ConfigOverrides, selected: Option<&str>) -> Result<Vec<YarnConfig>> {
+		let toml_input = read_file(config_path)?;
+		let mut tables = deserialize(&toml_input)?;
+		let declared_count = tables.len();
+		
+		if let Some(selected) = selected {
+			tables.retain(|toml| toml.project_name() == selected);
+			
+			if tables.is_empty() {
+				return Err(anyhow!(
+					"Config file does not declare a project named `{selected}`.\n\
+					 Path: {config_path:?}\n\n\
+					 Help: A project's name is its `name` field, or its `destination_module_path` if it has no name."));
+			}
+		}
 		
+		if tables.len() > 1 && overrides != &ConfigOverrides::default() {
+			return Err(anyhow!(
+				"Config file declares {count} projects, paths cannot be overridden for all of them at once.\n\
+				 Path: {config_path:?}\n\n\
+				 Help: Select a single project before overriding its paths.", count = tables.len()));
+		}
+		
+		let projects =
+			tables
+				.into_iter()
+				.map(|toml| {
+					let name = toml.project_name().to_string();
+					
+					YarnConfig::from_fields(toml, config_path, overrides)
+						.map_err(|err| if declared_count > 1 {
+							anyhow!("In project `{name}`:\n{err}")
+						} else {
+							err
+						})
+				}).try_collect::<Vec<_>>()?;
+		
+		ensure_unique_destinations(&projects)?;
+		Ok(projects)
+	}
+	
+	/// Validates `toml` and resolves its paths, without touching the file-system.
+	///
+	/// `config_path` does not need to exist, it is only used to resolve relative paths.
+	pub fn from_fields(toml: DeserializableConfig, config_path: &Path, overrides: &ConfigOverrides) -> Result<YarnConfig> {
 		let (storage_qualified, vars_qualified) =
 			ensure_not_empty(&toml)?;
 		
@@ -183,43 +517,42 @@ impl YarnConfig {
 			format!("{mod_path}::shared_internal",
 				mod_path = toml.destination_module_path);
 		
+		let config_dir =
+			config_path
+				.parent()
+				.unwrap_or_else(|| Path::new(""));
+		
 		let destination_os_path =
-			PathBuf::from_str(&toml.destination_os_path)
-				.map_err(|err| anyhow!(
-					"Could not parse `destination_os_path` into `PathBuf`.\n\
-					 Path: `{}`\n\
-					 Error: {err}\n\n\
-					 Help: Ensure the `destination_os_path` is a valid OS file-system path. (like `src/dialogues/nodes/`)"
-					, toml.destination_os_path)
-				)?;
-		
-		std::fs::create_dir_all(&destination_os_path)
-			.map_err(|err| anyhow!(
-				"Directory `destination_os_path` does not exist or could not be created.\n\
-				 Path: {destination_os_path:?}\n\
-				 Error: {err}\n\n\
-				 Help: This program might need additional permissions to access or create the directory.\n\
-				 Help: Try running the program as an administrator.")
-			)?;
+			resolve_path("destination_os_path", &toml.destination_os_path, overrides.destination_os_path.as_ref(),
+				config_dir, "\"src/dialogues/nodes\"")?;
 		
-		let yarn_root_folder =
-			PathBuf::from_str(&toml.yarn_root_folder)
-				.map_err(|err| anyhow!(
-					"Could not parse `yarn_root_folder` into `PathBuf`.\n\
-					 Path: `{}`\n\
-					 Error: `{err}`\n\n\
-					 Help: Ensure the `yarn_root_folder` is a valid OS file-system path. (like \"/../yarn_scripts\".)"
-					, toml.yarn_root_folder)
-				)?;
+		let yarn_root_folders =
+			resolve_roots(&toml, overrides.yarn_root_folder.as_ref(), config_dir)?;
+		
+		let include_patterns =
+			if toml.include.is_empty() {
+				vec![Pattern::new(DEFAULT_INCLUDE_PATTERN).expect("default pattern is valid")]
+			} else {
+				parse_patterns("include", &toml.include)?
+			};
+		
+		let exclude_patterns =
+			parse_patterns("exclude", &toml.exclude)?;
 
 		let exclude_yarn_folders =
-			toml.exclude_yarn_folders
-			    .iter()
-			    .map(|folder| 
-				    yarn_root_folder.join(folder))
-			    .collect();
+			yarn_root_folders
+				.iter()
+				.flat_map(|root| 
+					toml.exclude_yarn_folders
+					    .iter()
+					    .map(|folder| root.join(folder)))
+				.collect();
+		
+		let name = toml.project_name().to_string();
 		
 		Ok(YarnConfig {
+			name,
+			config_path: config_path.to_path_buf(),
 			storage_qualified,
 			storage_direct: toml.storage_type_name,
 			command_qualified,
@@ -229,8 +562,103 @@ impl YarnConfig {
 			allow_overwrite: toml.allow_overwrite,
 			generate_storage: toml.generate_storage,
 			destination_os_path,
-			yarn_root_folder,
+			yarn_root_folders,
+			include_patterns,
+			exclude_patterns,
 			exclude_yarn_folders,
+			max_errors: toml.max_errors.unwrap_or(DEFAULT_MAX_ERRORS),
+			lints: toml.lints,
+			line_group_selection: toml.line_group_selection,
+			node_group_saliency: toml.node_group_saliency,
 		})
 	}
-}
\ No newline at end of file
+
+	/// `file` relative to the config file's folder, with `/` separators, so generated code is the same on every machine.
+	pub fn source_path(&self, file: &Path) -> String {
+		let config_dir =
+			self.config_path
+			    .parent()
+			    .unwrap_or_else(|| Path::new(""));
+
+		file.strip_prefix(config_dir)
+		    .unwrap_or(file)
+		    .to_string_lossy()
+		    .replace('\\', "/")
+	}
+
+	/// The default config, declared at `yarn_project.toml`.
+	#[cfg(test)]
+	pub(crate) fn for_tests() -> YarnConfig {
+		let toml = deserialize(DEFAULT_CONFIG).unwrap().remove(0);
+		YarnConfig::from_fields(toml, Path::new("yarn_project.toml"), &ConfigOverrides::default()).unwrap()
+	}
+}
+
+#[test]
+fn test_deserialize_projects() {
+	use crate::lint::LintLevel;
+	use pretty_assertions::assert_eq;
+	
+	const SHARED: &str =
+		"storage_module_path = \"\"\n\
+		 storage_type_name = \"Storage\"\n\
+		 command_module_path = \"crate::cmd\"\n\
+		 command_type_name = \"Cmd\"\n\
+		 vars_module_path = \"\"\n\
+		 allow_overwrite = true\n\
+		 generate_storage = true\n\
+		 destination_module_path = \"crate::dialogue\"\n";
+	
+	let single = deserialize(SHARED).unwrap();
+	assert_eq!(single.len(), 1);
+	assert_eq!(single[0].project_name(), "crate::dialogue");
+	
+	let projects = deserialize(&format!(
+		"{SHARED}\
+		 [[project]]\n\
+		 name = \"main\"\n\
+		 [[project]]\n\
+		 command_type_name = \"DlcCmd\"\n\
+		 destination_module_path = \"dlc::dialogue\"\n")).unwrap();
+	
+	let names = projects.iter().map(DeserializableConfig::project_name).collect::<Vec<_>>();
+	assert_eq!(names, ["main", "dlc::dialogue"]);
+	assert_eq!(projects[0].command_type_name, "Cmd");
+	assert_eq!(projects[1].command_type_name, "DlcCmd");
+	
+	let projects = deserialize(&format!(
+		"{SHARED}\
+		 [lints]\n\
+		 unreachable_node = \"deny\"\n\
+		 [[project]]\n\
+		 name = \"main\"\n\
+		 [[project]]\n\
+		 name = \"dlc\"\n\
+		 [project.lints]\n\
+		 unused_variable = \"allow\"\n")).unwrap();
+	
+	assert_eq!((projects[0].lints.unreachable_node, projects[0].lints.unused_variable), (LintLevel::Deny, LintLevel::Warn));
+	assert_eq!((projects[1].lints.unreachable_node, projects[1].lints.unused_variable), (LintLevel::Deny, LintLevel::Allow));
+	
+	assert!(deserialize("project = 5").is_err());
+	assert!(deserialize("project = []").is_err());
+}
+
+#[test]
+fn test_unknown_fields() {
+	let error = |toml_input: &str| deserialize(toml_input).unwrap_err().to_string();
+	
+	assert!(error("storage_path = \"\"").contains("renamed to `storage_module_path`"));
+	assert!(error("comand_type_name = \"\"").contains("Did you mean `command_type_name`?"));
+	assert!(error("something_else = 1").contains("Valid fields are:"));
+	assert!(error("[[project]]\ndestination = \"\"").contains("`project` number 1"));
+	
+	let default = deserialize(DEFAULT_CONFIG).unwrap();
+	assert_eq!(default.len(), 1);
+	
+	let config = YarnConfig::from_fields(default[0].clone(), Path::new("game/yarn_project.toml"), &ConfigOverrides::default()).unwrap();
+	assert_eq!(config.destination_os_path, Path::new("game/src/dialogue"));
+	assert_eq!(config.source_path(Path::new("game/yarn/intro.yarn")), "yarn/intro.yarn");
+	assert_eq!(config.line_group_selection, LineGroupSelection::Random);
+	assert_eq!(config.node_group_saliency, NodeGroupSaliency::First);
+}
@@ -0,0 +1,479 @@
+use crate::{LineNumber, UnparsedLine};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+	Error,
+	Warning,
+}
+
+impl Display for Severity {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Severity::Error => write!(f, "error"),
+			Severity::Warning => write!(f, "warning"),
+		}
+	}
+}
+
+macro_rules! error_codes {
+	($($(#[$doc: meta])* $name: ident = $code: literal,)*) => {
+		/// Identifies each kind of diagnostic, so tooling can filter or suppress specific ones.
+		///
+		/// Codes are stable: once released, a code is never renumbered nor reused for another kind of error.
+		#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+		pub enum ErrorCode {
+			$($(#[$doc])* $name,)*
+		}
+
+		impl ErrorCode {
+			pub const ALL: &'static [ErrorCode] = &[$(ErrorCode::$name,)*];
+
+			pub fn as_str(self) -> &'static str {
+				match self {
+					$(ErrorCode::$name => $code,)*
+				}
+			}
+		}
+	};
+}
+
+error_codes! {
+	/// A line looked like a known syntax, but could not be parsed as it.
+	UnparsableLine = "YS0001",
+	/// A line did not match any known syntax.
+	UnknownSyntax = "YS0002",
+	InvalidVarDeclaration = "YS0003",
+	MissingTitleName = "YS0004",
+	InvalidTracking = "YS0005",
+	DuplicateTitle = "YS0006",
+	DuplicateTracking = "YS0007",
+	MissingTitle = "YS0008",
+	InvalidTitle = "YS0009",
+	/// `===` found before `---`.
+	MisplacedEndDelimiter = "YS0010",
+	/// `---` found before `===`.
+	MisplacedStartDelimiter = "YS0011",
+	OrphanLines = "YS0012",
+	UnterminatedNode = "YS0013",
+	OrphanEndOptions = "YS0014",
+	/// `<<elseif>>`, `<<else>>` or `<<endif>>` without a matching `<<if>>`.
+	OrphanFork = "YS0015",
+	IndentationMismatch = "YS0016",
+	DuplicateElse = "YS0017",
+	UnclosedBranch = "YS0018",
+	UnexpectedLineInBranch = "YS0019",
+	DuplicateLineId = "YS0020",
+	UnknownJumpTarget = "YS0021",
+	/// A node mentioned in `visited` or `visited_count` does not exist.
+	UnknownVisitedNode = "YS0022",
+	DuplicateVarDeclaration = "YS0023",
+	/// Reading, creating or writing a file in the destination folder failed.
+	OutputIo = "YS0024",
+	OverwriteNotAllowed = "YS0025",
+	RenderFailed = "YS0026",
+	/// The generated files could not be nested into a single module, for `include_yarn!`.
+	InlineFailed = "YS0027",
+	/// `--check` found generated files that are out of date.
+	OutdatedOutput = "YS0028",
+	/// An expression's type does not fit the operator, function, condition or variable it's used with.
+	TypeMismatch = "YS0029",
+	/// An undeclared variable is used with types that cannot be unified.
+	ConflictingVarTypes = "YS0030",
+	/// Lint: a line after an unconditional `<<jump>>`, `<<return>>` or `<<stop>>`.
+	UnreachableCode = "YS0031",
+	/// Lint: a declared variable that is never read nor written.
+	UnusedVariable = "YS0032",
+	/// Lint: a variable that is written by `<<set>>` but never read.
+	WriteOnlyVariable = "YS0033",
+	/// Lint: a node that no `<<jump>>` reaches and that is not tagged as an entry point.
+	UnreachableNode = "YS0034",
+	/// Lint: an option's `<<if>>` condition that does not depend on any variable or function.
+	ConstantCondition = "YS0035",
+	/// Two nodes have the same title, possibly in different files.
+	DuplicateNodeTitle = "YS0036",
+	/// `fmt --check` found yarn files that are not formatted.
+	UnformattedYarnFile = "YS0037",
+	/// A `when:` header that is empty or whose condition could not be parsed.
+	InvalidWhen = "YS0038",
+}
+
+impl Display for ErrorCode {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
+
+/// A location inside a yarn file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Span {
+	pub file: PathBuf,
+	pub line: LineNumber,
+	/// Char range inside the line, `None` covers the whole line except its indentation.
+	pub columns: Option<Range<usize>>,
+}
+
+impl Span {
+	pub fn line(file: &Path, line: LineNumber) -> Span {
+		Span {
+			file: file.to_path_buf(),
+			line,
+			columns: None,
+		}
+	}
+
+	pub fn with_columns(mut self, columns: Range<usize>) -> Span {
+		self.columns = Some(columns);
+		self
+	}
+}
+
+impl Display for Span {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		let column = self.columns.as_ref().map_or(1, |columns| columns.start + 1);
+		write!(f, "{}:{}:{column}", self.file.display(), self.line)
+	}
+}
+
+impl UnparsedLine {
+	pub fn span(&self, file: &Path) -> Span {
+		Span::line(file, self.line_number)
+	}
+}
+
+/// An error or warning pointing at a line of a yarn file, rendered like `rustc` does.
+///
+/// Implements [std::error::Error], so it can be returned through [anyhow::Result] and recovered with `downcast`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	/// `None` for errors that were not classified, like the ones coming from outside the parser.
+	pub code: Option<ErrorCode>,
+	pub message: String,
+	pub span: Option<Span>,
+	/// The text of the line at `span`, read from the file when rendering if not provided.
+	pub source_line: Option<String>,
+	pub notes: Vec<String>,
+	pub help: Vec<String>,
+}
+
+impl Diagnostic {
+	pub fn error(code: ErrorCode, message: impl Into<String>) -> Diagnostic {
+		Diagnostic {
+			code: Some(code),
+			..Diagnostic::uncoded(message)
+		}
+	}
+
+	pub fn warning(code: ErrorCode, message: impl Into<String>) -> Diagnostic {
+		Diagnostic {
+			severity: Severity::Warning,
+			..Diagnostic::error(code, message)
+		}
+	}
+
+	fn uncoded(message: impl Into<String>) -> Diagnostic {
+		Diagnostic {
+			severity: Severity::Error,
+			code: None,
+			message: message.into(),
+			span: None,
+			source_line: None,
+			notes: vec![],
+			help: vec![],
+		}
+	}
+
+	pub fn with_span(mut self, span: Span) -> Diagnostic {
+		self.span = Some(span);
+		self
+	}
+
+	/// Sets both the span and the source line, sparing a read from the file-system when rendering.
+	pub(crate) fn at_line(self, file: &Path, line: &UnparsedLine) -> Diagnostic {
+		self.with_span(line.span(file))
+		    .with_source_line(&line.text)
+	}
+
+	pub fn with_source_line(mut self, text: impl Into<String>) -> Diagnostic {
+		self.source_line = Some(text.into());
+		self
+	}
+
+	pub fn with_note(mut self, note: impl Into<String>) -> Diagnostic {
+		self.notes.push(note.into());
+		self
+	}
+
+	pub fn with_help(mut self, help: impl Into<String>) -> Diagnostic {
+		self.help.push(help.into());
+		self
+	}
+
+	fn read_source_line(span: &Span) -> Option<String> {
+		std::fs::read_to_string(&span.file)
+			.ok()?
+			.lines()
+			.nth(span.line.checked_sub(1)?)
+			.map(str::to_string)
+	}
+
+	/// Renders the diagnostic with the offending line and a caret underline, like:
+	/// ```text
+	/// error[YS0021]: Node `Ending` mentioned in `jump` does not exist.
+	///   --> dialogue/intro.yarn:12:5
+	///    |
+	/// 12 |     <<jump Ending>>
+	///    |     ^^^^^^^^^^^^^^^
+	///    = help: Make sure that the node name is correct.
+	/// ```
+	pub fn render(&self) -> String {
+		let mut output =
+			match self.code {
+				Some(code) => format!("{}[{code}]: {}", self.severity, self.message),
+				None => format!("{}: {}", self.severity, self.message),
+			};
+
+		let Some(span) = &self.span
+			else { return self.render_notes(output, "") };
+
+		let gutter = " ".repeat(span.line.to_string().len());
+
+		let Some(text) = self.resolve_source_line()
+			else {
+				output.push_str(&format!("\n{gutter}--> {span}"));
+				return self.render_notes(output, &gutter);
+			};
+
+		let columns = Self::resolve_columns(span, &text);
+
+		// Keeps tabs so the caret stays aligned regardless of the terminal's tab width.
+		let padding =
+			text.chars()
+			    .take(columns.start)
+			    .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+			    .collect::<String>();
+
+		let carets = "^".repeat(columns.len().max(1));
+
+		output.push_str(&format!(
+			"\n{gutter}--> {file}:{line}:{column}\n\
+			 {gutter} |\n\
+			 {line} | {text}\n\
+			 {gutter} | {padding}{carets}",
+			file = span.file.display(), line = span.line, column = columns.start + 1));
+
+		self.render_notes(output, &gutter)
+	}
+
+	fn resolve_source_line(&self) -> Option<String> {
+		self.source_line
+		    .clone()
+		    .or_else(|| Self::read_source_line(self.span.as_ref()?))
+	}
+
+	pub(crate) fn resolve_columns(span: &Span, text: &str) -> Range<usize> {
+		span.columns
+		    .clone()
+		    .unwrap_or_else(|| {
+			    let indent = text.chars().take_while(|ch| ch.is_whitespace()).count();
+			    indent..text.trim_end().chars().count()
+		    })
+	}
+
+	/// One line of JSON, for `--message-format=json`.
+	///
+	/// Lines and columns are 1-based, `end_column` is exclusive.
+	/// Location fields are `null` when the diagnostic doesn't point at a yarn file.
+	pub fn to_json(&self) -> String {
+		let columns =
+			self.span
+			    .as_ref()
+			    .and_then(|span| {
+				    span.columns
+				        .clone()
+				        .or_else(|| Some(Self::resolve_columns(span, &self.resolve_source_line()?)))
+			    });
+
+		serde_json::json!({
+			"severity": self.severity.to_string(),
+			"code": self.code.map(ErrorCode::as_str),
+			"message": self.message,
+			"file": self.span.as_ref().map(|span| span.file.to_string_lossy()),
+			"line": self.span.as_ref().map(|span| span.line),
+			"column": columns.as_ref().map(|columns| columns.start + 1),
+			"end_column": columns.as_ref().map(|columns| columns.end + 1),
+			"notes": self.notes,
+			"help": self.help,
+		}).to_string()
+	}
+
+	fn render_notes(&self, mut output: String, gutter: &str) -> String {
+		for note in &self.notes {
+			output.push_str(&format!("\n{gutter} = note: {note}"));
+		}
+
+		for help in &self.help {
+			output.push_str(&format!("\n{gutter} = help: {help}"));
+		}
+
+		output
+	}
+}
+
+impl Display for Diagnostic {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.render())
+	}
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Several diagnostics reported at once, rendered one after the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostics {
+	pub list: Vec<Diagnostic>,
+	/// How many diagnostics were left out of `list`, because there were more than `max_errors`.
+	pub omitted: usize,
+}
+
+impl Diagnostics {
+	pub fn new(list: Vec<Diagnostic>) -> Diagnostics {
+		Diagnostics { list, omitted: 0 }
+	}
+
+	/// Keeps only the first `max_errors` diagnostics, `0` means no limit.
+	pub fn limited(mut list: Vec<Diagnostic>, max_errors: usize) -> Diagnostics {
+		let omitted =
+			if max_errors > 0 && list.len() > max_errors {
+				list.split_off(max_errors).len()
+			} else {
+				0
+			};
+
+		Diagnostics { list, omitted }
+	}
+
+	pub fn total(&self) -> usize {
+		self.list.len() + self.omitted
+	}
+}
+
+impl Display for Diagnostics {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		let mut rendered =
+			self.list
+			    .iter()
+			    .map(Diagnostic::render)
+			    .collect::<Vec<_>>();
+
+		if self.omitted > 0 {
+			rendered.push(format!(
+				"error: {omitted} more errors were not shown.\n \
+				 = help: Increase `max_errors` in the config file to see them.", omitted = self.omitted));
+		}
+
+		if self.total() > 1 {
+			rendered.push(format!("error: aborting due to {} previous errors", self.total()));
+		}
+
+		write!(f, "{}", rendered.join("\n\n"))
+	}
+}
+
+impl std::error::Error for Diagnostics {}
+
+/// Unwraps [Diagnostic] and [Diagnostics] errors, any other error becomes a diagnostic without a span.
+pub fn into_diagnostics(err: anyhow::Error) -> Vec<Diagnostic> {
+	match err.downcast::<Diagnostic>() {
+		Ok(diagnostic) => vec![diagnostic],
+		Err(err) =>
+			match err.downcast::<Diagnostics>() {
+				Ok(diagnostics) => diagnostics.list,
+				Err(err) => vec![Diagnostic::uncoded(err.to_string())],
+			},
+	}
+}
+
+/// Returns true if `err` already points at the offending line, so there's no need to add context to it.
+pub fn is_located(err: &anyhow::Error) -> bool {
+	err.is::<Diagnostic>() || err.is::<Diagnostics>()
+}
+
+/// Like [Result::map_err], but leaves errors that are [located](is_located) untouched.
+pub trait MapUnlocatedErr<T> {
+	fn map_unlocated_err(self, map: impl FnOnce(anyhow::Error) -> anyhow::Error) -> anyhow::Result<T>;
+}
+
+impl<T> MapUnlocatedErr<T> for anyhow::Result<T> {
+	fn map_unlocated_err(self, map: impl FnOnce(anyhow::Error) -> anyhow::Error) -> anyhow::Result<T> {
+		self.map_err(|err| if is_located(&err) { err } else { map(err) })
+	}
+}
+
+#[test]
+fn test_render() {
+	use pretty_assertions::assert_eq;
+
+	let diagnostic =
+		Diagnostic::error(ErrorCode::UnknownJumpTarget, "Node `Ending` mentioned in `jump` does not exist.")
+			.with_span(Span::line(Path::new("intro.yarn"), 12))
+			.with_source_line("\t<<jump Ending>> ")
+			.with_help("Make sure that the node name is correct.");
+
+	assert_eq!(diagnostic.render(),
+		"error[YS0021]: Node `Ending` mentioned in `jump` does not exist.\n\
+		 \x20 --> intro.yarn:12:2\n\
+		 \x20  |\n\
+		 12 | \t<<jump Ending>> \n\
+		 \x20  | \t^^^^^^^^^^^^^^^\n\
+		 \x20  = help: Make sure that the node name is correct.");
+
+	let diagnostic =
+		Diagnostic::error(ErrorCode::InvalidTitle, "Invalid character `-` in `node title`.")
+			.with_span(Span::line(Path::new("intro.yarn"), 3).with_columns(10..11))
+			.with_source_line("title: Ch-01");
+
+	assert_eq!(diagnostic.render(),
+		"error[YS0009]: Invalid character `-` in `node title`.\n\
+		 \x20--> intro.yarn:3:11\n\
+		 \x20 |\n\
+		 3 | title: Ch-01\n\
+		 \x20 |           ^");
+}
+
+#[test]
+fn test_json() {
+	use pretty_assertions::assert_eq;
+
+	let diagnostic =
+		Diagnostic::error(ErrorCode::InvalidTitle, "Invalid character `-` in `node title`.")
+			.with_span(Span::line(Path::new("intro.yarn"), 3).with_columns(10..11))
+			.with_source_line("title: Ch-01")
+			.with_help("Titles can only contain letters, digits and underscores('_').");
+
+	assert_eq!(diagnostic.to_json(),
+		r#"{"code":"YS0009","column":11,"end_column":12,"file":"intro.yarn","help":["Titles can only contain letters, digits and underscores('_')."],"line":3,"message":"Invalid character `-` in `node title`.","notes":[],"severity":"error"}"#);
+
+	let diagnostic =
+		Diagnostic::error(ErrorCode::UnknownJumpTarget, "Node `Ending` mentioned in `jump` does not exist.")
+			.with_span(Span::line(Path::new("intro.yarn"), 12))
+			.with_source_line("\t<<jump Ending>> ");
+
+	let json: serde_json::Value = serde_json::from_str(&diagnostic.to_json()).unwrap();
+	assert_eq!((&json["column"], &json["end_column"]), (&serde_json::json!(2), &serde_json::json!(17)));
+
+	let json: serde_json::Value = serde_json::from_str(&Diagnostic::uncoded("Could not read file.").to_json()).unwrap();
+	assert_eq!((&json["code"], &json["file"], &json["line"]), (&serde_json::Value::Null, &serde_json::Value::Null, &serde_json::Value::Null));
+}
+
+#[test]
+fn test_codes_are_unique() {
+	let mut seen = std::collections::HashSet::new();
+
+	for code in ErrorCode::ALL {
+		assert!(seen.insert(code.as_str()), "Code `{code}` is used by more than one `ErrorCode`.");
+	}
+}
@@ -3,8 +3,9 @@ use genco::lang::Rust;
 use genco::{quote_in, Tokens};
 use crate::expressions::yarn_expr::YarnExpr;
 use crate::quoting::quotable_types::enums::enum_type_title;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BuiltInFunctionCall {
 	Visited(String),
 	VisitedCount(String),
@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use proc_macro2::{Ident, Punct, Spacing, Span};
+use proc_macro2::{Delimiter, Group, Ident, Punct, Spacing, Span};
 use proc_macro2::{TokenStream, TokenTree};
 use quote::{ToTokens, TokenStreamExt};
 use std::fmt::{Debug, Formatter};
@@ -206,14 +206,9 @@ impl CustomExpr {
 				TokenTree::Punct(punct) if punct.as_char() == '$' => {
 					match iterator.next() {
 						Some(TokenTree::Ident(ident)) => {
+							// Becomes `get_var(ident)`, which `YarnExpr::parse_call` turns into `YarnExpr::GetVar`.
 							result.append(TokenTree::Ident(Ident::new("get_var", Span::call_site())));
-							result.append(TokenTree::Punct(Punct::new(':', Spacing::Joint)));
-							result.append(TokenTree::Punct(Punct::new(':', Spacing::Alone)));
-							result.append(TokenTree::Punct(Punct::new('<', Spacing::Alone)));
-							result.append(TokenTree::Ident(ident));
-							result.append(TokenTree::Punct(Punct::new('>', Spacing::Alone)));
-							result.append(TokenTree::Punct(Punct::new('(', Spacing::Alone)));
-							result.append(TokenTree::Punct(Punct::new(')', Spacing::Alone)));
+							result.append(TokenTree::Group(Group::new(Delimiter::Parenthesis, TokenStream::from(TokenTree::Ident(ident)))));
 						}
 						invalid => {
 							return Err(anyhow!(
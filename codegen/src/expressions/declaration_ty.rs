@@ -1,9 +1,10 @@
 use genco::prelude::FormatInto;
 use genco::lang::Rust;
 use genco::Tokens;
+use serde::{Deserialize, Serialize};
 
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum DeclarationTy {
 	String,
 	bool,
@@ -101,3 +102,10 @@ impl FormatInto<Rust> for &DeclarationTy {
 		}
 	}
 }
+
+impl std::fmt::Display for DeclarationTy {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		// Variants are named after the Rust types they generate.
+		write!(f, "{self:?}")
+	}
+}
@@ -6,8 +6,9 @@ use anyhow::{anyhow, Result};
 use crate::expressions::built_in_calls::BuiltInFunctionCall;
 use crate::expressions::declaration_ty::DeclarationTy;
 use crate::quoting::util::SeparatedItems;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum YarnExpr {
 	Lit(YarnLit),
 	GetVar(String),
@@ -3,8 +3,9 @@ use anyhow::{Result, anyhow};
 use genco::lang::Rust;
 use genco::prelude::{FormatInto, quoted};
 use genco::{quote_in, Tokens};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum YarnLit {
 	Int(i64),
 	Float(f64),
@@ -3,16 +3,16 @@ use genco::lang::Rust;
 use genco::prelude::FormatInto;
 use genco::{quote_in, Tokens};
 use crate::expressions::{SynBinOp, SynUnaryOp};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum YarnUnaryOp {
 	Not,
 	Negate,
 }
 
 impl YarnUnaryOp {
-	#[allow(unused)]
-	pub(super) fn resolve(self) -> &'static str {
+	pub(crate) fn resolve(self) -> &'static str {
 		match self {
 			YarnUnaryOp::Not => "!",
 			YarnUnaryOp::Negate => "-",
@@ -46,7 +46,7 @@ impl FormatInto<Rust> for &YarnUnaryOp {
 
 
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum YarnBinaryOp {
 	Add, Sub,
 	Mul,
@@ -59,6 +59,27 @@ pub enum YarnBinaryOp {
 }
 
 impl YarnBinaryOp {
+	pub(crate) fn resolve(self) -> &'static str {
+		match self {
+			YarnBinaryOp::Add => "+",
+			YarnBinaryOp::Sub => "-",
+			YarnBinaryOp::Mul => "*",
+			YarnBinaryOp::Div => "/",
+			YarnBinaryOp::Rem => "%",
+			YarnBinaryOp::And => "&&",
+			YarnBinaryOp::Or => "||",
+			YarnBinaryOp::Eq => "==",
+			YarnBinaryOp::Ne => "!=",
+			YarnBinaryOp::Lt => "<",
+			YarnBinaryOp::Le => "<=",
+			YarnBinaryOp::Gt => ">",
+			YarnBinaryOp::Ge => ">=",
+			YarnBinaryOp::BitXor => "^",
+			YarnBinaryOp::BitAnd => "&",
+			YarnBinaryOp::BitOr => "|",
+		}
+	}
+
 	pub(super) fn try_from_syn(syn_op: SynBinOp) -> Result<Self> {
 		match syn_op {
 			SynBinOp::Add(_) => Ok(YarnBinaryOp::Add),
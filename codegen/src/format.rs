@@ -0,0 +1,433 @@
+use crate::diagnostic::Diagnostics;
+use crate::io::read::yarn_file_from_text;
+use crate::parsing::grouping::scope::{FlatLine, Flow, YarnScope};
+use crate::parsing::lexer::{comment_start, lex, scan_literals, Token};
+use crate::parsing::util::indent_level;
+use crate::parsing::{parse_nodes, YarnNode};
+use crate::{Indent, LineNumber};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const INDENT: &str = "    ";
+
+/// A yarn file whose contents are not formatted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormattedFile {
+	pub path: PathBuf,
+	pub formatted: String,
+	/// Unified diff from the file on disk to the formatted contents.
+	pub diff: String,
+}
+
+impl FormattedFile {
+	/// Replaces the file on disk with the formatted contents, always encoded as UTF-8.
+	pub fn write(&self) -> Result<()> {
+		std::fs::write(&self.path, &self.formatted)
+			.map_err(|err| anyhow!(
+				"Could not write formatted yarn file.\n\
+				 Path: {path:?}\n\
+				 Error: `{err}`\n\n\
+				 Help: This program might need additional permissions to access this file.", path = self.path))
+	}
+}
+
+enum Line {
+	Blank,
+	Code {
+		depth: usize,
+		code: String,
+		tags: Option<String>,
+		comment: Option<String>,
+	},
+}
+
+fn push_scope_depths(scope: &YarnScope, depth: usize, depths: &mut HashMap<LineNumber, usize>) {
+	for flow in scope.flows() {
+		match flow {
+			Flow::Flat(flat_lines) => {
+				for flat in flat_lines {
+					let line_number =
+						match flat {
+							FlatLine::Speech(speech) => speech.line_number,
+							FlatLine::Command(command) => command.line_number,
+						};
+
+					depths.insert(line_number, depth);
+				}
+			},
+			Flow::OptionsFork(options_fork) => {
+				for (option, scope_option) in options_fork.iter_options() {
+					depths.insert(option.line_number, depth);
+
+					if let Some(scope) = scope_option {
+						push_scope_depths(scope, depth + 1, depths);
+					}
+				}
+			},
+			Flow::LineGroup(line_group) => {
+				for (group_line, scope_option) in line_group.iter_lines() {
+					depths.insert(group_line.speech.line_number, depth);
+
+					if let Some(scope) = scope_option {
+						push_scope_depths(scope, depth + 1, depths);
+					}
+				}
+			},
+			Flow::IfBranch(if_branch) => {
+				depths.insert(if_branch.if_.0.line_number, depth);
+
+				if let Some(if_scope) = &if_branch.if_.1 {
+					push_scope_depths(if_scope, depth + 1, depths);
+				}
+
+				for (else_if, scope_option) in &if_branch.else_ifs {
+					depths.insert(else_if.line_number, depth);
+
+					if let Some(else_if_scope) = scope_option {
+						push_scope_depths(else_if_scope, depth + 1, depths);
+					}
+				}
+
+				if let Some((else_, scope_option)) = &if_branch.else_ {
+					depths.insert(else_.line_number, depth);
+
+					if let Some(else_scope) = scope_option {
+						push_scope_depths(else_scope, depth + 1, depths);
+					}
+				}
+			},
+			Flow::OnceBranch(once_branch) => {
+				depths.insert(once_branch.once_.0.line_number, depth);
+
+				if let Some(once_scope) = &once_branch.once_.1 {
+					push_scope_depths(once_scope, depth + 1, depths);
+				}
+
+				if let Some((else_, scope_option)) = &once_branch.else_ {
+					depths.insert(else_.line_number, depth);
+
+					if let Some(else_scope) = scope_option {
+						push_scope_depths(else_scope, depth + 1, depths);
+					}
+				}
+			},
+		}
+	}
+}
+
+/// How many scopes deep each line of the nodes is, as understood by the parser.
+fn node_depths(nodes: &[YarnNode]) -> HashMap<LineNumber, usize> {
+	let mut depths = HashMap::new();
+
+	for node in nodes {
+		for scope in &node.contents {
+			push_scope_depths(scope, 0, &mut depths);
+		}
+	}
+
+	depths
+}
+
+fn find_outside_literals(code: &str, pattern: &str, from: usize) -> Option<usize> {
+	scan_literals(code)
+		.into_iter()
+		.find(|(index, _, in_literal)| *index >= from && !in_literal && code[*index..].starts_with(pattern))
+		.map(|(index, ..)| index)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+	let mut collapsed = String::new();
+
+	for (_, ch, in_literal) in scan_literals(text) {
+		if !in_literal && ch.is_whitespace() {
+			if !collapsed.ends_with(' ') {
+				collapsed.push(' ');
+			}
+		} else {
+			collapsed.push(ch);
+		}
+	}
+
+	collapsed.trim().to_string()
+}
+
+/// `<<  set $gold   to 5 >>` becomes `<<set $gold to 5>>`.
+fn normalize_commands(code: &str) -> String {
+	let mut normalized = String::new();
+	let mut index = 0;
+
+	while let Some(open) = find_outside_literals(code, "<<", index)
+		&& let Some(close) = find_outside_literals(code, ">>", open + 2) {
+		normalized.push_str(&code[index..open]);
+		normalized.push_str("<<");
+		normalized.push_str(&collapse_whitespace(&code[open + 2..close]));
+		normalized.push_str(">>");
+		index = close + 2;
+	}
+
+	normalized.push_str(&code[index..]);
+	normalized
+}
+
+/// Byte index of the first `#tag`, the same `#` the line parsers split the tags at.
+fn tags_start(code: &str) -> Option<usize> {
+	lex(code)
+		.map_while(Result::ok)
+		.find(|lexeme| matches!(lexeme.token, Token::Tags(_)))
+		.map(|lexeme| lexeme.span.start)
+}
+
+fn format_header_line(line: &str) -> String {
+	let Some((key, value)) = line.split_once(':')
+		else { return line.to_string() };
+
+	let value = value.trim();
+
+	let formatted =
+		match key.trim().to_lowercase().as_str() {
+			"title" => format!("title: {value}"),
+			"tracking" => format!("tracking: {}", value.to_lowercase()),
+			"when" => {
+				match value.to_lowercase().as_str() {
+					keyword @ ("always" | "once") => format!("when: {keyword}"),
+					_ => format!("when: {value}"),
+				}
+			},
+			"tags" => {
+				format!("tags: {}", value.split(',')
+				                         .map(str::trim)
+				                         .filter(|tag| !tag.is_empty())
+				                         .collect::<Vec<_>>()
+				                         .join(", "))
+			},
+			_ => return line.to_string(),
+		};
+
+	formatted.trim_end().to_string()
+}
+
+/// Splits `code` into the normalized line and its tags.
+fn format_body_line(code: &str) -> (String, Option<String>) {
+	let mut code = normalize_commands(code);
+
+	for arrow in ["->", "=>"] {
+		if let Some(line_text) = code.strip_prefix(arrow)
+			&& !line_text.trim().is_empty() {
+			code = format!("{arrow} {}", line_text.trim_start());
+		}
+	}
+
+	match tags_start(&code) {
+		Some(start) => (code[..start].trim_end().to_string(), Some(code[start..].trim_end().to_string())),
+		None => (code, None),
+	}
+}
+
+/// Lines are indented by their depth, consecutive lines with tags have them aligned,
+/// and runs of blank lines are collapsed into one.
+fn render(lines: &[Line], newline: &str) -> String {
+	let width = |depth: &usize, code: &String| depth * INDENT.len() + code.chars().count();
+	let mut tag_columns = vec![0; lines.len()];
+	let mut group_start = 0;
+
+	for index in 0..=lines.len() {
+		let has_tags = matches!(lines.get(index), Some(Line::Code { tags: Some(_), .. }));
+
+		if has_tags {
+			continue;
+		}
+
+		let column =
+			lines[group_start..index]
+				.iter()
+				.filter_map(|line| match line {
+					Line::Code { depth, code, .. } => Some(width(depth, code) + 1),
+					Line::Blank => None,
+				}).max()
+				.unwrap_or_default();
+
+		tag_columns[group_start..index].fill(column);
+		group_start = index + 1;
+	}
+
+	let mut rendered: Vec<String> = vec![];
+
+	for (line, tag_column) in lines.iter().zip(tag_columns) {
+		let Line::Code { depth, code, tags, comment } = line
+			else {
+				if rendered.last().is_some_and(|last| !last.is_empty()) {
+					rendered.push(String::new());
+				}
+
+				continue;
+			};
+
+		let mut text = INDENT.repeat(*depth);
+		text.push_str(code);
+
+		if let Some(tags) = tags {
+			let padding = tag_column - width(depth, code);
+			text.push_str(&" ".repeat(padding));
+			text.push_str(tags);
+		}
+
+		if let Some(comment) = comment {
+			if !code.is_empty() {
+				text.push(' ');
+			}
+
+			text.push_str(comment);
+		}
+
+		rendered.push(text);
+	}
+
+	while rendered.last().is_some_and(String::is_empty) {
+		rendered.pop();
+	}
+
+	let mut formatted = rendered.join(newline);
+	formatted.push_str(newline);
+	formatted
+}
+
+/// Formats the contents of a yarn file:
+/// - Lines inside options and `<<if>>` branches are indented by 4 spaces per level.
+/// - `title:`, `tags:`, `tracking:` and `when:` headers are normalized.
+/// - Extra spaces inside `<<commands>>` are removed.
+/// - `#tags` of consecutive lines are aligned.
+///
+/// Comments are kept, and formatting an already formatted file changes nothing.
+/// Files with errors are reported instead, since their structure is not known.
+pub fn format_yarn(path: &Path, text: &str) -> Result<String> {
+	let mut diagnostics = vec![];
+	let (nodes, _) = parse_nodes(yarn_file_from_text(path.to_path_buf(), text), &mut diagnostics);
+
+	if !diagnostics.is_empty() {
+		return Err(Diagnostics::limited(diagnostics, 0).into());
+	}
+
+	let depths = node_depths(&nodes);
+	let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+
+	let mut lines = vec![];
+	let mut in_body = false;
+	// `<<endif>>`, `<<endonce>>`, `<-` and `<<declare>>` lines are not part of the parsed scopes,
+	// they get the depth of the last line with the same indentation.
+	let mut depth_by_indent = HashMap::<Indent, usize>::new();
+	let mut last_depth = 0;
+	// Comment-only lines take the depth of the line after them.
+	let mut pending_comments = vec![];
+
+	for (index, source_line) in text.lines().enumerate() {
+		let line_number = index + 1;
+
+		let (code, comment) =
+			match comment_start(source_line) {
+				Some(start) => (&source_line[..start], Some(source_line[start..].trim_end().to_string())),
+				None => (source_line, None),
+			};
+
+		let trimmed = code.trim();
+
+		if trimmed.is_empty() && comment.is_none() {
+			lines.push(Line::Blank);
+			continue;
+		}
+
+		if trimmed == "---" || trimmed == "===" {
+			for pending in pending_comments.drain(..) {
+				if let Some(Line::Code { depth, .. }) = lines.get_mut(pending) {
+					*depth = last_depth;
+				}
+			}
+
+			in_body = trimmed == "---";
+			depth_by_indent.clear();
+			last_depth = 0;
+			lines.push(Line::Code { depth: 0, code: trimmed.to_string(), tags: None, comment });
+			continue;
+		}
+
+		if !in_body {
+			lines.push(Line::Code { depth: 0, code: format_header_line(trimmed), tags: None, comment });
+			continue;
+		}
+
+		if trimmed.is_empty() {
+			pending_comments.push(lines.len());
+			lines.push(Line::Code { depth: 0, code: String::new(), tags: None, comment });
+			continue;
+		}
+
+		let indent = indent_level(&code);
+		let depth =
+			depths.get(&line_number)
+			      .or_else(|| depth_by_indent.get(&indent))
+			      .copied()
+			      .unwrap_or(last_depth);
+
+		depth_by_indent.insert(indent, depth);
+		last_depth = depth;
+
+		for pending in pending_comments.drain(..) {
+			if let Some(Line::Code { depth: comment_depth, .. }) = lines.get_mut(pending) {
+				*comment_depth = depth;
+			}
+		}
+
+		let (code, tags) = format_body_line(trimmed);
+		lines.push(Line::Code { depth, code, tags, comment });
+	}
+
+	Ok(render(&lines, newline))
+}
+
+#[test]
+fn test_format_yarn() {
+	use pretty_assertions::assert_eq;
+
+	let input =
+		"Title:Intro\n\
+		 tags:  entry ,start\n\
+		 ---\n\
+		 \n\
+		 \n\
+		 Alice: Hello! #line:hello   // greeting\n\
+		 <<  set $gold   to \"a  b\" >>\n\
+		 // Pick one\n\
+		 \t->Yes #line:yes\n\
+		 \t\t\tBob: Great. #line:great #happy\n\
+		 \t->   No\n\
+		 \t\t<<if $gold == \"a  b\">>\n\
+		 \t\t  Bob: Oh.\n\
+		 \t\t<<else>>\n\
+		 \t\t  <<jump Intro>>\n\
+		 \t\t<<endif>>\n\
+		 ===\n\
+		 \n";
+
+	let expected =
+		"title: Intro\n\
+		 tags: entry, start\n\
+		 ---\n\
+		 \n\
+		 Alice: Hello! #line:hello // greeting\n\
+		 <<set $gold to \"a  b\">>\n\
+		 // Pick one\n\
+		 -> Yes          #line:yes\n\
+		 \x20   Bob: Great. #line:great #happy\n\
+		 -> No\n\
+		 \x20   <<if $gold == \"a  b\">>\n\
+		 \x20       Bob: Oh.\n\
+		 \x20   <<else>>\n\
+		 \x20       <<jump Intro>>\n\
+		 \x20   <<endif>>\n\
+		 ===\n";
+
+	let formatted = format_yarn(Path::new("intro.yarn"), input).unwrap();
+	assert_eq!(formatted, expected);
+	assert_eq!(format_yarn(Path::new("intro.yarn"), &formatted).unwrap(), formatted);
+	assert!(format_yarn(Path::new("intro.yarn"), "title: A\n---\n<<endif>>\n===\n").is_err());
+}
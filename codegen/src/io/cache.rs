@@ -0,0 +1,192 @@
+use crate::diagnostic::Diagnostics;
+use crate::io::read::YarnFile;
+use crate::parsing::raw::var_declaration::VarDeclaration;
+use crate::parsing::{parse_nodes, YarnNode};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+pub const CACHE_FILE_NAME: &str = ".yarn_spire_cache.json";
+
+/// Bump whenever the parsing output changes, so caches written by older versions get discarded.
+const CACHE_FORMAT_VERSION: u32 = 9;
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+	hash: u64,
+	nodes: Vec<YarnNode>,
+	var_declarations: Vec<VarDeclaration>,
+}
+
+/// Parse results of each yarn file, keyed by the file's path and invalidated by the hash of its contents.
+///
+/// Lets re-runs skip parsing files that did not change since the last run.
+#[derive(Default, Serialize, Deserialize)]
+pub struct ParseCache {
+	version: u32,
+	entries: HashMap<PathBuf, CacheEntry>,
+}
+
+fn hash_file(yarn_file: &YarnFile) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	yarn_file.lines.hash(&mut hasher);
+	hasher.finish()
+}
+
+impl ParseCache {
+	/// Loads the cache at `path`.
+	///
+	/// Returns an empty cache if the file does not exist, is corrupted or was written by another version.
+	pub fn load(path: &Path) -> Result<ParseCache> {
+		let contents =
+			match std::fs::read_to_string(path) {
+				Ok(contents) => contents,
+				Err(err) if err.kind() == ErrorKind::NotFound => return Ok(ParseCache::default()),
+				Err(err) => {
+					return Err(anyhow!(
+						"Could not read cache file at `{path:?}`.\n\
+						 Error: {err}\n\n\
+						 Help: You can safely delete the cache file, it will be re-created on the next run."));
+				},
+			};
+
+		Ok(serde_json::from_str::<ParseCache>(&contents)
+			.ok()
+			.filter(|cache| cache.version == CACHE_FORMAT_VERSION)
+			.unwrap_or_default())
+	}
+
+	pub fn save(&self, path: &Path) -> Result<()> {
+		let contents =
+			serde_json::to_string(self)
+				.map_err(|err| anyhow!(
+					"Could not serialize parse cache.\n\
+					 Error: {err}"))?;
+
+		std::fs::write(path, contents)
+			.map_err(|err| anyhow!(
+				"Could not write cache file at `{path:?}`.\n\
+				 Error: {err}"))
+	}
+
+	/// Parses each file, or reuses the cached results if its contents did not change.
+	///
+	/// Entries of files that are not in `yarn_files` are dropped.
+	///
+	/// Every file is parsed even if some have errors, then all of them are returned at once as [Diagnostics],
+	/// up to `max_errors`. Files with errors are not cached, so their errors are reported again on the next run.
+	pub(crate) fn parse_all(&mut self, yarn_files: Vec<YarnFile>, max_errors: usize) -> Result<(Vec<YarnNode>, Vec<VarDeclaration>)> {
+		let mut old_entries = std::mem::take(&mut self.entries);
+		self.version = CACHE_FORMAT_VERSION;
+
+		let mut nodes_sum = vec![];
+		let mut vars_sum = vec![];
+		let mut diagnostics = vec![];
+
+		for yarn_file in yarn_files {
+			let path = yarn_file.path.clone();
+			let hash = hash_file(&yarn_file);
+
+			let entry =
+				match old_entries.remove(&path) {
+					Some(entry) if entry.hash == hash => entry,
+					_ => {
+						let errors_before = diagnostics.len();
+						let (nodes, var_declarations) = parse_nodes(yarn_file, &mut diagnostics);
+						
+						if diagnostics.len() > errors_before {
+							continue;
+						}
+						
+						CacheEntry { hash, nodes, var_declarations }
+					},
+				};
+
+			nodes_sum.extend(entry.nodes.iter().cloned());
+			vars_sum.extend(entry.var_declarations.iter().cloned());
+			self.entries.insert(path, entry);
+		}
+
+		if diagnostics.is_empty() {
+			Ok((nodes_sum, vars_sum))
+		} else {
+			Err(Diagnostics::limited(diagnostics, max_errors).into())
+		}
+	}
+}
+
+#[test]
+fn test_parse_all() {
+	use crate::io::read::yarn_file_from_text;
+	use pretty_assertions::assert_eq;
+
+	fn yarn_file(path: &str, text: &str) -> YarnFile {
+		yarn_file_from_text(PathBuf::from(path), text)
+	}
+
+	fn titles(nodes: &[YarnNode]) -> Vec<&str> {
+		nodes.iter().map(|node| node.metadata.title.as_str()).collect()
+	}
+
+	let mut cache = ParseCache::default();
+
+	let (nodes, _) = cache.parse_all(vec![
+		yarn_file("a.yarn", "title: A\n---\nHello\n==="),
+		yarn_file("b.yarn", "title: B\n---\nWorld\n==="),
+	], 0).unwrap();
+	assert_eq!(titles(&nodes), ["A", "B"]);
+
+	let json = serde_json::to_string(&cache).unwrap();
+	let mut cache = serde_json::from_str::<ParseCache>(&json).unwrap();
+	let hash_a = cache.entries[Path::new("a.yarn")].hash;
+
+	let (nodes, _) = cache.parse_all(vec![
+		yarn_file("a.yarn", "title: A\n---\nHello\n==="),
+		yarn_file("c.yarn", "title: C\n---\nAgain\n==="),
+	], 0).unwrap();
+	assert_eq!(titles(&nodes), ["A", "C"]);
+	assert_eq!(cache.entries[Path::new("a.yarn")].hash, hash_a);
+	assert!(!cache.entries.contains_key(Path::new("b.yarn")));
+
+	let (nodes, _) = cache.parse_all(vec![
+		yarn_file("a.yarn", "title: Renamed\n---\nHello\n==="),
+	], 0).unwrap();
+	assert_eq!(titles(&nodes), ["Renamed"]);
+	assert_ne!(cache.entries[Path::new("a.yarn")].hash, hash_a);
+}
+
+#[test]
+fn test_parse_all_reports_every_error() {
+	use crate::io::read::yarn_file_from_text;
+
+	fn yarn_file(path: &str, text: &str) -> YarnFile {
+		yarn_file_from_text(PathBuf::from(path), text)
+	}
+
+	let files = || vec![
+		yarn_file("a.yarn", "title: A\n---\n<<endif>>\nHello\n<<else>>\n==="),
+		yarn_file("b.yarn", "title: B-1\n---\nWorld\n===\n==="),
+	];
+
+	let mut cache = ParseCache::default();
+	let err = cache.parse_all(files(), 0).err().unwrap();
+	let diagnostics = err.downcast::<Diagnostics>().unwrap();
+
+	let lines =
+		diagnostics
+			.list
+			.iter()
+			.map(|diagnostic| {
+				let span = diagnostic.span.as_ref().unwrap();
+				(span.file.to_str().unwrap(), span.line)
+			}).collect::<Vec<_>>();
+
+	assert_eq!(lines, [("a.yarn", 3), ("a.yarn", 5), ("b.yarn", 1), ("b.yarn", 5)]);
+	assert!(cache.entries.is_empty());
+
+	let diagnostics = cache.parse_all(files(), 3).err().unwrap().downcast::<Diagnostics>().unwrap();
+	assert_eq!((diagnostics.list.len(), diagnostics.omitted), (3, 1));
+}
@@ -1,2 +1,4 @@
+pub mod cache;
 pub mod read;
+pub mod watch;
 pub mod write;
\ No newline at end of file
@@ -1,100 +1,88 @@
 use crate::config::YarnConfig;
+use crate::parsing::lexer::comment_start;
 use crate::UnparsedLine;
 use anyhow::{anyhow, Result};
 use encoding_rs_io::DecodeReaderBytesBuilder;
-use houtamelo_utils::prelude::None;
+use glob::Pattern;
+use std::collections::HashSet;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
 use trim_in_place::TrimInPlace;
 
+#[derive(Clone)]
 pub struct YarnFile {
 	pub path: PathBuf,
 	pub lines: Vec<UnparsedLine>,
 }
 
-fn find_yarn_files(relative_path: &str, exclude: &[PathBuf]) -> Result<Vec<PathBuf>> {
-	let options = glob::MatchOptions {
-		case_sensitive: true,
-		require_literal_separator: false,
-		require_literal_leading_dot: false,
-	};
+const MATCH_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+	case_sensitive: true,
+	require_literal_separator: true,
+	require_literal_leading_dot: false,
+};
 
-	let pattern = relative_path.to_string() + "/**/*.yarn";
+fn find_yarn_files_in_root(root: &Path, cfg: &YarnConfig) -> Result<Vec<PathBuf>> {
+	let root_str = root
+		.to_str()
+		.ok_or_else(|| anyhow!(
+			"Could not convert yarn root folder to `str`.\n\
+			 Path: {root:?}\n\n\
+			 Help: The current search algorithm requires utf-8 valid strings, but the provided path has non-utf-8 chars."
+		))?;
 
-	let path_iter =
-		glob::glob_with(&pattern, options)
-			.map_err(|err| anyhow!(
-				"Could create glob pattern.\n\
-				 Error: {err}"
-			))?;
+	let mut files = vec![];
 
-	let paths: Vec<PathBuf> = path_iter
-		.into_iter()
-		.try_collect()
-		.map_err(|err| anyhow!("Could not glob path.\nError: {err}"))?;
+	for include in &cfg.include_patterns {
+		let pattern = format!("{root}/{include}", root = Pattern::escape(root_str));
 
-	let files: Vec<PathBuf> = paths
-		.into_iter()
-		.filter(|path| exclude.iter().none(|excluded| path.starts_with(excluded)))
-		.collect();
+		let path_iter =
+			glob::glob_with(&pattern, MATCH_OPTIONS)
+				.map_err(|err| anyhow!(
+					"Could create glob pattern.\n\
+					 Pattern: `{pattern}`\n\
+					 Error: {err}"
+				))?;
+
+		for path in path_iter {
+			let path = path.map_err(|err| anyhow!("Could not glob path.\nError: {err}"))?;
+			let relative = path.strip_prefix(root).unwrap_or(&path);
+
+			let excluded =
+				cfg.exclude_patterns.iter().any(|pattern| pattern.matches_path_with(relative, MATCH_OPTIONS))
+				|| cfg.exclude_yarn_folders.iter().any(|excluded| path.starts_with(excluded));
+
+			if !excluded && path.is_file() {
+				files.push(path);
+			}
+		}
+	}
 
 	Ok(files)
 }
 
-enum State {
-	OutsideLiteral,
-	OutsideLiteralIgnoreNext,
-	InsideLiteral,
-	InsideLiteralIgnoreNext,
-}
+/// Lists the files matching `include_patterns` inside every root, skipping the excluded ones.
+///
+/// A file matched by several patterns or roots is only listed once.
+pub fn find_yarn_paths(cfg: &YarnConfig) -> Result<Vec<PathBuf>> {
+	let mut seen = HashSet::new();
+	let mut files = vec![];
 
-fn filter_comments(line: &mut String) {
-	let mut state =
-		State::OutsideLiteral;
-
-	let mut iter =
-		line.char_indices()
-		    .peekable();
-
-	while let Some((index, ch)) = iter.next() {
-		match state {
-			State::InsideLiteral => {
-				match ch {
-					'\\' => {
-						state = State::InsideLiteralIgnoreNext;
-					}
-					'\"' => {
-						state = State::OutsideLiteral;
-					}
-					_ => {}
-				}
-			}
-			State::InsideLiteralIgnoreNext => {
-				state = State::InsideLiteral;
-			}
-			State::OutsideLiteral => {
-				match ch {
-					'\\' => {
-						state = State::OutsideLiteralIgnoreNext;
-					}
-					'\"' => {
-						state = State::InsideLiteral;
-					}
-					'/' => {
-						if iter.next_if(|(_, ch)| *ch == '/').is_some() {
-							line.truncate(index);
-							return;
-						}
-					}
-					_ => {}
-				}
-			}
-			State::OutsideLiteralIgnoreNext => {
-				state = State::OutsideLiteral;
+	for root in &cfg.yarn_root_folders {
+		for path in find_yarn_files_in_root(root, cfg)? {
+			if seen.insert(path.clone()) {
+				files.push(path);
 			}
 		}
 	}
+
+	Ok(files)
+}
+
+fn filter_comments(line: &mut String) {
+	if let Some(index) = comment_start(line) {
+		line.truncate(index);
+	}
 }
 
 #[test]
@@ -161,54 +149,136 @@ fn read_lines(file: File, path: PathBuf) -> Result<YarnFile> {
 		 		   Error: {err}")
 		      )?;
 
-	let lines =
-		source_lines
-			.into_iter()
-			.filter_map(|(line_number, mut text)| {
-				filter_comments(&mut text);
-				text.trim_end_in_place();
-				text.shrink_to_fit();
-
-				if text.is_empty() || text.chars().all(char::is_whitespace) {
-					None
-				} else {
-					Some(UnparsedLine { line_number, text })
-				}
-			}).collect();
-
 	Ok(YarnFile {
 		path,
-		lines,
+		lines: into_unparsed_lines(source_lines),
 	})
 }
 
-fn read_files(paths: Vec<PathBuf>) -> Result<Vec<YarnFile>> {
-	paths.into_iter()
-	     .map(|path| {
-		     let file =
-			     File::open(&path)
-				     .map_err(|err| anyhow!(
-						 "Could not open file at path: {path:?}\n\
-					      Error: {err}")
-				     )?;
-
-		     let lines = read_lines(file, path)?;
-		     Ok(lines)
-	     }).try_collect()
+/// Strips comments and skips blank lines, `source_lines` are numbered starting at 1.
+fn into_unparsed_lines(source_lines: impl IntoIterator<Item = (usize, String)>) -> Vec<UnparsedLine> {
+	source_lines
+		.into_iter()
+		.filter_map(|(line_number, mut text)| {
+			filter_comments(&mut text);
+			text.trim_end_in_place();
+			text.shrink_to_fit();
+
+			if text.is_empty() || text.chars().all(char::is_whitespace) {
+				None
+			} else {
+				Some(UnparsedLine { line_number, text })
+			}
+		}).collect()
 }
 
-pub fn find_and_read_yarn_files(cfg: &YarnConfig) -> Result<Vec<YarnFile>> {
-	let yarn_root_path = cfg
-		.yarn_root_folder
-		.to_str()
-		.ok_or_else(|| anyhow!(
-			"Could not convert `yarn_root_folder` to `str`.\n\
-			 Path: {:?}\n\n\
-			 Help: The current search algorithm requires utf-8 valid strings, but the provided path has non-utf-8 chars."
-			, cfg.yarn_root_folder)
+/// Same as [read_yarn_file], but for contents that are not saved yet, like the ones of an editor.
+pub fn yarn_file_from_text(path: PathBuf, text: &str) -> YarnFile {
+	let source_lines =
+		text.lines()
+		    .enumerate()
+		    .map(|(index, line)| (index + 1, line.to_string()));
+
+	YarnFile {
+		path,
+		lines: into_unparsed_lines(source_lines),
+	}
+}
+
+pub fn read_yarn_file(path: PathBuf) -> Result<YarnFile> {
+	let file =
+		File::open(&path)
+			.map_err(|err| anyhow!(
+				"Could not open file at path: {path:?}\n\
+				 Error: {err}")
+			)?;
+
+	read_lines(file, path)
+}
+
+/// Reads the whole file as text, comments and blank lines included.
+pub fn read_yarn_text(path: &Path) -> Result<String> {
+	let file =
+		File::open(path)
+			.map_err(|err| anyhow!(
+				"Could not open file at path: {path:?}\n\
+				 Error: {err}")
+			)?;
+
+	let mut text = String::new();
+
+	DecodeReaderBytesBuilder::new()
+		.encoding(None)
+		.bom_sniffing(true)
+		.build(file)
+		.read_to_string(&mut text)
+		.map_err(|err| anyhow!(
+			"Could not read file at path: {path:?}\n\
+			 Error: {err}")
 		)?;
 
-	let paths = find_yarn_files(yarn_root_path, &cfg.exclude_yarn_folders)?;
+	Ok(text)
+}
+
+pub fn find_and_read_yarn_files(cfg: &YarnConfig) -> Result<Vec<YarnFile>> {
+	find_yarn_paths(cfg)?
+		.into_iter()
+		.map(read_yarn_file)
+		.try_collect()
+}
+
+#[test]
+fn test_find_yarn_paths() {
+	use crate::config::{ConfigOverrides, DeserializableConfig};
+	use houtamelo_utils::{own, own_vec};
+	use pretty_assertions::assert_eq;
+
+	let dir = std::env::temp_dir().join(format!("yarn_spire_find_paths_{}", std::process::id()));
+	let files = [
+		"main/chapters/ch01/intro.yarn",
+		"main/chapters/ch01/drafts/old.yarn",
+		"main/chapters/ch02/battle_wip.yarn",
+		"main/shared/common.yarn",
+		"main/shared/nested/not_included.yarn",
+		"main/tests/fixture.yarn",
+		"dlc/chapters/dlc01.yarn",
+	];
+
+	for file in files {
+		let path = dir.join(file);
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(path, "").unwrap();
+	}
+
+	let fields = DeserializableConfig {
+		storage_type_name: own!("Storage"),
+		command_module_path: own!("crate::cmd"),
+		command_type_name: own!("Cmd"),
+		generate_storage: true,
+		destination_os_path: own!("out"),
+		destination_module_path: own!("crate::dialogue"),
+		yarn_root_folder: own!("main"),
+		yarn_root_folders: own_vec!["dlc"],
+		include: own_vec!["chapters/**/*.yarn", "shared/*.yarn", "tests/*.yarn"],
+		exclude: own_vec!["**/drafts/**", "**/*_wip.yarn"],
+		exclude_yarn_folders: own_vec!["tests"],
+		..DeserializableConfig::default()
+	};
+
+	let cfg = YarnConfig::from_fields(fields, &dir.join("yarn_project.toml"), &ConfigOverrides::default()).unwrap();
+
+	let found =
+		find_yarn_paths(&cfg)
+			.unwrap()
+			.into_iter()
+			.map(|path| path.strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/"))
+			.collect::<Vec<_>>();
+
+	std::fs::remove_dir_all(&dir).unwrap();
 
-	read_files(paths)
+	assert_eq!(found, [
+		"main/chapters/ch01/intro.yarn",
+		"main/shared/common.yarn",
+		"dlc/chapters/dlc01.yarn",
+	]);
 }
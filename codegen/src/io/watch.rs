@@ -0,0 +1,151 @@
+use crate::config::YarnConfig;
+use crate::io::cache::ParseCache;
+use crate::io::read::{find_yarn_paths, read_yarn_file, YarnFile};
+use crate::io::write::output::GeneratedOutput;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+struct WatchedFile {
+	modified: Option<SystemTime>,
+	yarn_file: YarnFile,
+}
+
+/// Keeps the yarn files in memory between runs, re-reading only the ones whose modification time changed.
+///
+/// Used by watch mode, which polls instead of relying on OS-specific file system notifications.
+pub struct YarnWatcher {
+	files: HashMap<PathBuf, WatchedFile>,
+	cache: ParseCache,
+	polled_once: bool,
+}
+
+fn modified_time(path: &PathBuf) -> Option<SystemTime> {
+	std::fs::metadata(path)
+		.and_then(|metadata| metadata.modified())
+		.ok()
+}
+
+impl YarnWatcher {
+	pub fn new(cache: ParseCache) -> Self {
+		YarnWatcher {
+			files: HashMap::new(),
+			cache,
+			polled_once: false,
+		}
+	}
+
+	pub fn cache(&self) -> &ParseCache {
+		&self.cache
+	}
+
+	/// Checks the yarn root folders for added, removed or modified files.
+	///
+	/// Returns `true` if anything changed since the last poll, always `true` on the first poll.
+	/// Files whose modification time changed but whose contents are the same do not count as changed.
+	pub fn poll(&mut self, cfg: &YarnConfig) -> Result<bool> {
+		let paths = find_yarn_paths(cfg)?;
+		let mut changed = !self.polled_once;
+		self.polled_once = true;
+
+		let mut old_files = std::mem::take(&mut self.files);
+
+		for path in paths {
+			let modified = modified_time(&path);
+
+			let watched =
+				match old_files.remove(&path) {
+					Some(watched) if watched.modified == modified => watched,
+					Some(watched) => {
+						let yarn_file = read_yarn_file(path.clone())?;
+						changed |= yarn_file.lines != watched.yarn_file.lines;
+						WatchedFile { modified, yarn_file }
+					},
+					None => {
+						changed = true;
+						WatchedFile { modified, yarn_file: read_yarn_file(path.clone())? }
+					},
+				};
+
+			self.files.insert(path, watched);
+		}
+
+		// Whatever is left was not found this time, so it was removed.
+		changed |= !old_files.is_empty();
+		Ok(changed)
+	}
+
+	/// Generates the code from the files read in the last [YarnWatcher::poll].
+	pub fn generate(&mut self, cfg: &YarnConfig) -> Result<GeneratedOutput> {
+		let mut yarn_files =
+			self.files
+			    .values()
+			    .map(|watched| watched.yarn_file.clone())
+			    .collect::<Vec<_>>();
+
+		// Keeps the output deterministic, `HashMap` iteration order is random.
+		yarn_files.sort_by(|a, b| a.path.cmp(&b.path));
+
+		crate::generate_from_files(cfg, yarn_files, &mut self.cache)
+	}
+}
+
+#[test]
+fn test_poll() {
+	use crate::config::{ConfigOverrides, DeserializableConfig};
+	use houtamelo_utils::own;
+	use std::fs::File;
+	use std::time::Duration;
+
+	let dir = std::env::temp_dir().join(format!("yarn_spire_watch_{}", std::process::id()));
+	let a_path = dir.join("yarn/a.yarn");
+	let b_path = dir.join("yarn/b.yarn");
+	std::fs::create_dir_all(a_path.parent().unwrap()).unwrap();
+	std::fs::write(&a_path, "title: A\n---\nHello\n===").unwrap();
+
+	// Modification times can be too coarse to tell quick writes apart, so they are set explicitly.
+	let touch = |path: &PathBuf, seconds: u64| {
+		File::options()
+			.write(true)
+			.open(path)
+			.unwrap()
+			.set_modified(SystemTime::now() + Duration::from_secs(seconds))
+			.unwrap();
+	};
+
+	let fields = DeserializableConfig {
+		storage_type_name: own!("Storage"),
+		command_module_path: own!("crate::cmd"),
+		command_type_name: own!("Cmd"),
+		generate_storage: true,
+		destination_os_path: own!("out"),
+		destination_module_path: own!("crate::dialogue"),
+		yarn_root_folder: own!("yarn"),
+		..DeserializableConfig::default()
+	};
+
+	let cfg = YarnConfig::from_fields(fields, &dir.join("yarn_project.toml"), &ConfigOverrides::default()).unwrap();
+	let mut watcher = YarnWatcher::new(ParseCache::default());
+
+	let mut polls = vec![watcher.poll(&cfg).unwrap(), watcher.poll(&cfg).unwrap()];
+
+	// Touched, but the contents are the same.
+	touch(&a_path, 10);
+	polls.push(watcher.poll(&cfg).unwrap());
+
+	std::fs::write(&a_path, "title: A\n---\nHello again\n===").unwrap();
+	touch(&a_path, 20);
+	polls.push(watcher.poll(&cfg).unwrap());
+
+	std::fs::write(&b_path, "title: B\n---\nWorld\n===").unwrap();
+	polls.push(watcher.poll(&cfg).unwrap());
+
+	std::fs::remove_file(&b_path).unwrap();
+	polls.push(watcher.poll(&cfg).unwrap());
+	polls.push(watcher.poll(&cfg).unwrap());
+
+	std::fs::remove_dir_all(&dir).unwrap();
+
+	assert_eq!(polls, [true, false, false, true, true, true, false]);
+}
@@ -0,0 +1,89 @@
+use crate::config::YarnConfig;
+use crate::io::write::manifest::find_files_to_delete;
+use crate::io::write::output::GeneratedOutput;
+use crate::io::write::util::read_if_exists;
+use anyhow::Result;
+use similar::TextDiff;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutdatedReason {
+	/// The file should exist but does not.
+	Missing,
+	/// The file exists, but its contents differ from the generated ones.
+	Changed,
+	/// The file exists, but is no longer generated.
+	Obsolete,
+}
+
+/// A file in `destination_os_path` that does not match the generated output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutdatedFile {
+	/// Path relative to `destination_os_path`.
+	pub relative_path: PathBuf,
+	pub reason: OutdatedReason,
+	/// Unified diff from the file on disk to the generated contents.
+	pub diff: String,
+}
+
+pub(crate) fn unified_diff(relative_path: &Path, old: &str, new: &str) -> String {
+	let path = relative_path.display();
+
+	TextDiff::from_lines(old, new)
+		.unified_diff()
+		.context_radius(3)
+		.header(&format!("a/{path}"), &format!("b/{path}"))
+		.to_string()
+}
+
+/// Compares `output` against the files already in `destination_os_path`, without writing anything.
+pub fn find_outdated_files(output: &GeneratedOutput, cfg: &YarnConfig) -> Result<Vec<OutdatedFile>> {
+	let mut outdated = vec![];
+
+	for generated in &output.files {
+		let path = cfg.destination_os_path.join(&generated.relative_path);
+
+		let (reason, old) =
+			match read_if_exists(&path)? {
+				Some(on_disk) if on_disk == generated.contents => continue,
+				Some(on_disk) => (OutdatedReason::Changed, on_disk),
+				None => (OutdatedReason::Missing, String::new()),
+			};
+
+		outdated.push(OutdatedFile {
+			relative_path: generated.relative_path.clone(),
+			diff: unified_diff(&generated.relative_path, &old, &generated.contents),
+			reason,
+		});
+	}
+
+	// Obsolete files are only deleted when overwriting is allowed, so only then they count as outdated.
+	if cfg.allow_overwrite {
+		for relative_path in find_files_to_delete(output, cfg)? {
+			if let Some(on_disk) = read_if_exists(&cfg.destination_os_path.join(&relative_path))? {
+				outdated.push(OutdatedFile {
+					diff: unified_diff(&relative_path, &on_disk, ""),
+					relative_path,
+					reason: OutdatedReason::Obsolete,
+				});
+			}
+		}
+	}
+
+	Ok(outdated)
+}
+
+#[test]
+fn test_unified_diff() {
+	use pretty_assertions::assert_eq;
+
+	let diff = unified_diff(Path::new("nodes/mod.rs"), "mod Start;\nmod Other;\n", "mod Start;\nmod Renamed;\n");
+
+	assert_eq!(diff,
+		"--- a/nodes/mod.rs\n\
+		 +++ b/nodes/mod.rs\n\
+		 @@ -1,2 +1,2 @@\n \
+		 mod Start;\n\
+		 -mod Other;\n\
+		 +mod Renamed;\n");
+}
@@ -1,106 +1,98 @@
 use crate::config::YarnConfig;
-use crate::io::write::util::{get_or_create_file, write_to_file};
+use crate::expressions::declaration_ty::DeclarationTy;
+use crate::io::write::output::GeneratedFile;
+use crate::io::write::util::render_tokens;
 use crate::parsing::raw::var_declaration::VarDeclaration;
 use crate::quoting;
 use crate::quoting::quotable_types::node::{IDNode, LinesMap};
 use anyhow::Result;
-use core_types::{default_storage, instruction, options, runtime, speech, title, var_trait};
+use core_types::{default_storage, detour, instruction, options, runtime, speech, title, var_trait};
 use quoting::core_types;
 use quoting::core_types::command_line;
+use std::collections::HashMap;
 
-fn write_root(cfg: &YarnConfig) -> Result<()> {
-	let path = cfg.destination_os_path.join("mod.rs");
-	let file = get_or_create_file(&path, cfg.allow_overwrite)?;
+fn generate_root(cfg: &YarnConfig) -> Result<GeneratedFile> {
 	let tokens = core_types::tokens_root_module(cfg);
-	write_to_file(&path, file, tokens)
+	render_tokens("mod.rs", tokens)
 }
 
-fn write_built_in_functions(cfg: &YarnConfig) -> Result<()> {
-	let path = cfg.destination_os_path.join("built_in_functions.rs");
-	let file = get_or_create_file(&path, cfg.allow_overwrite)?;
+fn generate_built_in_functions() -> Result<GeneratedFile> {
 	let tokens = core_types::built_in_functions::all_tokens();
-	write_to_file(&path, file, tokens)
+	render_tokens("built_in_functions.rs", tokens)
 }
 
-fn write_command(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<()> {
-	let path = cfg.destination_os_path.join("command_line.rs");
-	let file = get_or_create_file(&path, cfg.allow_overwrite)?;
+fn generate_command(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<GeneratedFile> {
 	let tokens = command_line::all_tokens(cfg, nodes_mapped);
-	write_to_file(&path, file, tokens)
+	render_tokens("command_line.rs", tokens)
 }
 
-fn write_default_storage(
+fn generate_detour(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<GeneratedFile> {
+	let tokens = detour::all_tokens(cfg, nodes_mapped);
+	render_tokens("detour.rs", tokens)
+}
+
+fn generate_default_storage(
 	cfg: &YarnConfig,
-	nodes: &[IDNode],
 	var_declarations: &[VarDeclaration],
-) -> Result<()> {
-	let tokens = default_storage::all_tokens(cfg, nodes, var_declarations)?;
-	let path = cfg.destination_os_path.join("default_storage.rs");
-	let file = get_or_create_file(&path, cfg.allow_overwrite)?;
-	write_to_file(&path, file, tokens)
+	var_types: &HashMap<String, Option<DeclarationTy>>,
+) -> Result<GeneratedFile> {
+	let tokens = default_storage::all_tokens(cfg, var_declarations, var_types);
+	render_tokens("default_storage.rs", tokens)
 }
 
-fn write_instruction(cfg: &YarnConfig) -> Result<()> {
-	let path = cfg.destination_os_path.join("instruction.rs");
-	let file = get_or_create_file(&path, cfg.allow_overwrite)?;
+fn generate_instruction(cfg: &YarnConfig) -> Result<GeneratedFile> {
 	let tokens = instruction::all_tokens(cfg);
-	write_to_file(&path, file, tokens)
+	render_tokens("instruction.rs", tokens)
 }
 
-fn write_options(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<()> {
-	let path = cfg.destination_os_path.join("options.rs");
-	let file = get_or_create_file(&path, cfg.allow_overwrite)?;
+fn generate_options(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<GeneratedFile> {
 	let tokens = options::all_tokens(cfg, nodes_mapped);
-	write_to_file(&path, file, tokens)
+	render_tokens("options.rs", tokens)
 }
 
-fn write_runtime(cfg: &YarnConfig) -> Result<()> {
-	let path = cfg.destination_os_path.join("runtime.rs");
-	let file = get_or_create_file(&path, cfg.allow_overwrite)?;
+fn generate_runtime(cfg: &YarnConfig) -> Result<GeneratedFile> {
 	let tokens = runtime::all_tokens(cfg);
-	write_to_file(&path, file, tokens)
+	render_tokens("runtime.rs", tokens)
 }
 
-fn write_speech(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<()> {
-	let path = cfg.destination_os_path.join("speech.rs");
-	let file = get_or_create_file(&path, cfg.allow_overwrite)?;
+fn generate_speech(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<GeneratedFile> {
 	let tokens = speech::all_tokens(cfg, nodes_mapped);
-	write_to_file(&path, file, tokens)
+	render_tokens("speech.rs", tokens)
 }
 
-fn write_title(cfg: &YarnConfig, nodes: &[IDNode]) -> Result<()> {
-	let path = cfg.destination_os_path.join("title.rs");
-	let file = get_or_create_file(&path, cfg.allow_overwrite)?;
+fn generate_title(cfg: &YarnConfig, nodes: &[IDNode]) -> Result<GeneratedFile> {
 	let tokens = title::all_tokens(cfg, nodes);
-	write_to_file(&path, file, tokens)
+	render_tokens("title.rs", tokens)
 }
 
-fn write_var_trait(cfg: &YarnConfig) -> Result<()> {
-	let path = cfg.destination_os_path.join("var_trait.rs");
-	let file = get_or_create_file(&path, cfg.allow_overwrite)?;
+fn generate_var_trait(cfg: &YarnConfig) -> Result<GeneratedFile> {
 	let tokens = var_trait::all_tokens(cfg);
-	write_to_file(&path, file, tokens)
+	render_tokens("var_trait.rs", tokens)
 }
 
-pub fn write_all(
+pub fn generate_all(
 	config: &YarnConfig,
 	nodes: &[IDNode],
 	nodes_mapped: &[(&IDNode, LinesMap)],
 	var_declarations: &[VarDeclaration],
-) -> Result<()> {
-	write_root(config)?;
-	write_built_in_functions(config)?;
-	write_command(config, nodes_mapped)?;
-	write_instruction(config)?;
-	write_options(config, nodes_mapped)?;
-	write_runtime(config)?;
-	write_speech(config, nodes_mapped)?;
-	write_title(config, nodes)?;
-	write_var_trait(config)?;
+	var_types: &HashMap<String, Option<DeclarationTy>>,
+) -> Result<Vec<GeneratedFile>> {
+	let mut files = vec![
+		generate_root(config)?,
+		generate_built_in_functions()?,
+		generate_command(config, nodes_mapped)?,
+		generate_detour(config, nodes_mapped)?,
+		generate_instruction(config)?,
+		generate_options(config, nodes_mapped)?,
+		generate_runtime(config)?,
+		generate_speech(config, nodes_mapped)?,
+		generate_title(config, nodes)?,
+		generate_var_trait(config)?,
+	];
 
 	if config.generate_storage {
-		write_default_storage(config, nodes, var_declarations)?;
+		files.push(generate_default_storage(config, var_declarations, var_types)?);
 	}
 
-	Ok(())
-}
\ No newline at end of file
+	Ok(files)
+}
@@ -0,0 +1,132 @@
+use crate::io::write::output::GeneratedFile;
+use anyhow::Result;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use proc_macro2::{Span, TokenStream};
+use quote::ToTokens;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use syn::{Ident, Item, ItemMod, Visibility};
+use syn::token::Brace;
+
+/// Parses the generated files and nests them into a single module named `module_name`,
+/// replacing every `mod name;` declaration with the contents of the file it points to.
+pub fn inline_modules(files: &[GeneratedFile], module_name: &str) -> Result<TokenStream> {
+	let by_path: HashMap<&Path, &GeneratedFile> =
+		files.iter()
+		     .map(|file| (file.relative_path.as_path(), file))
+		     .collect();
+
+	let root =
+		by_path
+			.get(Path::new("mod.rs"))
+			.ok_or_else(|| Diagnostic::error(ErrorCode::InlineFailed, "Generated output does not contain a root `mod.rs` file."))?;
+
+	let (attrs, items) = parse_and_inline(root, Path::new(""), &by_path)?;
+
+	let module = ItemMod {
+		attrs,
+		vis: Visibility::Public(Default::default()),
+		unsafety: None,
+		mod_token: Default::default(),
+		ident: Ident::new(module_name, Span::call_site()),
+		content: Some((Brace::default(), items)),
+		semi: None,
+	};
+
+	Ok(module.into_token_stream())
+}
+
+fn parse_and_inline(
+	file: &GeneratedFile,
+	dir: &Path,
+	by_path: &HashMap<&Path, &GeneratedFile>,
+) -> Result<(Vec<syn::Attribute>, Vec<Item>)> {
+	let parsed =
+		syn::parse_file(&file.contents)
+			.map_err(|err| Diagnostic::error(ErrorCode::InlineFailed, format!(
+				"Could not parse generated file `{path:?}` as Rust code.", path = file.relative_path))
+				.with_note(err.to_string()))?;
+
+	let items: Vec<Item> =
+		parsed.items
+		      .into_iter()
+		      .map(|item| match item {
+			      Item::Mod(mut item_mod) if item_mod.content.is_none() => {
+				      let name = item_mod.ident.to_string();
+				      let (child, child_dir) = find_module_file(dir, &name, by_path)?;
+				      let (inner_attrs, inner_items) = parse_and_inline(child, &child_dir, by_path)?;
+
+				      item_mod.attrs.extend(inner_attrs);
+				      item_mod.content = Some((Brace::default(), inner_items));
+				      item_mod.semi = None;
+				      Ok(Item::Mod(item_mod))
+			      },
+			      other => Ok(other),
+		      })
+		      .collect::<Result<_>>()?;
+
+	Ok((parsed.attrs, items))
+}
+
+fn find_module_file<'a>(
+	dir: &Path,
+	name: &str,
+	by_path: &HashMap<&Path, &'a GeneratedFile>,
+) -> Result<(&'a GeneratedFile, PathBuf)> {
+	let child_dir = dir.join(name);
+	let as_file = dir.join(format!("{name}.rs"));
+	let as_folder = child_dir.join("mod.rs");
+
+	by_path
+		.get(as_file.as_path())
+		.or_else(|| by_path.get(as_folder.as_path()))
+		.map(|file| (*file, child_dir))
+		.ok_or_else(|| Diagnostic::error(ErrorCode::InlineFailed, format!(
+			"Module `{name}` is declared in `{dir:?}`, but neither `{as_file:?}` nor `{as_folder:?}` were generated."))
+			.into())
+}
+
+#[test]
+fn test_inline_modules() {
+	use pretty_assertions::assert_eq;
+
+	fn file(path: &str, contents: &str) -> GeneratedFile {
+		GeneratedFile {
+			relative_path: PathBuf::from(path),
+			contents: contents.to_string(),
+		}
+	}
+
+	let files = [
+		file("mod.rs", "#![allow(unused)]\npub mod title;\npub mod nodes;\npub type Counter = usize;"),
+		file("title.rs", "pub struct Title;"),
+		file("nodes/mod.rs", "mod Start;\npub use Start::*;"),
+		file("nodes/Start/mod.rs", "#![allow(non_snake_case)]\nmod title;"),
+		file("nodes/Start/title.rs", "pub struct Start;"),
+	];
+
+	let expected = quote::quote! {
+		pub mod dialogue {
+			#![allow(unused)]
+			pub mod title {
+				pub struct Title;
+			}
+			pub mod nodes {
+				mod Start {
+					#![allow(non_snake_case)]
+					mod title {
+						pub struct Start;
+					}
+				}
+				pub use Start::*;
+			}
+			pub type Counter = usize;
+		}
+	};
+
+	let actual = inline_modules(&files, "dialogue").unwrap();
+	assert_eq!(actual.to_string(), expected.to_string());
+
+	assert!(inline_modules(&files[1..], "dialogue").is_err());
+	assert!(inline_modules(&files[..2], "dialogue").is_err());
+}
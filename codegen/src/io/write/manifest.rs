@@ -0,0 +1,143 @@
+use crate::config::YarnConfig;
+use crate::io::write::output::GeneratedOutput;
+use crate::io::write::util::read_if_exists;
+use anyhow::Result;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+/// Lists every file generated by the last run, relative to `destination_os_path`.
+///
+/// Only files listed here are ever deleted, so hand-written files in the destination folder are never touched.
+pub const MANIFEST_FILE_NAME: &str = ".yarn_spire_manifest";
+
+const MANIFEST_HEADER: &str = "# Files generated by yarn_spire_codegen, stale ones get deleted on the next run. Do not edit.";
+
+/// Rejects absolute paths and `..`, so a tampered manifest cannot point outside the destination folder.
+fn is_inside_destination(relative_path: &Path) -> bool {
+	relative_path
+		.components()
+		.all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn parse_manifest(contents: &str) -> Vec<PathBuf> {
+	contents
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(PathBuf::from)
+		.filter(|path| is_inside_destination(path))
+		.collect()
+}
+
+pub fn read_manifest(cfg: &YarnConfig) -> Result<Vec<PathBuf>> {
+	let contents = read_if_exists(&cfg.destination_os_path.join(MANIFEST_FILE_NAME))?;
+	Ok(contents.as_deref().map(parse_manifest).unwrap_or_default())
+}
+
+/// `still_owned` are files from previous runs that were not deleted, they are kept in the manifest
+/// so they can be cleaned up once overwriting is allowed.
+pub fn write_manifest(cfg: &YarnConfig, output: &GeneratedOutput, still_owned: &[PathBuf]) -> Result<()> {
+	let path = cfg.destination_os_path.join(MANIFEST_FILE_NAME);
+
+	let mut lines =
+		output.files
+		      .iter()
+		      .map(|file| file.relative_path.as_path())
+		      .chain(still_owned.iter().map(PathBuf::as_path))
+		      .map(|path| path.to_string_lossy().replace('\\', "/"))
+		      .collect::<Vec<_>>();
+
+	lines.sort();
+	lines.dedup();
+	lines.insert(0, MANIFEST_HEADER.to_string());
+
+	std::fs::write(&path, lines.join("\n") + "\n")
+		.map_err(|err| Diagnostic::error(ErrorCode::OutputIo, format!("Could not write manifest file at `{path:?}`."))
+			.with_note(err.to_string())
+			.into())
+}
+
+/// Files listed in the manifest of the last run that are absent from `output`, relative to `destination_os_path`,
+/// such as the modules of renamed or deleted nodes.
+///
+/// Files the manifest never listed are not owned by the generator, so they are never returned.
+pub fn find_files_to_delete(output: &GeneratedOutput, cfg: &YarnConfig) -> Result<Vec<PathBuf>> {
+	let generated: HashSet<&Path> =
+		output.files
+		      .iter()
+		      .map(|file| file.relative_path.as_path())
+		      .collect();
+
+	let mut seen = HashSet::new();
+
+	Ok(read_manifest(cfg)?
+		.into_iter()
+		.filter(|path| !generated.contains(path.as_path()))
+		.filter(|path| cfg.destination_os_path.join(path).is_file())
+		.filter(|path| seen.insert(path.clone()))
+		.collect())
+}
+
+/// Removes the folders left empty after deleting files, up to (but not including) `destination_os_path`.
+pub fn remove_empty_parents(cfg: &YarnConfig, relative_path: &Path) {
+	let mut current = relative_path.parent();
+
+	while let Some(dir) = current
+		&& !dir.as_os_str().is_empty() {
+		// Fails if the folder is not empty, which is exactly when we want to stop.
+		if std::fs::remove_dir(cfg.destination_os_path.join(dir)).is_err() {
+			break;
+		}
+
+		current = dir.parent();
+	}
+}
+
+#[test]
+fn test_parse_manifest() {
+	use pretty_assertions::assert_eq;
+
+	let manifest = format!("{MANIFEST_HEADER}\nmod.rs\n\nnodes/Start/mod.rs\n../outside.rs\n/etc/passwd\n");
+
+	assert_eq!(parse_manifest(&manifest), vec![
+		PathBuf::from("mod.rs"),
+		PathBuf::from("nodes/Start/mod.rs"),
+	]);
+}
+
+#[test]
+fn test_only_manifest_files_are_deleted() {
+	use crate::io::cache::ParseCache;
+	use crate::io::read::yarn_file_from_text;
+	use pretty_assertions::assert_eq;
+
+	let destination = std::env::temp_dir().join(format!("yarn_spire_manifest_{}", std::process::id()));
+	std::fs::create_dir_all(destination.join("nodes/Old")).unwrap();
+	std::fs::create_dir_all(destination.join("nodes/Start")).unwrap();
+	std::fs::write(destination.join("nodes/Old/mod.rs"), "").unwrap();
+	std::fs::write(destination.join(MANIFEST_FILE_NAME), format!("{MANIFEST_HEADER}\nnodes/Old/mod.rs\n")).unwrap();
+
+	// `Start` has no commands so `enum_command.rs` is not generated, but the generator never wrote this one.
+	std::fs::write(destination.join("nodes/Start/enum_command.rs"), "// Hand-written").unwrap();
+
+	let mut cfg = YarnConfig::for_tests();
+	cfg.destination_os_path = destination.clone();
+	cfg.allow_overwrite = true;
+
+	let yarn_file = yarn_file_from_text(PathBuf::from("start.yarn"), "title: Start\n---\nHello\n===");
+	let output = crate::generate_from_files(&cfg, vec![yarn_file], &mut ParseCache::default()).unwrap();
+
+	let to_delete = find_files_to_delete(&output, &cfg).unwrap();
+	let summary = output.write(&cfg).unwrap();
+	let old_exists = destination.join("nodes/Old").exists();
+	let hand_written_exists = destination.join("nodes/Start/enum_command.rs").exists();
+	let manifest = read_manifest(&cfg).unwrap();
+	std::fs::remove_dir_all(&destination).unwrap();
+
+	assert_eq!(to_delete, [PathBuf::from("nodes/Old/mod.rs")]);
+	assert_eq!(summary.deleted, 1);
+	assert!(!old_exists);
+	assert!(hand_written_exists);
+	assert!(!manifest.contains(&PathBuf::from("nodes/Start/enum_command.rs")));
+}
@@ -1,56 +1,67 @@
 pub mod util;
 pub mod core_types;
 pub mod nodes;
+pub mod output;
+pub mod inline;
+pub mod check;
+pub mod manifest;
 
 use crate::config::YarnConfig;
+use crate::expressions::declaration_ty::DeclarationTy;
 use crate::parsing::raw::var_declaration::VarDeclaration;
 use crate::parsing::YarnNode;
 use crate::quoting::quotable_types::line_ids::{convert_to_id_nodes, BuiltInCommand, IDFlatLine};
 use crate::quoting::quotable_types::node::IDNode;
 use crate::quoting::quotable_types::scope::IDScope;
-use anyhow::{anyhow, Result};
-use std::collections::HashSet;
+use crate::diagnostic::{Diagnostic, Diagnostics, ErrorCode};
+use anyhow::Result;
+use output::GeneratedOutput;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 
 fn check_nodes_in_jumps(nodes: &[IDNode]) -> Result<()> {
 	let built_nodes: HashSet<&str> = nodes
 		.iter()
-		.map(|node| node.metadata.title.as_str())
+		.map(IDNode::jump_title)
 		.collect();
 
-	let nodes_in_jump = nodes
+	let diagnostics = nodes
 		.iter()
 		.flat_map(|node|
 			node.scopes
 			    .iter()
-			    .flat_map(IDScope::iter_flat_lines)
-			    .filter_map(|line|
-				    if let IDFlatLine::BuiltInCommand(BuiltInCommand::Jump { node_destination_title, .. }) = line {
-					    Some(node_destination_title.as_str())
-				    } else {
-					    None
-				    }))
-		.collect::<Vec<&str>>();
-
-	let nodes_not_found = nodes_in_jump
-		.into_iter()
-		.filter(|node| !built_nodes.contains(node))
+			    .flat_map(IDScope::iter_flat_lines))
+		.filter_map(|line|
+			if let IDFlatLine::BuiltInCommand(
+				| BuiltInCommand::Jump { span, node_destination_title }
+				| BuiltInCommand::Detour { span, node_destination_title, .. }
+			) = line
+				&& !built_nodes.contains(node_destination_title.as_str()) {
+				Some(Diagnostic::error(ErrorCode::UnknownJumpTarget, format!("Node `{node_destination_title}` mentioned in `jump` or `detour` does not exist."))
+					.with_span(span.clone())
+					.with_help("Make sure that the node name is correct, and that the file declaring it is included."))
+			} else {
+				None
+			})
 		.collect::<Vec<_>>();
 
-	if nodes_not_found.is_empty() {
+	if diagnostics.is_empty() {
 		Ok(())
 	} else {
-		Err(anyhow!(
-			"Some nodes mentioned in `jump`(`<<jump [node_name]>>`) commands are not present in the provided files.\n\
-			 Node names: {}\n\
-			 Make sure that the node names are correct.", nodes_not_found.join(", ")))
+		Err(Diagnostics::new(diagnostics).into())
 	}
 }
 
-pub fn generate_and_write(
+pub fn generate(
 	config: &YarnConfig,
+	yarn_files: Vec<PathBuf>,
 	nodes: Vec<YarnNode>,
 	var_declarations: Vec<VarDeclaration>,
-) -> Result<()> {
+	var_types: HashMap<String, Option<DeclarationTy>>,
+) -> Result<GeneratedOutput> {
+	let node_count = nodes.len();
+	let var_declaration_count = var_declarations.len();
+	
 	let id_nodes = convert_to_id_nodes(nodes)?;
 
 	check_nodes_in_jumps(&id_nodes)?;
@@ -60,8 +71,15 @@ pub fn generate_and_write(
 		.map(|node| (node, node.map_lines()))
 		.collect::<Vec<_>>();
 
-	core_types::write_all(config, &id_nodes, &nodes_mapped, &var_declarations)?;
-	nodes::write_all(config, &id_nodes, &nodes_mapped)?;
+	let mut files = core_types::generate_all(config, &id_nodes, &nodes_mapped, &var_declarations, &var_types)?;
+	nodes::generate_all(config, &id_nodes, &nodes_mapped, &mut files)?;
 
-	Ok(())
-}
\ No newline at end of file
+	Ok(GeneratedOutput {
+		files,
+		yarn_files,
+		config_path: config.config_path.clone(),
+		yarn_root_folders: config.yarn_root_folders.clone(),
+		node_count,
+		var_declaration_count,
+	})
+}
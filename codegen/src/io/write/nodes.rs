@@ -1,36 +1,43 @@
 use crate::config::YarnConfig;
-use crate::io::write::util::{delete_file_if_exists, get_or_create_file, write_to_file};
+use crate::io::write::output::GeneratedFile;
+use crate::io::write::util::render_tokens;
 use crate::quoting::core_types::nodes;
-use crate::quoting::quotable_types::node::{IDNode, LinesMap};
+use crate::quoting::quotable_types::node::{node_groups, IDNode, LinesMap};
 use anyhow::Result;
+use std::path::PathBuf;
 use nodes::{enums, title};
 
-fn write_all_nodes_root(cfg: &YarnConfig, nodes: &[IDNode]) -> Result<()> {
+fn generate_all_nodes_root(nodes: &[IDNode]) -> Result<GeneratedFile> {
 	let tokens = nodes::tokens_all_nodes_root(nodes);
-	let path = cfg.destination_os_path.join("nodes/mod.rs");
-	let file = get_or_create_file(&path, cfg.allow_overwrite)?;
-	write_to_file(&path, file, tokens)
+	render_tokens("nodes/mod.rs", tokens)
 }
 
-fn write_node_specific_roots(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<()> {
+fn generate_node_specific_roots(nodes_mapped: &[(&IDNode, LinesMap)], files: &mut Vec<GeneratedFile>) -> Result<()> {
 	nodes_mapped.iter().try_for_each(|(node, lines_map)| {
 		let tokens = nodes::tokens_node_root(node, lines_map);
-		let path = cfg.destination_os_path.join(format!("nodes/{title}/mod.rs", title = &node.metadata.title));
-		let file = get_or_create_file(&path, cfg.allow_overwrite)?;
-		write_to_file(&path, file, tokens)
+		let path = format!("nodes/{title}/mod.rs", title = &node.metadata.title);
+		files.push(render_tokens(path, tokens)?);
+		Ok(())
 	})
 }
 
-fn write_title_modules(cfg: &YarnConfig, nodes: &[IDNode]) -> Result<()> {
+fn generate_title_modules(cfg: &YarnConfig, nodes: &[IDNode], files: &mut Vec<GeneratedFile>) -> Result<()> {
 	let inferred_tracking = title::infer_all_nodes_tracking(nodes)?;
 
+	for group in node_groups(nodes) {
+		let tracking = title::infer_group_tracking(&group, &inferred_tracking);
+		let tokens = title::group_tokens(cfg, &group, tracking);
+		files.push(render_tokens(format!("nodes/{title}/title.rs", title = group.title), tokens)?);
+		files.push(render_tokens(format!("nodes/{title}/mod.rs", title = group.title), nodes::tokens_group_root(group.title))?);
+	}
+
 	inferred_tracking
 		.into_iter()
 		.try_for_each(|(node, tracking)| {
 			let tokens = title::all_tokens(cfg, node, tracking);
-			let path = cfg.destination_os_path.join(format!("nodes/{title}/title.rs", title = &node.metadata.title));
-			let file = get_or_create_file(&path, cfg.allow_overwrite)?;
-			write_to_file(&path, file, tokens)
+			let path = format!("nodes/{title}/title.rs", title = &node.metadata.title);
+			files.push(render_tokens(path, tokens)?);
+			Ok(())
 		})
 }
 
@@ -53,85 +60,99 @@ fn write_enum_any_modules(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]
 }
 */
 
-fn write_enum_command_modules(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<()> {
+fn generate_enum_command_modules(
+	cfg: &YarnConfig,
+	nodes_mapped: &[(&IDNode, LinesMap)],
+	files: &mut Vec<GeneratedFile>,
+) -> Result<()> {
 	nodes_mapped.iter().try_for_each(|(node, lines_map)| {
-		let path = cfg
-			.destination_os_path
-			.join(format!("nodes/{title}/enum_command.rs", title = &node.metadata.title));
+		let path = PathBuf::from(format!("nodes/{title}/enum_command.rs", title = &node.metadata.title));
 
 		if let Some(tokens) = enums::command::all_tokens(cfg, node, lines_map) {
-			let file = get_or_create_file(&path, cfg.allow_overwrite)?;
-			write_to_file(&path, file, tokens)
-		} else if cfg.allow_overwrite {
-			delete_file_if_exists(&path)
-		} else {
-			Ok(())
+			files.push(render_tokens(path, tokens)?);
 		}
+		
+		Ok(())
 	})
 }
 
-fn write_enum_options_fork_modules(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<()> {
+fn generate_enum_options_fork_modules(
+	cfg: &YarnConfig,
+	nodes_mapped: &[(&IDNode, LinesMap)],
+	files: &mut Vec<GeneratedFile>,
+) -> Result<()> {
 	nodes_mapped.iter().try_for_each(|(node, lines_map)| {
-		let path = cfg
-			.destination_os_path
-			.join(format!("nodes/{title}/enum_options_fork.rs", title = &node.metadata.title));
+		let path = PathBuf::from(format!("nodes/{title}/enum_options_fork.rs", title = &node.metadata.title));
 
 		if let Some(tokens) = enums::options_fork::all_tokens(cfg, node, lines_map) {
-			let file = get_or_create_file(&path, cfg.allow_overwrite)?;
-			write_to_file(&path, file, tokens)
-		} else if cfg.allow_overwrite {
-			delete_file_if_exists(&path)
-		} else {
-			Ok(())
+			files.push(render_tokens(path, tokens)?);
 		}
+		
+		Ok(())
 	})
 }
 
-fn write_enum_speech_modules(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<()> {
+fn generate_enum_speech_modules(
+	cfg: &YarnConfig,
+	nodes_mapped: &[(&IDNode, LinesMap)],
+	files: &mut Vec<GeneratedFile>,
+) -> Result<()> {
 	nodes_mapped.iter().try_for_each(|(node, lines_map)| {
-		let path = cfg
-			.destination_os_path
-			.join(format!("nodes/{title}/enum_speech.rs", title = &node.metadata.title));
+		let path = PathBuf::from(format!("nodes/{title}/enum_speech.rs", title = &node.metadata.title));
 
 		if let Some(tokens) = enums::speech::all_tokens(cfg, node, lines_map) {
-			let file = get_or_create_file(&path, cfg.allow_overwrite)?;
-			write_to_file(&path, file, tokens)
-		} else if cfg.allow_overwrite {
-			delete_file_if_exists(&path)
-		} else {
-			Ok(())
+			files.push(render_tokens(path, tokens)?);
 		}
+		
+		Ok(())
 	})
 }
 
-fn write_enum_option_line_modules(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Result<()> {
+fn generate_enum_option_line_modules(
+	cfg: &YarnConfig,
+	nodes_mapped: &[(&IDNode, LinesMap)],
+	files: &mut Vec<GeneratedFile>,
+) -> Result<()> {
 	nodes_mapped.iter().try_for_each(|(node, lines_map)| {
-		let path = cfg
-			.destination_os_path
-			.join(format!("nodes/{title}/enum_option_line.rs", title = &node.metadata.title));
+		let path = PathBuf::from(format!("nodes/{title}/enum_option_line.rs", title = &node.metadata.title));
 
 		if let Some(tokens) = enums::option_line::all_tokens(cfg, node, lines_map) {
-			let file = get_or_create_file(&path, cfg.allow_overwrite)?;
-			write_to_file(&path, file, tokens)
-		} else if cfg.allow_overwrite {
-			delete_file_if_exists(&path)
-		} else {
-			Ok(())
+			files.push(render_tokens(path, tokens)?);
+		}
+		
+		Ok(())
+	})
+}
+
+fn generate_enum_detour_modules(
+	cfg: &YarnConfig,
+	nodes_mapped: &[(&IDNode, LinesMap)],
+	files: &mut Vec<GeneratedFile>,
+) -> Result<()> {
+	nodes_mapped.iter().try_for_each(|(node, lines_map)| {
+		let path = PathBuf::from(format!("nodes/{title}/enum_detour.rs", title = &node.metadata.title));
+
+		if let Some(tokens) = enums::detour::all_tokens(cfg, node, lines_map) {
+			files.push(render_tokens(path, tokens)?);
 		}
+		
+		Ok(())
 	})
 }
 
-pub fn write_all(
+pub fn generate_all(
 	cfg: &YarnConfig,
 	nodes: &[IDNode],
 	nodes_mapped: &[(&IDNode, LinesMap)],
+	files: &mut Vec<GeneratedFile>,
 ) -> Result<()> {
-	write_all_nodes_root(cfg, nodes)?;
-	write_node_specific_roots(cfg, nodes_mapped)?;
-	write_title_modules(cfg, nodes)?;
-	//write_enum_any_modules(cfg, nodes_mapped)?;
-	write_enum_command_modules(cfg, nodes_mapped)?;
-	write_enum_options_fork_modules(cfg, nodes_mapped)?;
-	write_enum_speech_modules(cfg, nodes_mapped)?;
-	write_enum_option_line_modules(cfg, nodes_mapped)
-}
\ No newline at end of file
+	files.push(generate_all_nodes_root(nodes)?);
+	generate_node_specific_roots(nodes_mapped, files)?;
+	generate_title_modules(cfg, nodes, files)?;
+	//generate_enum_any_modules(cfg, nodes_mapped)?;
+	generate_enum_command_modules(cfg, nodes_mapped, files)?;
+	generate_enum_options_fork_modules(cfg, nodes_mapped, files)?;
+	generate_enum_speech_modules(cfg, nodes_mapped, files)?;
+	generate_enum_option_line_modules(cfg, nodes_mapped, files)?;
+	generate_enum_detour_modules(cfg, nodes_mapped, files)
+}
@@ -0,0 +1,107 @@
+use crate::config::YarnConfig;
+use crate::io::write::check::{find_outdated_files, OutdatedFile};
+use crate::io::write::inline::inline_modules;
+use crate::io::write::manifest::{find_files_to_delete, remove_empty_parents, write_manifest};
+use crate::io::write::util::{delete_file_if_exists, get_or_create_file, read_if_exists, write_to_file};
+use anyhow::Result;
+use proc_macro2::TokenStream;
+use std::path::PathBuf;
+
+/// A single rendered Rust file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedFile {
+	/// Path relative to `destination_os_path`.
+	pub relative_path: PathBuf,
+	pub contents: String,
+}
+
+/// Everything produced by [crate::generate], kept in memory until [GeneratedOutput::write] is called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedOutput {
+	pub files: Vec<GeneratedFile>,
+	/// The yarn files that were read to produce this output.
+	pub yarn_files: Vec<PathBuf>,
+	pub config_path: PathBuf,
+	pub yarn_root_folders: Vec<PathBuf>,
+	pub node_count: usize,
+	pub var_declaration_count: usize,
+}
+
+/// How many files [GeneratedOutput::write] touched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteSummary {
+	pub written: usize,
+	pub unchanged: usize,
+	pub deleted: usize,
+}
+
+impl GeneratedOutput {
+	/// Writes all files to `destination_os_path`, respecting `allow_overwrite`.
+	///
+	/// When overwriting is allowed, files generated by previous runs that are no longer part of the output
+	/// are deleted, see [crate::MANIFEST_FILE_NAME].
+	///
+	/// Files whose contents on disk are already identical to the generated ones are left untouched,
+	/// so their modification time does not change and tools watching them don't rebuild needlessly.
+	pub fn write(&self, cfg: &YarnConfig) -> Result<WriteSummary> {
+		let mut summary = WriteSummary::default();
+		
+		for generated in &self.files {
+			let path = cfg.destination_os_path.join(&generated.relative_path);
+			
+			if read_if_exists(&path)?.is_some_and(|on_disk| on_disk == generated.contents) {
+				summary.unchanged += 1;
+				continue;
+			}
+			
+			let file = get_or_create_file(&path, cfg.allow_overwrite)?;
+			write_to_file(&path, file, &generated.contents)?;
+			summary.written += 1;
+		}
+
+		let to_delete = find_files_to_delete(self, cfg)?;
+
+		if cfg.allow_overwrite {
+			for relative_path in &to_delete {
+				if delete_file_if_exists(&cfg.destination_os_path.join(relative_path))? {
+					remove_empty_parents(cfg, relative_path);
+					summary.deleted += 1;
+				}
+			}
+
+			write_manifest(cfg, self, &[])?;
+		} else {
+			write_manifest(cfg, self, &to_delete)?;
+		}
+
+		Ok(summary)
+	}
+
+	/// Lists the files in `destination_os_path` that differ from this output, without writing anything.
+	///
+	/// An empty list means the generated code is up to date.
+	pub fn check(&self, cfg: &YarnConfig) -> Result<Vec<OutdatedFile>> {
+		find_outdated_files(self, cfg)
+	}
+
+	/// Prints `cargo:rerun-if-changed` for the config file, the yarn root folders and every yarn file read.
+	///
+	/// Only meaningful when called from a `build.rs` script.
+	/// The root folders are included so that newly created yarn files also trigger a re-run.
+	pub fn emit_rerun_if_changed(&self) {
+		println!("cargo:rerun-if-changed={}", self.config_path.display());
+		for root in &self.yarn_root_folders {
+			println!("cargo:rerun-if-changed={}", root.display());
+		}
+
+		for path in &self.yarn_files {
+			println!("cargo:rerun-if-changed={}", path.display());
+		}
+	}
+
+	/// Nests all generated files into a single `pub mod module_name { ... }` item,
+	/// so the output can be expanded by a procedural macro instead of being written to disk.
+	pub fn to_module_tokens(&self, module_name: &str) -> Result<TokenStream> {
+		inline_modules(&self.files, module_name)
+	}
+}
@@ -1,12 +1,20 @@
 use std::fs::File;
-use std::path::PathBuf;
-use anyhow::{Result, anyhow};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use crate::diagnostic::{Diagnostic, ErrorCode};
 use genco::lang::Rust;
 use genco::lang::rust::Tokens;
+use crate::io::write::output::GeneratedFile;
 
 pub fn get_or_create_file(path: &PathBuf, allow_overwrite: bool) -> Result<File> {
 	if let Some(parent) = path.parent() {
-		std::fs::create_dir_all(parent)?;
+		std::fs::create_dir_all(parent)
+			.map_err(|err| Diagnostic::error(ErrorCode::OutputIo, format!(
+				"Directory does not exist or could not be created: {parent:?}"))
+				.with_note(err.to_string())
+				.with_help("This program might need additional permissions to access or create the directory.")
+				.with_help("Try running the program as an administrator."))?;
 	}
 	
 	if !path.exists() {
@@ -15,40 +23,69 @@ pub fn get_or_create_file(path: &PathBuf, allow_overwrite: bool) -> Result<File>
 			.write(true)
 			.truncate(true)
 			.open(path)
-			.map_err(|err| anyhow!(
-				"Could not create file at `{path:?}`.\n\
-				 Error: {err}"))
+			.map_err(|err| Diagnostic::error(ErrorCode::OutputIo, format!("Could not create file at `{path:?}`."))
+				.with_note(err.to_string())
+				.into())
 	} else if !allow_overwrite {
-		Err(anyhow!(
-			"File already exists at `{path:?}`, but `allow_overwrite` is false.\n\n\
-			 Help: You can enable overwriting by changing `allow_overwrite` to `true` in the config file."))
+		Err(Diagnostic::error(ErrorCode::OverwriteNotAllowed, format!(
+			"File already exists at `{path:?}`, but `allow_overwrite` is false."))
+			.with_help("You can enable overwriting by changing `allow_overwrite` to `true` in the config file.")
+			.into())
 	} else {
 		File::options()
 			.create(false)
 			.write(true)
 			.truncate(true)
 			.open(path)
-			.map_err(|err| anyhow!(
-				"Could not open file at `{path:?}`.\n\
-				 Error: {err}"))
+			.map_err(|err| Diagnostic::error(ErrorCode::OutputIo, format!("Could not open file at `{path:?}`."))
+				.with_note(err.to_string())
+				.into())
 	}
 }
 
-pub fn write_to_file(path: &PathBuf, mut file: File, tokens: Tokens) -> Result<()> {
+pub fn render_tokens(relative_path: impl Into<PathBuf>, tokens: Tokens) -> Result<GeneratedFile> {
+	let relative_path = relative_path.into();
 	let fmt = genco::fmt::Config::from_lang::<Rust>().with_indentation(genco::fmt::Indentation::Space(4));
 	let config = genco::lang::rust::Config::default();
-	let mut writer = genco::fmt::IoWriter::new(&mut file);
+	let mut writer = genco::fmt::FmtWriter::new(String::new());
 	tokens.format_file(&mut writer.as_formatter(&fmt), &config)
-	      .map_err(|err| anyhow!("Could not write to `{path:?}`.\nError: {err}"))
+	      .map_err(|err| Diagnostic::error(ErrorCode::RenderFailed, format!("Could not render `{relative_path:?}`."))
+		      .with_note(err.to_string()))?;
+	
+	Ok(GeneratedFile {
+		relative_path,
+		contents: writer.into_inner(),
+	})
+}
+
+pub fn write_to_file(path: &Path, mut file: File, contents: &str) -> Result<()> {
+	file.write_all(contents.as_bytes())
+	    .map_err(|err| Diagnostic::error(ErrorCode::OutputIo, format!("Could not write to `{path:?}`."))
+		    .with_note(err.to_string())
+		    .into())
 }
 
-pub fn delete_file_if_exists(path: &PathBuf) -> Result<()> {
+/// Returns whether the file existed.
+pub fn delete_file_if_exists(path: &PathBuf) -> Result<bool> {
 	if path.exists() {
 		std::fs::remove_file(path)
-			.map_err(|err| anyhow!(
-				"Could not delete file at `{path:?}`.\n\
-				 Error: {err}"))
+			.map_err(|err| Diagnostic::error(ErrorCode::OutputIo, format!("Could not delete file at `{path:?}`."))
+				.with_note(err.to_string()))?;
+		
+		Ok(true)
 	} else {
-		Ok(())
+		Ok(false)
+	}
+}
+
+pub fn read_if_exists(path: &Path) -> Result<Option<String>> {
+	match std::fs::read_to_string(path) {
+		Ok(contents) => Ok(Some(contents)),
+		Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+		Err(err) => {
+			Err(Diagnostic::error(ErrorCode::OutputIo, format!("Could not read file at `{path:?}`."))
+				.with_note(err.to_string())
+				.into())
+		},
 	}
 }
\ No newline at end of file
@@ -0,0 +1,189 @@
+#![allow(clippy::type_complexity)]
+#![feature(let_chains)]
+#![feature(if_let_guard)]
+#![feature(string_remove_matches)]
+#![feature(assert_matches)]
+#![feature(coroutines)]
+#![feature(pattern)]
+#![feature(stmt_expr_attributes)]
+#![feature(proc_macro_expand)]
+#![feature(iterator_try_collect)]
+#![feature(extract_if)]
+#![feature(coroutine_trait)]
+#![feature(iter_from_coroutine)]
+#![feature(gen_blocks)]
+
+extern crate core;
+
+mod expressions;
+mod parsing;
+mod io;
+mod config;
+mod diagnostic;
+mod format;
+mod lint;
+mod lsp;
+mod quoting;
+mod type_check;
+
+use anyhow::Result;
+use expressions::declaration_ty::DeclarationTy;
+use std::collections::HashMap;
+use std::path::Path;
+
+pub use diagnostic::{into_diagnostics, Diagnostic, Diagnostics, ErrorCode, Severity, Span};
+pub use config::{ConfigOverrides, DeserializableConfig, YarnConfig, DEFAULT_CONFIG};
+pub use io::cache::{ParseCache, CACHE_FILE_NAME};
+pub use io::watch::YarnWatcher;
+pub use io::write::check::{OutdatedFile, OutdatedReason};
+pub use io::write::manifest::MANIFEST_FILE_NAME;
+pub use io::write::output::{GeneratedFile, GeneratedOutput, WriteSummary};
+pub use format::{format_yarn, FormattedFile};
+pub use lint::{LintConfig, LintLevel, ENTRY_TAG};
+pub use lsp::run_language_server;
+
+type LineNumber = usize;
+type Indent = isize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct UnparsedLine {
+	line_number: LineNumber,
+	text: String,
+}
+
+/// Reads and parses all the yarn files described by `config`, then generates the Rust code for them.
+///
+/// Nothing is written to the file-system, use [GeneratedOutput::write] for that.
+pub fn generate(config: &YarnConfig) -> Result<GeneratedOutput> {
+	generate_cached(config, &mut ParseCache::default())
+}
+
+/// Same as [generate], but only parses the yarn files whose contents changed since they were last stored in `cache`.
+pub fn generate_cached(config: &YarnConfig, cache: &mut ParseCache) -> Result<GeneratedOutput> {
+	let yarn_files =
+		io::read::find_and_read_yarn_files(config)?;
+	
+	generate_from_files(config, yarn_files, cache)
+}
+
+fn generate_from_files(config: &YarnConfig, yarn_files: Vec<io::read::YarnFile>, cache: &mut ParseCache) -> Result<GeneratedOutput> {
+	let yarn_paths =
+		yarn_files
+			.iter()
+			.map(|file| file.path.clone())
+			.collect();
+	
+	let (nodes, var_declarations, var_types) =
+		parse_and_check(config, yarn_files, cache)?;
+	
+	io::write::generate(config, yarn_paths, nodes, var_declarations, var_types)
+}
+
+fn parse_and_check(
+	config: &YarnConfig,
+	yarn_files: Vec<io::read::YarnFile>,
+	cache: &mut ParseCache,
+) -> Result<(Vec<parsing::YarnNode>, Vec<parsing::raw::var_declaration::VarDeclaration>, HashMap<String, Option<DeclarationTy>>)> {
+	let (mut nodes, var_declarations) =
+		cache.parse_all(yarn_files, config.max_errors)?;
+	
+	let duplicate_errors = parsing::duplicates::find_duplicates(&nodes, &var_declarations);
+	if !duplicate_errors.is_empty() {
+		return Err(Diagnostics::limited(duplicate_errors, config.max_errors).into());
+	}
+	
+	let (var_types, type_errors) = type_check::check_nodes(&nodes, &var_declarations);
+	if !type_errors.is_empty() {
+		return Err(Diagnostics::limited(type_errors, config.max_errors).into());
+	}
+	
+	type_check::widen_numbers(&mut nodes, &var_types);
+	
+	Ok((nodes, var_declarations, var_types))
+}
+
+/// Reads and parses all the yarn files described by `config`, then checks them with the rules of `config.lints`.
+///
+/// Returns the warnings, and the errors of rules set to `deny`. 
+/// Files that cannot be parsed or type checked are reported as `Err`, like in [generate].
+pub fn lint(config: &YarnConfig) -> Result<Vec<Diagnostic>> {
+	let yarn_files =
+		io::read::find_and_read_yarn_files(config)?;
+	
+	let (nodes, var_declarations, _) =
+		parse_and_check(config, yarn_files, &mut ParseCache::default())?;
+	
+	let id_nodes = quoting::quotable_types::line_ids::convert_to_id_nodes(nodes)?;
+	Ok(lint::lint_nodes(&id_nodes, &var_declarations, &config.lints))
+}
+
+/// Formats every yarn file described by `config`, see [format_yarn].
+///
+/// Returns the files whose contents would change, nothing is written, use [FormattedFile::write] for that.
+/// Files that cannot be parsed are reported as `Err`, after trying every file.
+pub fn format(config: &YarnConfig) -> Result<Vec<FormattedFile>> {
+	let mut unformatted = vec![];
+	let mut errors = vec![];
+	
+	for path in io::read::find_yarn_paths(config)? {
+		let text = io::read::read_yarn_text(&path)?;
+		
+		match format_yarn(&path, &text) {
+			Ok(formatted) if formatted != text => {
+				let diff = io::write::check::unified_diff(Path::new(&config.source_path(&path)), &text, &formatted);
+				unformatted.push(FormattedFile { path, formatted, diff });
+			},
+			Ok(_) => {},
+			Err(err) => errors.extend(into_diagnostics(err)),
+		}
+	}
+	
+	if errors.is_empty() {
+		Ok(unformatted)
+	} else {
+		Err(Diagnostics::limited(errors, config.max_errors).into())
+	}
+}
+
+fn build_project(config: &YarnConfig) -> Result<GeneratedOutput> {
+	let cache_path = config.destination_os_path.join(CACHE_FILE_NAME);
+	let mut cache = ParseCache::load(&cache_path)?;
+	
+	let output = generate_cached(config, &mut cache)?;
+	output.emit_rerun_if_changed();
+	output.write(config)?;
+	cache.save(&cache_path)?;
+	Ok(output)
+}
+
+/// Entry point for `build.rs` scripts.
+///
+/// Reads the config file at `config_path`, generates the code of every project and writes it to their destination folders.
+/// Then tells Cargo to re-run the build script whenever the config file or any of the yarn files change.
+///
+/// All projects are generated even if some of them fail, the returned error lists every failure.
+///
+/// ```no_run
+/// // Inside `fn main()` of your `build.rs`:
+/// yarn_spire_codegen::build("yarn_project.toml").unwrap();
+/// ```
+pub fn build(config_path: impl AsRef<std::path::Path>) -> Result<Vec<GeneratedOutput>> {
+	let projects =
+		YarnConfig::parse_projects(config_path.as_ref(), &ConfigOverrides::default(), None)?;
+	
+	let mut outputs = vec![];
+	let mut errors = vec![];
+	
+	for config in &projects {
+		match build_project(config) {
+			Ok(output) => outputs.push(output),
+			Err(err) => errors.push(format!("In project `{name}`:\n{err}", name = config.name)),
+		}
+	}
+	
+	if errors.is_empty() {
+		Ok(outputs)
+	} else {
+		Err(anyhow::anyhow!("{}", errors.join("\n\n")))
+	}
+}
@@ -0,0 +1,508 @@
+use crate::diagnostic::{Diagnostic, ErrorCode, Severity, Span};
+use crate::expressions::yarn_expr::YarnExpr;
+use crate::parsing::raw::speech::Speaker;
+use crate::parsing::raw::var_declaration::VarDeclaration;
+use crate::quoting::quotable_types::line_ids::{BuiltInCommand, IDFlatLine, IDFlow};
+use crate::quoting::quotable_types::node::IDNode;
+use crate::quoting::quotable_types::scope::IDScope;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Nodes with this tag are started by the game, so `unreachable_node` ignores them.
+pub const ENTRY_TAG: &str = "entry";
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+	Allow,
+	#[default]
+	Warn,
+	/// Reported as an error, `lint` fails if any is found.
+	Deny,
+}
+
+/// The `[lints]` table of the config file, every rule is `warn` by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+	/// Lines after an unconditional `<<jump>>` or `<<stop>>`.
+	pub unreachable_code: LintLevel,
+	/// Declared variables that are never read nor written.
+	pub unused_variable: LintLevel,
+	/// Variables written by `<<set>>` that are never read.
+	pub write_only_variable: LintLevel,
+	/// Nodes that no `<<jump>>` reaches, and that are not tagged as entry points.
+	pub unreachable_node: LintLevel,
+	/// Option conditions that don't depend on any variable or function.
+	pub constant_condition: LintLevel,
+}
+
+/// The `<<jump>>` or `<<stop>>` that ends the flow of a scope.
+struct FlowEnd {
+	span: Span,
+	description: String,
+}
+
+#[derive(Default)]
+struct Reachability {
+	ended_at: Option<FlowEnd>,
+	/// Only the first unreachable line after the end is reported.
+	reported: bool,
+}
+
+struct Linter<'a> {
+	config: &'a LintConfig,
+	diagnostics: Vec<Diagnostic>,
+	read_vars: HashSet<&'a str>,
+	/// Where each variable is first written, in the order they are found.
+	written_vars: Vec<(&'a str, Span)>,
+	/// Titles reached by a `<<jump>>` or `<<detour>>` from another node.
+	jump_targets: HashSet<&'a str>,
+}
+
+fn flat_line_span(line: &IDFlatLine) -> &Span {
+	match line {
+		IDFlatLine::Speech(speech) => &speech.span,
+		IDFlatLine::CustomCommand(command) => &command.span,
+		| IDFlatLine::BuiltInCommand(BuiltInCommand::Set { span, .. })
+		| IDFlatLine::BuiltInCommand(BuiltInCommand::Jump { span, .. })
+		| IDFlatLine::BuiltInCommand(BuiltInCommand::Detour { span, .. })
+		| IDFlatLine::BuiltInCommand(BuiltInCommand::Return { span })
+		| IDFlatLine::BuiltInCommand(BuiltInCommand::Stop { span }) => span,
+	}
+}
+
+/// Literals and operators only, so the result is the same every time.
+fn is_constant(expr: &YarnExpr) -> bool {
+	expr.iter_exprs()
+	    .all(|sub_expr|
+		    matches!(sub_expr,
+			    | YarnExpr::Lit(_)
+			    | YarnExpr::Parenthesis(_)
+			    | YarnExpr::UnaryOp { .. }
+			    | YarnExpr::BinaryOp { .. }
+			    | YarnExpr::Cast { .. }))
+}
+
+impl<'a> Linter<'a> {
+	fn emit(&mut self, level: LintLevel, mut diagnostic: Diagnostic) {
+		match level {
+			LintLevel::Allow => {},
+			LintLevel::Warn => self.diagnostics.push(diagnostic),
+			LintLevel::Deny => {
+				diagnostic.severity = Severity::Error;
+				self.diagnostics.push(diagnostic);
+			},
+		}
+	}
+
+	fn read_expr(&mut self, expr: &'a YarnExpr) {
+		for sub_expr in expr.iter_exprs() {
+			if let YarnExpr::GetVar(var_name) = sub_expr {
+				self.read_vars.insert(var_name);
+			}
+		}
+	}
+
+	fn check_reachable(&mut self, reachability: &mut Reachability, span: &Span) {
+		let Some(end) = &reachability.ended_at
+			else { return };
+
+		if reachability.reported {
+			return;
+		}
+
+		reachability.reported = true;
+
+		let diagnostic =
+			Diagnostic::warning(ErrorCode::UnreachableCode, "Unreachable line.")
+				.with_span(span.clone())
+				.with_note(format!("Any line after {} at {} never runs.", end.description, end.span))
+				.with_help("Remove the unreachable lines, or move them before it.");
+
+		self.emit(self.config.unreachable_code, diagnostic);
+	}
+
+	/// Returns where the scope's flow always ends, if it does.
+	fn visit_nested(&mut self, node_title: &'a str, file: &'a Path, scope: Option<&'a IDScope>) -> Option<FlowEnd> {
+		let mut reachability = Reachability::default();
+
+		if let Some(scope) = scope {
+			self.visit_flows(node_title, file, &scope.flows, &mut reachability);
+		}
+
+		reachability.ended_at
+	}
+
+	fn visit_flows(&mut self, node_title: &'a str, file: &'a Path, flows: &'a [IDFlow], reachability: &mut Reachability) {
+		for flow in flows {
+			match flow {
+				IDFlow::Flat(lines) => {
+					for line in lines {
+						self.check_reachable(reachability, flat_line_span(line));
+						self.visit_flat_line(node_title, line, reachability);
+					}
+				},
+				IDFlow::OptionsFork(options_fork) => {
+					let fork_span = &options_fork.options.get(0).expect("forks have at least one option").0.span;
+					self.check_reachable(reachability, fork_span);
+
+					let mut all_end = true;
+
+					for (option, scope_option) in options_fork.options.iter() {
+						for arg in &option.text.1 {
+							self.read_expr(arg);
+						}
+
+						if option.once_key.is_some() {
+							all_end = false;
+						}
+
+						if let Some(condition) = &option.if_condition {
+							// An unavailable option doesn't end the flow.
+							all_end = false;
+							self.read_expr(condition);
+
+							if is_constant(condition) {
+								let diagnostic =
+									Diagnostic::warning(ErrorCode::ConstantCondition, "Option condition is constant.")
+										.with_span(option.span.clone())
+										.with_note("The condition does not depend on any variable or function, so the option is either always or never available.")
+										.with_help("Remove the condition, or make it depend on a variable.");
+
+								self.emit(self.config.constant_condition, diagnostic);
+							}
+						}
+
+						let option_end = self.visit_nested(node_title, file, scope_option.as_deref());
+						all_end &= option_end.is_some();
+					}
+
+					if all_end && reachability.ended_at.is_none() {
+						reachability.ended_at = Some(FlowEnd {
+							span: fork_span.clone(),
+							description: "the options whose every choice ends with `<<jump>>` or `<<stop>>`".to_string(),
+						});
+					}
+				},
+				IDFlow::LineGroup(line_group) => {
+					let group_span = &line_group.lines.get(0).expect("line groups have at least one line").0.speech.span;
+					self.check_reachable(reachability, group_span);
+
+					// The group is skipped when every line's condition fails.
+					let mut all_end = line_group.lines.iter().any(|(line, _)| line.condition.is_none());
+
+					for (line, scope_option) in line_group.lines.iter() {
+						if let Some(Speaker::Variable(var_name)) = &line.speech.speaker {
+							self.read_vars.insert(var_name);
+						}
+
+						for arg in &line.speech.text.1 {
+							self.read_expr(arg);
+						}
+
+						if let Some(condition) = &line.condition {
+							self.read_expr(condition);
+						}
+
+						all_end &= self.visit_nested(node_title, file, scope_option.as_deref()).is_some();
+					}
+
+					if all_end && reachability.ended_at.is_none() {
+						reachability.ended_at = Some(FlowEnd {
+							span: group_span.clone(),
+							description: "the line group whose every line ends with `<<jump>>` or `<<stop>>`".to_string(),
+						});
+					}
+				},
+				IDFlow::IfBranch(if_branch) => {
+					let (if_, if_scope) = &if_branch.if_;
+					let if_span = Span::line(file, if_.line_number);
+					self.check_reachable(reachability, &if_span);
+					self.read_expr(&if_.condition);
+
+					let mut all_end = self.visit_nested(node_title, file, if_scope.as_deref()).is_some();
+
+					for (else_if, scope_option) in &if_branch.else_ifs {
+						self.read_expr(&else_if.condition);
+						all_end &= self.visit_nested(node_title, file, scope_option.as_deref()).is_some();
+					}
+
+					match &if_branch.else_ {
+						Some((_, scope_option)) => {
+							all_end &= self.visit_nested(node_title, file, scope_option.as_deref()).is_some();
+						},
+						None => {
+							all_end = false;
+						},
+					}
+
+					if all_end && reachability.ended_at.is_none() {
+						reachability.ended_at = Some(FlowEnd {
+							span: if_span,
+							description: "the `<<if>>` whose every branch ends with `<<jump>>` or `<<stop>>`".to_string(),
+						});
+					}
+				},
+				IDFlow::OnceBranch(once_branch) => {
+					let (once_, once_scope) = &once_branch.once_;
+					let once_span = Span::line(file, once_.line_number);
+					self.check_reachable(reachability, &once_span);
+
+					if let Some(condition) = &once_.condition {
+						self.read_expr(condition);
+					}
+
+					let mut all_end = self.visit_nested(node_title, file, once_scope.as_deref()).is_some();
+
+					match &once_branch.else_ {
+						Some((_, scope_option)) => {
+							all_end &= self.visit_nested(node_title, file, scope_option.as_deref()).is_some();
+						},
+						None => {
+							all_end = false;
+						},
+					}
+
+					if all_end && reachability.ended_at.is_none() {
+						reachability.ended_at = Some(FlowEnd {
+							span: once_span,
+							description: "the `<<once>>` whose both branches end with `<<jump>>` or `<<stop>>`".to_string(),
+						});
+					}
+				},
+			}
+		}
+	}
+
+	fn visit_flat_line(&mut self, node_title: &'a str, line: &'a IDFlatLine, reachability: &mut Reachability) {
+		match line {
+			IDFlatLine::Speech(speech) => {
+				if let Some(Speaker::Variable(var_name)) = &speech.speaker {
+					self.read_vars.insert(var_name);
+				}
+
+				for arg in &speech.text.1 {
+					self.read_expr(arg);
+				}
+			},
+			IDFlatLine::CustomCommand(command) => {
+				for arg in &command.args {
+					self.read_expr(arg);
+				}
+			},
+			IDFlatLine::BuiltInCommand(BuiltInCommand::Set { span, var_name, value, .. }) => {
+				self.read_expr(value);
+
+				if !self.written_vars.iter().any(|(written, _)| written == var_name) {
+					self.written_vars.push((var_name, span.clone()));
+				}
+			},
+			IDFlatLine::BuiltInCommand(BuiltInCommand::Jump { span, node_destination_title }) => {
+				// A node jumping to itself is still unreachable from the others.
+				if node_destination_title != node_title {
+					self.jump_targets.insert(node_destination_title);
+				}
+
+				if reachability.ended_at.is_none() {
+					reachability.ended_at = Some(FlowEnd {
+						span: span.clone(),
+						description: format!("`<<jump {node_destination_title}>>`"),
+					});
+				}
+			},
+			IDFlatLine::BuiltInCommand(BuiltInCommand::Detour { node_destination_title, .. }) => {
+				// The detour comes back, so the lines after it are still reachable.
+				if node_destination_title != node_title {
+					self.jump_targets.insert(node_destination_title);
+				}
+			},
+			IDFlatLine::BuiltInCommand(BuiltInCommand::Return { span }) => {
+				if reachability.ended_at.is_none() {
+					reachability.ended_at = Some(FlowEnd {
+						span: span.clone(),
+						description: "`<<return>>`".to_string(),
+					});
+				}
+			},
+			IDFlatLine::BuiltInCommand(BuiltInCommand::Stop { span }) => {
+				if reachability.ended_at.is_none() {
+					reachability.ended_at = Some(FlowEnd {
+						span: span.clone(),
+						description: "`<<stop>>`".to_string(),
+					});
+				}
+			},
+		}
+	}
+
+	fn check_variables(&mut self, var_declarations: &[VarDeclaration]) {
+		for declaration in var_declarations {
+			let var_name = declaration.var_name.as_str();
+
+			if self.read_vars.contains(var_name) || self.written_vars.iter().any(|(written, _)| *written == var_name) {
+				continue;
+			}
+
+			let diagnostic =
+				Diagnostic::warning(ErrorCode::UnusedVariable, format!("Variable `${var_name}` is declared but never used."))
+					.with_span(Span::line(&declaration.source_file, declaration.line_number))
+					.with_help("Remove the declaration, or use the variable.");
+
+			self.emit(self.config.unused_variable, diagnostic);
+		}
+
+		let write_only =
+			self.written_vars
+			    .iter()
+			    .filter(|(var_name, _)| !self.read_vars.contains(var_name))
+			    .cloned()
+			    .collect::<Vec<_>>();
+
+		for (var_name, span) in write_only {
+			let diagnostic =
+				Diagnostic::warning(ErrorCode::WriteOnlyVariable, format!("Variable `${var_name}` is set but never read."))
+					.with_span(span)
+					.with_help("Remove the `<<set>>` commands, or read the variable somewhere.")
+					.with_help("If the game reads it from Rust code, set `write_only_variable = \"allow\"` in the `[lints]` table of the config file.");
+
+			self.emit(self.config.write_only_variable, diagnostic);
+		}
+	}
+
+	fn check_nodes_reached(&mut self, nodes: &[IDNode]) {
+		for node in nodes {
+			let title = node.jump_title();
+
+			if self.jump_targets.contains(title) || node.metadata.tags.iter().any(|tag| tag == ENTRY_TAG) {
+				continue;
+			}
+
+			let diagnostic =
+				Diagnostic::warning(ErrorCode::UnreachableNode, format!("Node `{title}` is never reached by a `<<jump>>` or `<<detour>>`."))
+					.with_span(Span::line(&node.source_file, node.metadata.title_line_number))
+					.with_help(format!("If the game starts dialogue at this node, tag it as an entry point: `tags: {ENTRY_TAG}`."))
+					.with_help("Otherwise, remove the node or jump to it from another node.");
+
+			self.emit(self.config.unreachable_node, diagnostic);
+		}
+	}
+}
+
+/// Checks `nodes` with the rules in `config`, rules set to `deny` are reported as errors.
+///
+/// Diagnostics are sorted by file and line.
+pub fn lint_nodes(nodes: &[IDNode], var_declarations: &[VarDeclaration], config: &LintConfig) -> Vec<Diagnostic> {
+	let mut linter = Linter {
+		config,
+		diagnostics: vec![],
+		read_vars: HashSet::new(),
+		written_vars: vec![],
+		jump_targets: HashSet::new(),
+	};
+
+	for node in nodes {
+		// Top-level scopes run one after the other, so a `<<jump>>` in one makes the next ones unreachable.
+		let mut reachability = Reachability::default();
+
+		for scope in &node.scopes {
+			linter.visit_flows(node.jump_title(), &node.source_file, &scope.flows, &mut reachability);
+		}
+	}
+
+	linter.check_variables(var_declarations);
+	linter.check_nodes_reached(nodes);
+
+	let mut diagnostics = linter.diagnostics;
+	diagnostics.sort_by_key(|diagnostic| diagnostic.span.as_ref().map(|span| (span.file.clone(), span.line)));
+	diagnostics
+}
+
+#[test]
+fn test_lint_nodes() {
+	use crate::expressions::yarn_lit::YarnLit;
+	use crate::quoting::test_util::parse_id_nodes;
+	use pretty_assertions::assert_eq;
+	use std::path::PathBuf;
+
+	fn lint(text: &str, declared: &[&str], config: &LintConfig) -> Vec<(usize, ErrorCode, Severity)> {
+		let var_declarations =
+			declared
+				.iter()
+				.map(|var_name| VarDeclaration {
+					source_file: PathBuf::from("test.yarn"),
+					line_number: 1,
+					var_name: var_name.to_string(),
+					default_value: YarnExpr::Lit(YarnLit::Int(0)),
+					cast_ty: None,
+				}).collect::<Vec<_>>();
+
+		lint_nodes(&parse_id_nodes(text), &var_declarations, config)
+			.into_iter()
+			.map(|diagnostic| (diagnostic.span.unwrap().line, diagnostic.code.unwrap(), diagnostic.severity))
+			.collect()
+	}
+
+	const TEXT: &str =
+		"title: Start\n\
+		 tags: entry\n\
+		 ---\n\
+		 <<set $gold to 5>>\n\
+		 <<set $name to \"Bob\">>\n\
+		 -> Buy\n\
+		 \x20   <<jump Shop>>\n\
+		 -> Leave <<if 1 > 2>>\n\
+		 \x20   <<stop>>\n\
+		 <<if $gold > 3>>\n\
+		 \x20   <<jump Shop>>\n\
+		 <<else>>\n\
+		 \x20   <<stop>>\n\
+		 <<endif>>\n\
+		 Never said.\n\
+		 ===\n\
+		 title: Shop\n\
+		 ---\n\
+		 Welcome!\n\
+		 <<jump Shop>>\n\
+		 ===\n\
+		 title: Secret\n\
+		 ---\n\
+		 <<stop>>\n\
+		 Also never said.\n\
+		 Nor this.\n\
+		 ===\n\
+		 title: Hub\n\
+		 tags: entry\n\
+		 ---\n\
+		 <<detour Side>>\n\
+		 Back again.\n\
+		 ===\n\
+		 title: Side\n\
+		 ---\n\
+		 <<return>>\n\
+		 Skipped.\n\
+		 ===";
+
+	assert_eq!(lint(TEXT, &["gold", "unused"], &LintConfig::default()), [
+		(1, ErrorCode::UnusedVariable, Severity::Warning),
+		(5, ErrorCode::WriteOnlyVariable, Severity::Warning),
+		(8, ErrorCode::ConstantCondition, Severity::Warning),
+		(15, ErrorCode::UnreachableCode, Severity::Warning),
+		(22, ErrorCode::UnreachableNode, Severity::Warning),
+		(25, ErrorCode::UnreachableCode, Severity::Warning),
+		(37, ErrorCode::UnreachableCode, Severity::Warning),
+	]);
+
+	let config = LintConfig {
+		unreachable_code: LintLevel::Allow,
+		unreachable_node: LintLevel::Deny,
+		..LintConfig::default()
+	};
+
+	assert_eq!(lint(TEXT, &[], &config), [
+		(5, ErrorCode::WriteOnlyVariable, Severity::Warning),
+		(8, ErrorCode::ConstantCondition, Severity::Warning),
+		(22, ErrorCode::UnreachableNode, Severity::Error),
+	]);
+}
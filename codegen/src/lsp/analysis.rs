@@ -0,0 +1,411 @@
+use crate::diagnostic::{into_diagnostics, Diagnostic, Span};
+use crate::expressions::built_in_calls::BuiltInFunctionCall;
+use crate::expressions::declaration_ty::DeclarationTy;
+use crate::expressions::yarn_expr::YarnExpr;
+use crate::io::read::yarn_file_from_text;
+use crate::lint::{lint_nodes, LintConfig};
+use crate::parsing::duplicates::find_duplicates;
+use crate::parsing::raw::var_declaration::VarDeclaration;
+use crate::parsing::{parse_nodes, YarnNode};
+use crate::quoting::quotable_types::line_ids::convert_to_id_nodes;
+use crate::type_check::{check_nodes, visit_node, Usage};
+use crate::LineNumber;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+pub const BUILT_IN_COMMANDS: &[&str] = &["jump", "stop", "set", "declare", "if", "elseif", "else", "endif", "once", "endonce", "detour", "return"];
+
+pub const BUILT_IN_FUNCTIONS: &[&str] = &[
+	"visited", "visited_count", "random", "random_range", "dice", "round", "round_places",
+	"floor", "ceil", "inc", "dec", "decimal", "int", "format_invariant",
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SymbolKind {
+	Node,
+	Variable,
+}
+
+/// A node title or variable name, at the columns it was written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+	pub kind: SymbolKind,
+	pub name: String,
+	pub span: Span,
+	/// The node's `title:` line, or the variable's `<<declare>>`.
+	pub is_definition: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CompletionKind {
+	Node,
+	Variable,
+	Function,
+	BuiltInCommand,
+	CustomCommand,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+	pub label: String,
+	pub kind: CompletionKind,
+	/// The text typed by accepting the completion, `label` if `None`.
+	pub insert_text: Option<String>,
+	pub detail: Option<String>,
+}
+
+/// Everything the language server knows about the yarn files of a workspace, rebuilt whenever they change.
+pub struct Analysis {
+	/// Parse errors, or the type errors and lints if there are none.
+	pub diagnostics: Vec<Diagnostic>,
+	pub symbols: Vec<Symbol>,
+	/// `None` for variables whose type could not be inferred, missing if the files have parse errors or duplicates.
+	pub var_types: HashMap<String, Option<DeclarationTy>>,
+	pub command_names: BTreeSet<String>,
+}
+
+fn is_ident_char(ch: char) -> bool {
+	ch.is_alphanumeric() || ch == '_'
+}
+
+/// Char ranges where `word` appears in `line` as a whole word, including `prefix`.
+fn find_word(line: &str, prefix: &str, word: &str) -> Vec<Range<usize>> {
+	let needle = format!("{prefix}{word}");
+	let needle_len = needle.chars().count();
+
+	line.match_indices(&needle)
+	    .filter(|(byte_index, _)| {
+		    let before = line[..*byte_index].chars().next_back();
+		    let after = line[byte_index + needle.len()..].chars().next();
+		    !before.is_some_and(is_ident_char) && !after.is_some_and(is_ident_char)
+	    })
+	    .map(|(byte_index, _)| {
+		    let start = line[..byte_index].chars().count();
+		    start..start + needle_len
+	    }).collect()
+}
+
+fn push_expr_names<'a>(expr: &'a YarnExpr, names: &mut BTreeSet<(SymbolKind, &'a str)>) {
+	for sub_expr in expr.iter_exprs() {
+		match sub_expr {
+			YarnExpr::GetVar(var_name) => {
+				names.insert((SymbolKind::Variable, var_name));
+			},
+			| YarnExpr::BuiltInFunctionCall(BuiltInFunctionCall::Visited(node_name))
+			| YarnExpr::BuiltInFunctionCall(BuiltInFunctionCall::VisitedCount(node_name)) => {
+				names.insert((SymbolKind::Node, node_name));
+			},
+			_ => {},
+		}
+	}
+}
+
+/// Runs the lints, which need the same nodes with ids the generated code uses.
+fn lint_id_nodes(
+	nodes: Vec<YarnNode>,
+	var_declarations: &[VarDeclaration],
+	lints: &LintConfig,
+	diagnostics: &mut Vec<Diagnostic>,
+) {
+	match convert_to_id_nodes(nodes) {
+		Ok(id_nodes) => diagnostics.extend(lint_nodes(&id_nodes, var_declarations, lints)),
+		Err(err) => diagnostics.extend(into_diagnostics(err)),
+	}
+}
+
+impl Analysis {
+	/// `sources` are the path and contents of every yarn file, errors in one file don't prevent analyzing the others.
+	pub fn new(sources: &[(PathBuf, String)], lints: &LintConfig) -> Analysis {
+		let mut diagnostics = vec![];
+		let mut nodes = vec![];
+		let mut var_declarations = vec![];
+
+		for (path, text) in sources {
+			let (file_nodes, file_declarations) = parse_nodes(yarn_file_from_text(path.clone(), text), &mut diagnostics);
+			nodes.extend(file_nodes);
+			var_declarations.extend(file_declarations);
+		}
+
+		let texts =
+			sources
+				.iter()
+				.map(|(path, text)| (path.as_path(), text.lines().collect::<Vec<_>>()))
+				.collect::<HashMap<_, _>>();
+
+		let (symbols, command_names) = Self::collect_symbols(&texts, &nodes, &var_declarations);
+
+		if diagnostics.is_empty() {
+			diagnostics = find_duplicates(&nodes, &var_declarations);
+		}
+
+		let mut var_types = HashMap::new();
+
+		if diagnostics.is_empty() {
+			(var_types, diagnostics) = check_nodes(&nodes, &var_declarations);
+		}
+
+		if diagnostics.is_empty() {
+			lint_id_nodes(nodes, &var_declarations, lints, &mut diagnostics);
+		}
+
+		Analysis {
+			diagnostics,
+			symbols,
+			var_types,
+			command_names,
+		}
+	}
+
+	fn collect_symbols(
+		texts: &HashMap<&Path, Vec<&str>>,
+		nodes: &[YarnNode],
+		var_declarations: &[VarDeclaration],
+	) -> (Vec<Symbol>, BTreeSet<String>) {
+		// Names mentioned by each line, their columns are searched in the line's text afterward.
+		let mut line_names: BTreeMap<(&Path, LineNumber), BTreeSet<(SymbolKind, &str)>> = BTreeMap::new();
+		let mut definitions = BTreeSet::new();
+		let mut command_names = BTreeSet::new();
+
+		for node in nodes {
+			let file = node.source_file.as_path();
+			let title_line = node.metadata.title_line_number;
+			line_names.entry((file, title_line)).or_default().insert((SymbolKind::Node, &node.metadata.title));
+			definitions.insert((SymbolKind::Node, file, title_line));
+
+			visit_node(node, &mut |line_number, usage| {
+				let names = line_names.entry((file, line_number)).or_default();
+
+				match usage {
+					| Usage::Any(expr)
+					| Usage::Condition(expr) => push_expr_names(expr, names),
+					Usage::Set { var_name, value, .. } => {
+						names.insert((SymbolKind::Variable, var_name));
+						push_expr_names(value, names);
+					},
+					Usage::Speaker(var_name) => {
+						names.insert((SymbolKind::Variable, var_name));
+					},
+					Usage::Jump(node_name) => {
+						names.insert((SymbolKind::Node, node_name));
+					},
+					Usage::Command(command_name) => {
+						command_names.insert(command_name.to_string());
+					},
+				}
+			});
+		}
+
+		for declaration in var_declarations {
+			let file = declaration.source_file.as_path();
+			line_names.entry((file, declaration.line_number)).or_default().insert((SymbolKind::Variable, &declaration.var_name));
+			definitions.insert((SymbolKind::Variable, file, declaration.line_number));
+		}
+
+		let mut symbols = vec![];
+
+		for ((file, line_number), names) in line_names {
+			let Some(text) = texts.get(file).and_then(|lines| lines.get(line_number.checked_sub(1)?))
+				else { continue };
+
+			for (kind, name) in names {
+				let prefix = if kind == SymbolKind::Variable { "$" } else { "" };
+
+				for columns in find_word(text, prefix, name) {
+					symbols.push(Symbol {
+						kind,
+						name: name.to_string(),
+						span: Span::line(file, line_number).with_columns(columns),
+						is_definition: definitions.contains(&(kind, file, line_number)),
+					});
+				}
+			}
+		}
+
+		(symbols, command_names)
+	}
+
+	/// The symbol whose columns contain `column`, or end right at it, so a cursor after the last char still finds it.
+	pub fn symbol_at(&self, file: &Path, line: LineNumber, column: usize) -> Option<&Symbol> {
+		self.symbols
+		    .iter()
+		    .find(|symbol| {
+			    let columns = symbol.span.columns.as_ref().expect("symbols always have columns");
+			    symbol.span.file == file && symbol.span.line == line && columns.start <= column && column <= columns.end
+		    })
+	}
+
+	pub fn definition(&self, symbol: &Symbol) -> Option<&Symbol> {
+		self.references(symbol).find(|other| other.is_definition)
+	}
+
+	/// Every symbol with the same kind and name, including `symbol` itself.
+	pub fn references<'a>(&'a self, symbol: &Symbol) -> impl Iterator<Item = &'a Symbol> {
+		let (kind, name) = (symbol.kind, symbol.name.clone());
+
+		self.symbols
+		    .iter()
+		    .filter(move |other| other.kind == kind && other.name == name)
+	}
+
+	fn names(&self, kind: SymbolKind) -> BTreeSet<&str> {
+		self.symbols
+		    .iter()
+		    .filter(|symbol| symbol.kind == kind && (kind == SymbolKind::Variable || symbol.is_definition))
+		    .map(|symbol| symbol.name.as_str())
+		    .collect()
+	}
+
+	fn describe_var_type(&self, var_name: &str) -> String {
+		match self.var_types.get(var_name) {
+			Some(Some(ty)) => format!("`{ty}`"),
+			Some(None) => "unknown, it's only used where any type is accepted".to_string(),
+			None => "unknown, fix the errors in the yarn files to infer it".to_string(),
+		}
+	}
+
+	/// Markdown describing `symbol`.
+	pub fn hover(&self, symbol: &Symbol) -> String {
+		let definition = self.definition(symbol);
+
+		match symbol.kind {
+			SymbolKind::Variable => {
+				let origin =
+					match definition {
+						Some(declaration) => format!("Declared at {}.", declaration.span),
+						None => "Not declared, its type is inferred from its usages.".to_string(),
+					};
+
+				format!("`${name}`: {ty}\n\n{origin}", name = symbol.name, ty = self.describe_var_type(&symbol.name))
+			},
+			SymbolKind::Node => {
+				match definition {
+					Some(title) => format!("Node `{name}`, declared at {span}.", name = symbol.name, span = title.span),
+					None => format!("Node `{name}` does not exist.", name = symbol.name),
+				}
+			},
+		}
+	}
+
+	/// Completions for a cursor placed right after `line_prefix`, based on what is being typed.
+	pub fn completions(&self, line_prefix: &str) -> Vec<Completion> {
+		let before_word = line_prefix.trim_end_matches(is_ident_char);
+
+		let unclosed_command =
+			before_word
+				.rfind("<<")
+				.filter(|start| !before_word[*start..].contains(">>"))
+				.map(|start| &before_word[start + 2..]);
+
+		let unclosed_braces =
+			before_word
+				.rfind('{')
+				.is_some_and(|start| !before_word[start..].contains('}'));
+
+		let nodes = || {
+			self.names(SymbolKind::Node)
+			    .into_iter()
+			    .map(|name| Completion { label: name.to_string(), kind: CompletionKind::Node, insert_text: None, detail: None })
+		};
+
+		let variables = |with_prefix: bool| {
+			self.names(SymbolKind::Variable)
+			    .into_iter()
+			    .map(move |name| Completion {
+				    label: format!("${name}"),
+				    kind: CompletionKind::Variable,
+				    insert_text: (!with_prefix).then(|| name.to_string()),
+				    detail: Some(self.describe_var_type(name)),
+			    })
+		};
+
+		if before_word.ends_with('$') {
+			return variables(false).collect();
+		}
+
+		if before_word.ends_with("visited(\"") || before_word.ends_with("visited_count(\"") {
+			return nodes().collect();
+		}
+
+		match unclosed_command {
+			Some("") => {
+				let built_ins =
+					BUILT_IN_COMMANDS
+						.iter()
+						.map(|name| Completion { label: name.to_string(), kind: CompletionKind::BuiltInCommand, insert_text: None, detail: None });
+
+				let customs =
+					self.command_names
+					    .iter()
+					    .map(|name| Completion { label: name.clone(), kind: CompletionKind::CustomCommand, insert_text: None, detail: None });
+
+				built_ins.chain(customs).collect()
+			},
+			Some(command) if ["jump", "detour"].iter().any(|name| {
+				command.trim_start().strip_prefix(name).is_some_and(|rest| rest.trim().is_empty() && rest.starts_with(' '))
+			}) => {
+				nodes().collect()
+			},
+			_ if unclosed_command.is_some() || unclosed_braces => {
+				BUILT_IN_FUNCTIONS
+					.iter()
+					.map(|name| Completion { label: name.to_string(), kind: CompletionKind::Function, insert_text: None, detail: None })
+					.chain(variables(true))
+					.collect()
+			},
+			_ => vec![],
+		}
+	}
+}
+
+#[test]
+fn test_analysis() {
+	use pretty_assertions::assert_eq;
+
+	let sources = [
+		(PathBuf::from("a.yarn"),
+		 "title: Intro\n\
+		  tags: entry\n\
+		  ---\n\
+		  <<set $gold to 5>>\n\
+		  <<play_sound \"bell\">>\n\
+		  <<jump Shop>>\n\
+		  ===".to_string()),
+		(PathBuf::from("b.yarn"),
+		 "title: Shop\n\
+		  ---\n\
+		  You have {$gold} coins.\n\
+		  ===".to_string()),
+	];
+
+	let analysis = Analysis::new(&sources, &LintConfig::default());
+	assert_eq!(analysis.diagnostics, []);
+
+	let jump = analysis.symbol_at(Path::new("a.yarn"), 6, 8).unwrap();
+	let title = analysis.definition(jump).unwrap();
+	assert_eq!(title.span.to_string(), "b.yarn:1:8");
+
+	let gold = analysis.symbol_at(Path::new("b.yarn"), 3, 11).unwrap();
+	let references =
+		analysis.references(gold)
+		        .map(|symbol| symbol.span.to_string())
+		        .collect::<Vec<_>>();
+	assert_eq!(references, ["a.yarn:4:7", "b.yarn:3:11"]);
+	assert!(analysis.hover(gold).starts_with("`$gold`: `"));
+
+	let labels = |prefix: &str| {
+		analysis.completions(prefix)
+		        .into_iter()
+		        .map(|completion| completion.label)
+		        .collect::<Vec<_>>()
+	};
+
+	assert_eq!(labels("<<jump S"), ["Intro", "Shop"]);
+	assert_eq!(labels("<<detour S"), ["Intro", "Shop"]);
+	assert_eq!(labels("You have {$g"), ["$gold"]);
+	assert!(labels("<<").contains(&"play_sound".to_string()));
+	assert!(labels("<<").contains(&"endonce".to_string()));
+	assert!(labels("<<").contains(&"detour".to_string()));
+	assert!(labels("{vis").contains(&"visited".to_string()));
+	assert_eq!(labels("Just text"), Vec::<String>::new());
+}
@@ -0,0 +1,439 @@
+mod analysis;
+mod transport;
+
+use crate::config::{ConfigOverrides, YarnConfig};
+use crate::diagnostic::{Diagnostic, Severity, Span};
+use crate::io::read::find_yarn_paths;
+use crate::lint::LintConfig;
+use analysis::{Analysis, CompletionKind, Symbol};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use transport::{char_to_utf16_column, path_to_uri, read_message, uri_to_path, utf16_to_char_column, write_message};
+
+const CONFIG_FILE_NAME: &str = "yarn_project.toml";
+
+/// JSON-RPC error code for requests the server doesn't know.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for known requests that failed.
+const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Debug)]
+struct UnknownMethod(String);
+
+impl std::fmt::Display for UnknownMethod {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Unknown method: `{}`", self.0)
+	}
+}
+
+impl std::error::Error for UnknownMethod {}
+
+struct Server {
+	root: Option<PathBuf>,
+	/// Contents of the documents open in the editor, which may differ from the saved ones.
+	open_documents: HashMap<PathBuf, String>,
+	/// Files that had diagnostics on the last publish, so they are cleared once fixed.
+	published: HashSet<PathBuf>,
+	analysis: Analysis,
+	/// Edits only mark the analysis as outdated, it's rebuilt when a request needs it.
+	outdated: bool,
+	shutdown_requested: bool,
+}
+
+fn line_text(source: &str, line: usize) -> &str {
+	source.lines().nth(line).unwrap_or("")
+}
+
+fn position_params(params: &Value) -> Option<(PathBuf, usize, usize)> {
+	let path = uri_to_path(params["textDocument"]["uri"].as_str()?)?;
+	let line = params["position"]["line"].as_u64()? as usize;
+	let character = params["position"]["character"].as_u64()? as usize;
+	Some((path, line, character))
+}
+
+fn completion_kind_number(kind: CompletionKind) -> u8 {
+	match kind {
+		CompletionKind::Node => 9,
+		CompletionKind::Variable => 6,
+		CompletionKind::Function => 3,
+		CompletionKind::BuiltInCommand => 14,
+		CompletionKind::CustomCommand => 2,
+	}
+}
+
+impl Server {
+	fn source_of(&self, path: &Path) -> Option<String> {
+		self.open_documents
+		    .get(path)
+		    .cloned()
+		    .or_else(|| std::fs::read_to_string(path).ok())
+	}
+
+	/// The files listed by `yarn_project.toml` at the workspace root, or every `.yarn` file inside it if there's no config.
+	fn yarn_paths_and_lints(&self) -> (Vec<PathBuf>, LintConfig) {
+		let Some(root) = &self.root
+			else { return (vec![], LintConfig::default()) };
+
+		let config_path = root.join(CONFIG_FILE_NAME);
+
+		if config_path.exists()
+			&& let Ok(projects) = YarnConfig::parse_projects(&config_path, &ConfigOverrides::default(), None) {
+			let paths =
+				projects
+					.iter()
+					.filter_map(|project| find_yarn_paths(project).ok())
+					.flatten()
+					.collect::<BTreeSet<_>>();
+
+			return (paths.into_iter().collect(), projects[0].lints.clone());
+		}
+
+		let pattern = format!("{root}/**/*.yarn", root = glob::Pattern::escape(&root.to_string_lossy()));
+		let paths =
+			glob::glob(&pattern)
+				.map(|paths| paths.filter_map(Result::ok).collect())
+				.unwrap_or_default();
+
+		(paths, LintConfig::default())
+	}
+
+	fn analyze(&mut self) {
+		let (mut paths, lints) = self.yarn_paths_and_lints();
+
+		for open_path in self.open_documents.keys() {
+			if !paths.contains(open_path) {
+				paths.push(open_path.clone());
+			}
+		}
+
+		let sources =
+			paths.into_iter()
+			     .filter_map(|path| {
+				     let source = self.source_of(&path)?;
+				     Some((path, source))
+			     }).collect::<Vec<_>>();
+
+		self.analysis = Analysis::new(&sources, &lints);
+		self.outdated = false;
+	}
+
+	fn analysis(&mut self) -> &Analysis {
+		if self.outdated {
+			self.analyze();
+		}
+
+		&self.analysis
+	}
+
+	fn range(&self, span: &Span) -> Value {
+		let source = self.source_of(&span.file).unwrap_or_default();
+		let line = span.line.saturating_sub(1);
+		let text = line_text(&source, line);
+		let columns = Diagnostic::resolve_columns(span, text);
+
+		json!({
+			"start": { "line": line, "character": char_to_utf16_column(text, columns.start) },
+			"end": { "line": line, "character": char_to_utf16_column(text, columns.end) },
+		})
+	}
+
+	fn location(&self, span: &Span) -> Value {
+		json!({ "uri": path_to_uri(&span.file), "range": self.range(span) })
+	}
+
+	fn lsp_diagnostic(&self, diagnostic: &Diagnostic, span: &Span) -> Value {
+		let mut message = diagnostic.message.clone();
+
+		for note in &diagnostic.notes {
+			message.push_str(&format!("\nnote: {note}"));
+		}
+
+		for help in &diagnostic.help {
+			message.push_str(&format!("\nhelp: {help}"));
+		}
+
+		json!({
+			"range": self.range(span),
+			"severity": if diagnostic.severity == Severity::Error { 1 } else { 2 },
+			"code": diagnostic.code.map(|code| code.as_str()),
+			"source": "yarn_spire",
+			"message": message,
+		})
+	}
+
+	fn publish_diagnostics(&mut self, output: &mut impl Write) -> Result<()> {
+		self.analyze();
+
+		let mut by_file: HashMap<PathBuf, Vec<Value>> = HashMap::new();
+
+		for diagnostic in &self.analysis.diagnostics {
+			if let Some(span) = &diagnostic.span {
+				by_file.entry(span.file.clone())
+				       .or_default()
+				       .push(self.lsp_diagnostic(diagnostic, span));
+			}
+		}
+
+		for fixed in &self.published {
+			by_file.entry(fixed.clone()).or_default();
+		}
+
+		self.published =
+			by_file.iter()
+			       .filter(|(_, diagnostics)| !diagnostics.is_empty())
+			       .map(|(path, _)| path.clone())
+			       .collect();
+
+		for (path, diagnostics) in by_file {
+			write_message(output, &json!({
+				"jsonrpc": "2.0",
+				"method": "textDocument/publishDiagnostics",
+				"params": { "uri": path_to_uri(&path), "diagnostics": diagnostics },
+			}))?;
+		}
+
+		Ok(())
+	}
+
+	fn symbol_at(&mut self, params: &Value) -> Option<Symbol> {
+		let (path, line, character) = position_params(params)?;
+		let source = self.source_of(&path)?;
+		let column = utf16_to_char_column(line_text(&source, line), character);
+
+		self.analysis()
+		    .symbol_at(&path, line + 1, column)
+		    .cloned()
+	}
+
+	fn definition(&mut self, params: &Value) -> Value {
+		let Some(symbol) = self.symbol_at(params)
+			else { return Value::Null };
+
+		match self.analysis.definition(&symbol) {
+			Some(definition) => self.location(&definition.span),
+			None => Value::Null,
+		}
+	}
+
+	fn references(&mut self, params: &Value) -> Value {
+		let Some(symbol) = self.symbol_at(params)
+			else { return Value::Null };
+
+		let include_declaration = params["context"]["includeDeclaration"].as_bool().unwrap_or(true);
+
+		let locations =
+			self.analysis
+			    .references(&symbol)
+			    .filter(|reference| include_declaration || !reference.is_definition)
+			    .map(|reference| self.location(&reference.span))
+			    .collect();
+
+		Value::Array(locations)
+	}
+
+	fn hover(&mut self, params: &Value) -> Value {
+		let Some(symbol) = self.symbol_at(params)
+			else { return Value::Null };
+
+		json!({
+			"contents": { "kind": "markdown", "value": self.analysis.hover(&symbol) },
+			"range": self.range(&symbol.span),
+		})
+	}
+
+	fn completion(&mut self, params: &Value) -> Value {
+		let Some((path, line, character)) = position_params(params)
+			else { return Value::Null };
+
+		let source = self.source_of(&path).unwrap_or_default();
+		let text = line_text(&source, line);
+		let prefix = text.chars().take(utf16_to_char_column(text, character)).collect::<String>();
+
+		let items =
+			self.analysis()
+			    .completions(&prefix)
+			    .into_iter()
+			    .map(|completion| json!({
+				    "label": completion.label,
+				    "kind": completion_kind_number(completion.kind),
+				    "insertText": completion.insert_text,
+				    "detail": completion.detail,
+			    })).collect();
+
+		Value::Array(items)
+	}
+
+	fn store_document(&mut self, params: &Value, text: Option<&Value>) {
+		let Some(path) = params["textDocument"]["uri"].as_str().and_then(uri_to_path)
+			else { return };
+
+		if let Some(text) = text.and_then(Value::as_str) {
+			self.open_documents.insert(path, text.to_string());
+			self.outdated = true;
+		}
+	}
+
+	/// Returns the result of a request, or `None` for notifications.
+	fn handle(&mut self, method: &str, params: &Value, output: &mut impl Write) -> Result<Option<Value>> {
+		let result =
+			match method {
+				"initialize" => {
+					self.root =
+						params["rootUri"].as_str().and_then(uri_to_path)
+							.or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+
+					json!({
+						"capabilities": {
+							"textDocumentSync": { "openClose": true, "change": 1, "save": { "includeText": true } },
+							"definitionProvider": true,
+							"referencesProvider": true,
+							"hoverProvider": true,
+							"completionProvider": { "triggerCharacters": ["$", "<", " ", "\""] },
+						},
+						"serverInfo": { "name": "yarn_spire_lsp" },
+					})
+				},
+				"initialized" => {
+					self.publish_diagnostics(output)?;
+					return Ok(None);
+				},
+				"textDocument/didOpen" => {
+					self.store_document(params, Some(&params["textDocument"]["text"]));
+					self.publish_diagnostics(output)?;
+					return Ok(None);
+				},
+				"textDocument/didChange" => {
+					// Only full syncs are requested, so the last change has the whole text.
+					let changes = params["contentChanges"].as_array();
+					self.store_document(params, changes.and_then(|changes| changes.last()).map(|change| &change["text"]));
+					return Ok(None);
+				},
+				"textDocument/didSave" => {
+					self.store_document(params, params.get("text"));
+					self.publish_diagnostics(output)?;
+					return Ok(None);
+				},
+				"textDocument/didClose" => {
+					if let Some(path) = params["textDocument"]["uri"].as_str().and_then(uri_to_path) {
+						self.open_documents.remove(&path);
+					}
+
+					self.publish_diagnostics(output)?;
+					return Ok(None);
+				},
+				"textDocument/definition" => self.definition(params),
+				"textDocument/references" => self.references(params),
+				"textDocument/hover" => self.hover(params),
+				"textDocument/completion" => self.completion(params),
+				"shutdown" => {
+					self.shutdown_requested = true;
+					Value::Null
+				},
+				_ => return Err(UnknownMethod(method.to_string()).into()),
+			};
+
+		Ok(Some(result))
+	}
+}
+
+/// Runs a language server for `.yarn` files, speaking JSON-RPC through `input` and `output`, usually stdin and stdout.
+///
+/// Diagnostics are published when a file is opened or saved, and the server also answers go-to-definition,
+/// find-references, completion and hover requests.
+/// Returns once the client sends `exit`, or an error if it did so without sending `shutdown` first.
+pub fn run_language_server(mut input: impl BufRead, mut output: impl Write) -> Result<()> {
+	let mut server = Server {
+		root: None,
+		open_documents: HashMap::new(),
+		published: HashSet::new(),
+		analysis: Analysis::new(&[], &LintConfig::default()),
+		outdated: false,
+		shutdown_requested: false,
+	};
+
+	while let Some(message) = read_message(&mut input)? {
+		let method = message["method"].as_str().unwrap_or_default();
+
+		if method == "exit" {
+			break;
+		}
+
+		let result = server.handle(method, &message["params"], &mut output);
+
+		// Notifications have no id and never get responses, even for errors.
+		let Some(id) = message.get("id")
+			else { continue };
+
+		let response =
+			match result {
+				Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+				Err(err) => {
+					let code = if err.is::<UnknownMethod>() { METHOD_NOT_FOUND } else { INTERNAL_ERROR };
+					json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": err.to_string() } })
+				},
+			};
+
+		write_message(&mut output, &response)?;
+	}
+
+	if server.shutdown_requested {
+		Ok(())
+	} else {
+		Err(anyhow!("The client sent `exit` before `shutdown`, or closed the connection."))
+	}
+}
+
+#[test]
+fn test_run_language_server() {
+	use pretty_assertions::assert_eq;
+
+	let root = std::env::temp_dir().join(format!("yarn_spire_lsp_{}", std::process::id()));
+	std::fs::create_dir_all(&root).unwrap();
+
+	let uri = path_to_uri(&root.join("intro.yarn"));
+	let broken = "title: Intro\ntags: entry\n---\n<<endif>>\n===";
+	let fixed = "title: Intro\ntags: entry\n---\nHello\n===";
+
+	let mut input = vec![];
+
+	for message in [
+		json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "rootUri": path_to_uri(&root) } }),
+		json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": { "textDocument": { "uri": uri, "text": broken } } }),
+		json!({ "jsonrpc": "2.0", "method": "textDocument/didSave", "params": { "textDocument": { "uri": uri }, "text": fixed } }),
+		json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/formatting", "params": {} }),
+		json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+		json!({ "jsonrpc": "2.0", "method": "exit" }),
+	] {
+		write_message(&mut input, &message).unwrap();
+	}
+
+	let mut output = vec![];
+	let result = run_language_server(input.as_slice(), &mut output);
+	std::fs::remove_dir_all(&root).unwrap();
+	result.unwrap();
+
+	let mut output = output.as_slice();
+	let mut messages = vec![];
+
+	while let Some(message) = read_message(&mut output).unwrap() {
+		messages.push(message);
+	}
+
+	assert_eq!(messages.len(), 5, "{messages:#?}");
+	assert_eq!(messages[0]["id"], 1);
+	assert_eq!(messages[0]["result"]["serverInfo"]["name"], "yarn_spire_lsp");
+
+	assert_eq!(messages[1]["method"], "textDocument/publishDiagnostics");
+	assert_eq!(messages[1]["params"]["uri"], uri);
+	assert_eq!(messages[1]["params"]["diagnostics"][0]["range"]["start"], json!({ "line": 3, "character": 0 }));
+
+	// Once fixed, the file's diagnostics are cleared.
+	assert_eq!(messages[2]["params"], json!({ "uri": uri, "diagnostics": [] }));
+
+	assert_eq!(messages[3]["id"], 2);
+	assert_eq!(messages[3]["error"]["code"], METHOD_NOT_FOUND);
+	assert_eq!(messages[4], json!({ "jsonrpc": "2.0", "id": 3, "result": null }));
+}
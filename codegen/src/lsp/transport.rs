@@ -0,0 +1,128 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Reads one JSON-RPC message, framed by a `Content-Length` header. Returns `None` once the input is closed.
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<Value>> {
+	let mut content_length = None;
+
+	loop {
+		let mut header = String::new();
+
+		if input.read_line(&mut header)? == 0 {
+			return Ok(None);
+		}
+
+		let header = header.trim_end();
+
+		if header.is_empty() {
+			break;
+		}
+
+		if let Some((name, value)) = header.split_once(':')
+			&& name.eq_ignore_ascii_case("Content-Length") {
+			content_length = Some(
+				value.trim()
+				     .parse::<usize>()
+				     .map_err(|err| anyhow!("Invalid `Content-Length` header: `{header}`\nError: {err}"))?);
+		}
+	}
+
+	let content_length = content_length.ok_or_else(|| anyhow!("Message is missing the `Content-Length` header."))?;
+
+	let mut content = vec![0; content_length];
+	input.read_exact(&mut content)?;
+
+	serde_json::from_slice(&content)
+		.map(Some)
+		.map_err(|err| anyhow!("Message is not valid JSON.\nError: {err}"))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> Result<()> {
+	let content = message.to_string();
+	write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+	output.flush()?;
+	Ok(())
+}
+
+/// Only `file://` URIs are supported, since yarn files are read from the file-system.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+	let encoded = uri.strip_prefix("file://")?;
+	let mut bytes = vec![];
+	let mut iter = encoded.bytes();
+
+	while let Some(byte) = iter.next() {
+		if byte == b'%' {
+			let hex = [iter.next()?, iter.next()?];
+			bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+		} else {
+			bytes.push(byte);
+		}
+	}
+
+	let path = String::from_utf8(bytes).ok()?;
+
+	// Windows paths look like `/C:/folder`.
+	match path.strip_prefix('/') {
+		Some(windows) if windows.as_bytes().get(1) == Some(&b':') => Some(PathBuf::from(windows)),
+		_ => Some(PathBuf::from(path)),
+	}
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+	let path = path.to_string_lossy().replace('\\', "/");
+	let mut uri = String::from("file://");
+
+	if !path.starts_with('/') {
+		uri.push('/');
+	}
+
+	for byte in path.bytes() {
+		if byte.is_ascii_alphanumeric() || b"/-_.~:".contains(&byte) {
+			uri.push(byte as char);
+		} else {
+			uri.push_str(&format!("%{byte:02X}"));
+		}
+	}
+
+	uri
+}
+
+/// LSP columns count UTF-16 code units, while spans count chars.
+pub fn char_to_utf16_column(line: &str, column: usize) -> usize {
+	line.chars().take(column).map(char::len_utf16).sum()
+}
+
+pub fn utf16_to_char_column(line: &str, utf16_column: usize) -> usize {
+	let mut units = 0;
+
+	line.chars()
+	    .take_while(|ch| {
+		    units += ch.len_utf16();
+		    units <= utf16_column
+	    }).count()
+}
+
+#[test]
+fn test_transport() {
+	use pretty_assertions::assert_eq;
+	use serde_json::json;
+
+	let mut buffer = vec![];
+	write_message(&mut buffer, &json!({ "jsonrpc": "2.0", "method": "exit" })).unwrap();
+	write_message(&mut buffer, &json!({ "text": "olá" })).unwrap();
+
+	let mut input = buffer.as_slice();
+	assert_eq!(read_message(&mut input).unwrap(), Some(json!({ "jsonrpc": "2.0", "method": "exit" })));
+	assert_eq!(read_message(&mut input).unwrap(), Some(json!({ "text": "olá" })));
+	assert_eq!(read_message(&mut input).unwrap(), None);
+
+	let path = uri_to_path("file:///home/user/my%20game/intro.yarn").unwrap();
+	assert_eq!(path, Path::new("/home/user/my game/intro.yarn"));
+	assert_eq!(path_to_uri(&path), "file:///home/user/my%20game/intro.yarn");
+	assert_eq!(uri_to_path("file:///C:/game/intro.yarn").unwrap(), Path::new("C:/game/intro.yarn"));
+
+	assert_eq!(char_to_utf16_column("a😀b", 2), 3);
+	assert_eq!(utf16_to_char_column("a😀b", 3), 2);
+}
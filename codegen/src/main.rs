@@ -1,61 +1,343 @@
-#![allow(clippy::type_complexity)]
-#![feature(let_chains)]
-#![feature(if_let_guard)]
-#![feature(string_remove_matches)]
-#![feature(assert_matches)]
-#![feature(coroutines)]
-#![feature(pattern)]
-#![feature(stmt_expr_attributes)]
-#![feature(proc_macro_expand)]
-#![feature(iterator_try_collect)]
-#![feature(extract_if)]
-#![feature(coroutine_trait)]
-#![feature(iter_from_coroutine)]
-#![feature(gen_blocks)]
-
-extern crate core;
-
-mod expressions;
-mod parsing;
-mod io;
-mod config;
-mod quoting;
-
-use anyhow::Result;
-use io::read;
-use parsing::parse_nodes;
-use crate::config::YarnConfig;
-
-type LineNumber = usize;
-type Indent = isize;
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct UnparsedLine {
-	line_number: LineNumber,
-	text: String,
-}
-
-pub fn main() -> Result<()> {
-	let config = 
-		YarnConfig::parse_file()?;
-	
-	let yarn_files = 
-		read::find_and_read_yarn_files(&config)?;
-	
-	let (nodes, var_declarations) =
-		yarn_files
+mod cli;
+
+use anyhow::{anyhow, Result};
+use cli::{CliArgs, Command, MessageFormat, ParsedArgs, Verbosity};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+use std::io::Write;
+use yarn_spire_codegen::{into_diagnostics, Diagnostic, Diagnostics, ErrorCode, GeneratedOutput, OutdatedReason, ParseCache, Severity, YarnConfig, YarnWatcher, CACHE_FILE_NAME, DEFAULT_CONFIG};
+
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+fn check(output: &GeneratedOutput, config: &YarnConfig, verbosity: Verbosity) -> Result<()> {
+	let outdated = output.check(config)?;
+	
+	if outdated.is_empty() {
+		if verbosity >= Verbosity::Normal {
+			println!("Generated code is up to date.");
+		}
+		
+		return Ok(());
+	}
+	
+	if verbosity >= Verbosity::Normal {
+		for file in &outdated {
+			let reason = match file.reason {
+				OutdatedReason::Missing => "missing",
+				OutdatedReason::Changed => "changed",
+				OutdatedReason::Obsolete => "obsolete",
+			};
+			
+			println!("{reason}: {path}", path = file.relative_path.display());
+			print!("{}", file.diff);
+		}
+	}
+	
+	Err(Diagnostic::error(ErrorCode::OutdatedOutput, format!(
+		"{count} generated file(s) in {destination:?} are out of date.",
+		count = outdated.len(), destination = config.destination_os_path))
+		.with_help("Run the generator without `--check` to update them.")
+		.into())
+}
+
+fn print_parsed(output: &GeneratedOutput, verbosity: Verbosity) {
+	if verbosity >= Verbosity::Verbose {
+		for path in &output.yarn_files {
+			println!("  Found: {path:?}");
+		}
+		
+		println!("Parsed {} nodes and {} variable declarations.", output.node_count, output.var_declaration_count);
+	}
+}
+
+fn write(
+	output: &GeneratedOutput,
+	config: &YarnConfig,
+	cache: &ParseCache,
+	cache_path: &Path,
+	verbosity: Verbosity,
+) -> Result<()> {
+	if verbosity >= Verbosity::Verbose {
+		println!("Writing {} files to: {:?}", output.files.len(), config.destination_os_path);
+	}
+	
+	let summary = output.write(config)?;
+	cache.save(cache_path)?;
+	
+	if verbosity >= Verbosity::Verbose {
+		println!("Wrote {} files, {} were unchanged, deleted {} obsolete files.", summary.written, summary.unchanged, summary.deleted);
+	}
+	
+	if verbosity >= Verbosity::Normal {
+		println!("Code generated successfully!");
+	}
+	
+	Ok(())
+}
+
+/// Writes [DEFAULT_CONFIG] at `config_path`, refusing to replace an existing file.
+fn init(config_path: &Path, verbosity: Verbosity) -> Result<()> {
+	let mut file =
+		std::fs::File::create_new(config_path)
+			.map_err(|err| anyhow!(
+				"Could not create config file.\n\
+				 Path: {config_path:?}\n\
+				 Error: `{err}`\n\n\
+				 Help: `init` never replaces an existing file, delete it first or use `--config <PATH>` to pick another path."))?;
+	
+	file.write_all(DEFAULT_CONFIG.as_bytes())
+		.map_err(|err| anyhow!(
+			"Could not write config file.\n\
+			 Path: {config_path:?}\n\
+			 Error: `{err}`"))?;
+	
+	if verbosity >= Verbosity::Normal {
+		println!("Created config file: {config_path:?}");
+	}
+	
+	Ok(())
+}
+
+/// Prints the warnings, then returns the errors of lints set to `deny`.
+fn lint(config: &YarnConfig, verbosity: Verbosity, format: MessageFormat) -> Result<()> {
+	if verbosity >= Verbosity::Verbose {
+		println!("Linting yarn files in: {:?}", config.yarn_root_folders);
+	}
+	
+	let (errors, warnings): (Vec<_>, Vec<_>) =
+		yarn_spire_codegen::lint(config)?
 			.into_iter()
-			.map(parse_nodes)
-			.try_fold((vec![], vec![]), |(mut nodes_sum, mut vars_sum), node_result| {
-				let (nodes, var_declarations) = node_result?;
-				nodes_sum.extend(nodes);
-				vars_sum.extend(var_declarations);
-				Result::<_>::Ok((nodes_sum, vars_sum))
-			})?;
+			.partition(|diagnostic| diagnostic.severity == Severity::Error);
 	
-	io::write::generate_and_write(&config, nodes, var_declarations)?;
+	for warning in &warnings {
+		match format {
+			MessageFormat::Human => eprintln!("{warning}\n"),
+			MessageFormat::Json => println!("{}", warning.to_json()),
+		}
+	}
+	
+	if !errors.is_empty() {
+		return Err(Diagnostics::limited(errors, config.max_errors).into());
+	}
+	
+	if verbosity >= Verbosity::Normal {
+		println!("Lint finished with {} warning(s).", warnings.len());
+	}
 	
-	println!("Code generated successfully!");
 	Ok(())
 }
 
+/// Formats the yarn files, or with `--check` only prints the changes formatting would make.
+fn fmt(config: &YarnConfig, check: bool, verbosity: Verbosity) -> Result<()> {
+	if verbosity >= Verbosity::Verbose {
+		println!("Formatting yarn files in: {:?}", config.yarn_root_folders);
+	}
+	
+	let unformatted = yarn_spire_codegen::format(config)?;
+	
+	if check {
+		if unformatted.is_empty() {
+			if verbosity >= Verbosity::Normal {
+				println!("Yarn files are formatted.");
+			}
+			
+			return Ok(());
+		}
+		
+		if verbosity >= Verbosity::Normal {
+			for file in &unformatted {
+				println!("unformatted: {path}", path = file.path.display());
+				print!("{}", file.diff);
+			}
+		}
+		
+		return Err(Diagnostic::error(ErrorCode::UnformattedYarnFile, format!(
+			"{count} yarn file(s) are not formatted.", count = unformatted.len()))
+			.with_help("Run `fmt` without `--check` to format them.")
+			.into());
+	}
+	
+	for file in &unformatted {
+		file.write()?;
+		
+		if verbosity >= Verbosity::Verbose {
+			println!("  Formatted: {:?}", file.path);
+		}
+	}
+	
+	if verbosity >= Verbosity::Normal {
+		println!("Formatted {} file(s).", unformatted.len());
+	}
+	
+	Ok(())
+}
+
+fn report(err: anyhow::Error, format: MessageFormat) {
+	match format {
+		MessageFormat::Human => {
+			// Diagnostics are already formatted like `error: ...`.
+			if err.is::<Diagnostic>() || err.is::<Diagnostics>() {
+				eprintln!("{err}");
+			} else {
+				eprintln!("Error: {err:?}");
+			}
+		},
+		MessageFormat::Json => {
+			for diagnostic in into_diagnostics(err) {
+				println!("{}", diagnostic.to_json());
+			}
+		},
+	}
+}
+
+fn report_in_project(name: &str, err: anyhow::Error, format: MessageFormat) {
+	if format == MessageFormat::Human {
+		eprintln!("Error in project `{name}`:");
+	}
+	
+	report(err, format);
+}
+
+struct WatchedProject {
+	config: YarnConfig,
+	watcher: YarnWatcher,
+	cache_path: PathBuf,
+}
+
+/// Regenerates the code whenever a yarn file changes, forever.
+///
+/// Errors are printed instead of returned, so a typo in a yarn file doesn't stop the watcher.
+fn watch(mut projects: Vec<WatchedProject>, verbosity: Verbosity, format: MessageFormat) -> ! {
+	if verbosity >= Verbosity::Normal {
+		for project in &projects {
+			println!("Watching for changes in: {:?}", project.config.yarn_root_folders);
+		}
+	}
+	
+	loop {
+		for WatchedProject { config, watcher, cache_path } in &mut projects {
+			let result =
+				watcher
+					.poll(config)
+					.and_then(|changed| {
+						if !changed {
+							return Ok(());
+						}
+						
+						let output = watcher.generate(config)?;
+						print_parsed(&output, verbosity);
+						write(&output, config, watcher.cache(), cache_path, verbosity)
+					});
+			
+			if let Err(err) = result {
+				report_in_project(&config.name, err, format);
+			}
+		}
+		
+		std::thread::sleep(WATCH_POLL_INTERVAL);
+	}
+}
+
+fn run(config: &YarnConfig, args: &CliArgs) -> Result<()> {
+	if args.command == Command::Lint {
+		return lint(config, args.verbosity, args.message_format);
+	}
+	
+	if args.command == Command::Fmt {
+		return fmt(config, args.check, args.verbosity);
+	}
+	
+	if args.verbosity >= Verbosity::Verbose {
+		println!("Searching for yarn files in: {:?}", config.yarn_root_folders);
+	}
+	
+	let cache_path = config.destination_os_path.join(CACHE_FILE_NAME);
+	let mut cache = ParseCache::load(&cache_path)?;
+	
+	let output =
+		yarn_spire_codegen::generate_cached(config, &mut cache)?;
+	
+	print_parsed(&output, args.verbosity);
+	
+	if args.check {
+		check(&output, config, args.verbosity)
+	} else {
+		write(&output, config, &cache, &cache_path, args.verbosity)
+	}
+}
+
+fn run_cli(args: &CliArgs) -> Result<()> {
+	if args.command == Command::Init {
+		return init(&args.config_path, args.verbosity);
+	}
+	
+	if args.verbosity >= Verbosity::Verbose {
+		println!("Reading config file: {:?}", args.config_path);
+	}
+	
+	let mut projects = 
+		YarnConfig::parse_projects(&args.config_path, &args.overrides, args.project.as_deref())?;
+	
+	if args.watch {
+		let watched =
+			projects
+				.into_iter()
+				.map(|config| {
+					let cache_path = config.destination_os_path.join(CACHE_FILE_NAME);
+					let watcher = YarnWatcher::new(ParseCache::load(&cache_path)?);
+					Ok(WatchedProject { config, watcher, cache_path })
+				}).collect::<Result<_>>()?;
+		
+		watch(watched, args.verbosity, args.message_format);
+	}
+	
+	if projects.len() == 1 {
+		return run(&projects.remove(0), args);
+	}
+	
+	// Each project is independent, so one failing doesn't prevent the others from being generated.
+	let mut failed = vec![];
+	
+	for config in &projects {
+		if args.verbosity >= Verbosity::Normal {
+			println!("Project `{name}`:", name = config.name);
+		}
+		
+		if let Err(err) = run(config, args) {
+			report_in_project(&config.name, err, args.message_format);
+			failed.push(config.name.as_str());
+		}
+	}
+	
+	if failed.is_empty() {
+		Ok(())
+	} else {
+		Err(anyhow!(
+			"{count} of {total} projects failed: `{names}`",
+			count = failed.len(), total = projects.len(), names = failed.join("`, `")))
+	}
+}
+
+pub fn main() -> ExitCode {
+	let args =
+		match CliArgs::from_env() {
+			Ok(ParsedArgs::Run(args)) => args,
+			Ok(ParsedArgs::Help) => {
+				cli::print_help();
+				return ExitCode::SUCCESS;
+			},
+			Err(err) => {
+				report(err, MessageFormat::Human);
+				return ExitCode::FAILURE;
+			},
+		};
+	
+	match run_cli(&args) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(err) => {
+			report(err, args.message_format);
+			ExitCode::FAILURE
+		},
+	}
+}
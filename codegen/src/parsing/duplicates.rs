@@ -0,0 +1,251 @@
+use crate::diagnostic::{Diagnostic, ErrorCode, Span};
+use crate::parsing::grouping::scope::{FlatLine, Flow, YarnScope};
+use crate::parsing::raw::var_declaration::VarDeclaration;
+use crate::parsing::YarnNode;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Pairs each repeated name with the place it was first declared, in the order the repetitions are found.
+fn find_repeated<'a>(declarations: impl IntoIterator<Item = (&'a str, Span)>) -> Vec<(&'a str, Span, Span)> {
+	let mut first_declarations = HashMap::<&str, Span>::new();
+	let mut repeated = vec![];
+
+	for (name, span) in declarations {
+		match first_declarations.get(name) {
+			Some(first) => repeated.push((name, first.clone(), span)),
+			None => {
+				first_declarations.insert(name, span);
+			},
+		}
+	}
+
+	repeated
+}
+
+fn push_line_ids<'a>(file: &Path, scope: &'a YarnScope, line_ids: &mut Vec<(&'a str, Span)>) {
+	for flow in scope.flows() {
+		match flow {
+			Flow::Flat(flat_lines) => {
+				for flat in flat_lines {
+					if let FlatLine::Speech(speech) = flat
+						&& let Some(id) = &speech.line_id {
+						line_ids.push((id, Span::line(file, speech.line_number)));
+					}
+				}
+			},
+			Flow::OptionsFork(options_fork) => {
+				for (option, scope_option) in options_fork.iter_options() {
+					if let Some(id) = &option.line_id {
+						line_ids.push((id, Span::line(file, option.line_number)));
+					}
+
+					if let Some(scope) = scope_option {
+						push_line_ids(file, scope, line_ids);
+					}
+				}
+			},
+			Flow::LineGroup(line_group) => {
+				for (group_line, scope_option) in line_group.iter_lines() {
+					if let Some(id) = &group_line.speech.line_id {
+						line_ids.push((id, Span::line(file, group_line.speech.line_number)));
+					}
+
+					if let Some(scope) = scope_option {
+						push_line_ids(file, scope, line_ids);
+					}
+				}
+			},
+			Flow::IfBranch(if_branch) => {
+				if let Some(if_scope) = &if_branch.if_.1 {
+					push_line_ids(file, if_scope, line_ids);
+				}
+
+				for (_, scope_option) in &if_branch.else_ifs {
+					if let Some(else_if_scope) = scope_option {
+						push_line_ids(file, else_if_scope, line_ids);
+					}
+				}
+
+				if let Some((_, Some(else_scope))) = &if_branch.else_ {
+					push_line_ids(file, else_scope, line_ids);
+				}
+			},
+			Flow::OnceBranch(once_branch) => {
+				if let Some(once_scope) = &once_branch.once_.1 {
+					push_line_ids(file, once_scope, line_ids);
+				}
+
+				if let Some((_, Some(else_scope))) = &once_branch.else_ {
+					push_line_ids(file, else_scope, line_ids);
+				}
+			},
+		}
+	}
+}
+
+/// Node titles, variables and line ids (`#line:id`) must be unique across all yarn files,
+/// each file is parsed on its own so they can only be compared once all of them are parsed.
+///
+/// Every repetition is reported, pointing at both copies.
+///
+/// Nodes with a `when:` header are allowed to share their title, as long as all of them have one.
+/// Their members are renamed to `{title}__{n}`, so other nodes can't use those titles.
+pub fn find_duplicates(nodes: &[YarnNode], var_declarations: &[VarDeclaration]) -> Vec<Diagnostic> {
+	let mut group_titles = HashSet::new();
+	
+	let titles =
+		nodes.iter()
+		     .filter(|node| node.metadata.when.is_empty() || group_titles.insert(node.metadata.title.as_str()))
+		     .map(|node| (node.metadata.title.as_str(), Span::line(&node.source_file, node.metadata.title_line_number)))
+		     .collect::<Vec<_>>();
+
+	let mut groups = HashMap::<&str, (Span, usize)>::new();
+
+	for node in nodes.iter().filter(|node| !node.metadata.when.is_empty()) {
+		let (_, member_count) =
+			groups.entry(node.metadata.title.as_str())
+			      .or_insert_with(|| (Span::line(&node.source_file, node.metadata.title_line_number), 0));
+
+		*member_count += 1;
+	}
+
+	let reserved_title_errors =
+		titles
+			.iter()
+			.filter_map(|(title, span)| {
+				let (group_title, number) = title.rsplit_once("__")?;
+				let (first, member_count) = groups.get(group_title)?;
+
+				number.parse::<usize>()
+				      .is_ok_and(|number| (1..=*member_count).contains(&number))
+				      .then(|| {
+					      Diagnostic::error(ErrorCode::DuplicateNodeTitle, format!(
+						      "Node title `{title}` is reserved for a member of the node group `{group_title}`."))
+						      .with_span(span.clone())
+						      .with_note(format!("Members of node groups are renamed to `{{title}}__{{n}}`, the group `{group_title}` starts at {first}."))
+						      .with_help("Rename the node or the node group.")
+				      })
+			}).collect::<Vec<_>>();
+
+	let title_errors =
+		find_repeated(titles)
+			.into_iter()
+			.map(|(title, first, span)|
+				Diagnostic::error(ErrorCode::DuplicateNodeTitle, format!("Node title `{title}` is declared more than once."))
+					.with_span(span)
+					.with_note(format!("First declared at {first}."))
+					.with_help("Node titles must be unique across all yarn files, rename one of the nodes.")
+					.with_help("To make a node group instead, give every node with that title a `when:` header."));
+
+	let variables =
+		var_declarations
+			.iter()
+			.map(|declaration| (declaration.var_name.as_str(), Span::line(&declaration.source_file, declaration.line_number)));
+
+	let variable_errors =
+		find_repeated(variables)
+			.into_iter()
+			.map(|(var_name, first, span)|
+				Diagnostic::error(ErrorCode::DuplicateVarDeclaration, format!("Variable `${var_name}` is declared more than once."))
+					.with_span(span)
+					.with_note(format!("First declared at {first}."))
+					.with_help("Variables must be declared only once across all yarn files, delete one of the declarations."));
+
+	let mut line_ids = vec![];
+
+	for node in nodes {
+		for scope in &node.contents {
+			push_line_ids(&node.source_file, scope, &mut line_ids);
+		}
+	}
+
+	let line_id_errors =
+		find_repeated(line_ids)
+			.into_iter()
+			.map(|(id, first, span)|
+				Diagnostic::error(ErrorCode::DuplicateLineId, format!("Duplicate line id found: `{id}`."))
+					.with_span(span)
+					.with_note(format!("First used at {first}."))
+					.with_help("Line ids (`#line:id`) must be unique across all yarn files."));
+
+	let mut diagnostics =
+		title_errors
+			.chain(reserved_title_errors)
+			.chain(variable_errors)
+			.chain(line_id_errors)
+			.collect::<Vec<_>>();
+
+	diagnostics.sort_by_key(|diagnostic| diagnostic.span.as_ref().map(|span| (span.file.clone(), span.line)));
+	diagnostics
+}
+
+#[test]
+fn test_find_duplicates() {
+	use crate::expressions::yarn_expr::YarnExpr;
+	use crate::expressions::yarn_lit::YarnLit;
+	use crate::io::read::yarn_file_from_text;
+	use pretty_assertions::assert_eq;
+	use std::path::PathBuf;
+
+	fn parse(path: &str, text: &str) -> Vec<YarnNode> {
+		let mut parse_errors = vec![];
+		let (nodes, _) = crate::parsing::parse_nodes(yarn_file_from_text(PathBuf::from(path), text), &mut parse_errors);
+		assert_eq!(parse_errors, []);
+		nodes
+	}
+
+	fn declaration(path: &str, line_number: usize) -> VarDeclaration {
+		VarDeclaration {
+			source_file: PathBuf::from(path),
+			line_number,
+			var_name: "gold".to_string(),
+			default_value: YarnExpr::Lit(YarnLit::Int(0)),
+			cast_ty: None,
+		}
+	}
+
+	let mut nodes = parse("a.yarn",
+		"title: Intro\n\
+		 ---\n\
+		 Hello #line:hello\n\
+		 ===\n\
+		 title: Story\n\
+		 when: once\n\
+		 ---\n\
+		 First\n\
+		 ===\n\
+		 title: Story\n\
+		 when: always\n\
+		 ---\n\
+		 Again\n\
+		 ===");
+
+	nodes.extend(parse("b.yarn",
+		"title: Other\n\
+		 ---\n\
+		 Hi\n\
+		 ===\n\
+		 title: Intro\n\
+		 ---\n\
+		 -> Hello again #line:hello\n\
+		 \x20   Bye\n\
+		 ===\n\
+		 title: Story__2\n\
+		 ---\n\
+		 Clash\n\
+		 ==="));
+
+	let rendered =
+		find_duplicates(&nodes, &[declaration("a.yarn", 1), declaration("b.yarn", 3)])
+			.into_iter()
+			.map(|diagnostic| (diagnostic.code.unwrap(), diagnostic.span.unwrap().to_string(), diagnostic.notes))
+			.collect::<Vec<_>>();
+
+	assert_eq!(rendered, [
+		(ErrorCode::DuplicateVarDeclaration, "b.yarn:3:1".to_string(), vec!["First declared at a.yarn:1:1.".to_string()]),
+		(ErrorCode::DuplicateNodeTitle, "b.yarn:5:1".to_string(), vec!["First declared at a.yarn:1:1.".to_string()]),
+		(ErrorCode::DuplicateLineId, "b.yarn:7:1".to_string(), vec!["First used at a.yarn:3:1.".to_string()]),
+		(ErrorCode::DuplicateNodeTitle, "b.yarn:10:1".to_string(),
+		 vec!["Members of node groups are renamed to `{title}__{n}`, the group `Story` starts at a.yarn:5:1.".to_string()]),
+	]);
+}
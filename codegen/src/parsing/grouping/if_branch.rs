@@ -4,17 +4,24 @@ use crate::parsing::raw::branches::if_statement::{ElseIf_, Else_, If_};
 use crate::parsing::raw::{Content, RawLine};
 use crate::Indent;
 use anyhow::{anyhow, Result};
+use crate::diagnostic::{Diagnostic, ErrorCode, MapUnlocatedErr, Span};
 use scope::read_next_scope;
 use std::iter::Peekable;
 use std::vec::IntoIter;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IfBranch {
 	pub if_: (If_, Option<Box<YarnScope>>),
 	pub else_ifs: Vec<(ElseIf_, Option<Box<YarnScope>>)>,
 	pub else_: Option<(Else_, Option<Box<YarnScope>>)>,
 }
 
+const BRANCH_HELP: &str =
+	"Branches are started with `<<if [condition]>>`, then closed with `<<endif>>`. \
+	 As long as a Branch is open, parallel(same indentation, not necessarily adjacent) lines must be \
+	 either `<<elseif [condition]>>`, `<<else>>` or `<<endif>>`.";
+
 impl IfBranch {
 	fn branches_indent(&self) -> Option<Indent> {
 		if let Some(indent) =
@@ -37,55 +44,54 @@ impl IfBranch {
 		})
 	}
 
-	fn add_elseif(&mut self, else_if: ElseIf_, scope_option: Option<Box<YarnScope>>) -> Result<()> {
+	fn add_elseif(&mut self, else_if: ElseIf_, span: Span, scope_option: Option<Box<YarnScope>>) -> Result<()> {
 		if let (Some(expected), Some(found))
 			= (self.branches_indent(), scope_option.as_ref().map(|scope| scope.indent()))
 			&& found != expected {
-			return Err(anyhow!(
-				"Cannot add `elseif` to fork list.\n\
-				 Error: Indentation mismatch, expected: {expected}, got: {found}.\n\
-				 Trying to add: {else_if:?}.\n\
-				 Help: Indentation is calculated by: [space + tabs * 4]\n\
-				 Help: Every fork(`<<if`, `<<elseif`, `<<else`) needs to have the same indentation."
-			));
+			return Err(Diagnostic::error(ErrorCode::IndentationMismatch, format!(
+				"Indentation mismatch in the scope of `<<elseif>>`, expected: {expected}, got: {found}."))
+				.with_span(span)
+				.with_help("Indentation is calculated by: [space + tabs * 4]")
+				.with_help("Every fork(`<<if`, `<<elseif`, `<<else`) needs to have the same indentation.")
+				.into());
 		}
 
 		self.else_ifs.push((else_if, scope_option));
 		Ok(())
 	}
 
-	fn add_else(&mut self, else_: Else_, scope_option: Option<Box<YarnScope>>) -> Result<()> {
+	fn add_else(&mut self, else_: Else_, span: Span, scope_option: Option<Box<YarnScope>>) -> Result<()> {
 		if let (Some(expected), Some(found)) =
 			(self.branches_indent(), scope_option.as_ref().map(|scope| scope.indent()))
 			&& found != expected
 		{
-			return Err(anyhow!(
-				"Cannot add `else` to fork list.\n\
-				 Error: Indentation mismatch, expected: {expected}, got: {found}.\n\
-				 Trying to add: {else_:?}.\n\n\
-				 Help: Indentation is calculated by: [space + tabs * 4]\n\
-				 Help: Every fork(`<<if`, `<<elseif`, `<<else`) needs to have the same indentation."
-			));
+			return Err(Diagnostic::error(ErrorCode::IndentationMismatch, format!(
+				"Indentation mismatch in the scope of `<<else>>`, expected: {expected}, got: {found}."))
+				.with_span(span)
+				.with_help("Indentation is calculated by: [space + tabs * 4]")
+				.with_help("Every fork(`<<if`, `<<elseif`, `<<else`) needs to have the same indentation.")
+				.into());
 		}
 
 		if let Some((already_else, _)) = &self.else_ {
-			Err(anyhow!(
-				"Cannot add `else` to fork list.\n\
-				 Error: branch already contains another `else`, data: {already_else:?}.\n\
-				 Trying to add: {else_:?}.\n\n\
-				 Help: There can only be one `else` fork per branch."
-			))
+			Err(Diagnostic::error(ErrorCode::DuplicateElse, "Branch already contains another `<<else>>`.")
+				.with_span(span)
+				.with_note(format!("The first `<<else>>` is at line nº{}.", already_else.line_number))
+				.with_help("There can only be one `else` fork per branch.")
+				.into())
 		} else {
 			self.else_ = Some((else_, scope_option));
 			Ok(())
 		}
 	}
 
-	pub fn build(parent_indent: Indent, if_line: If_,
-	             lines_iter: &mut Peekable<IntoIter<RawLine>>) -> Result<IfBranch> {
+	/// `if_span` points at `if_line`, to report branches that are never closed.
+	pub fn build(parent_indent: Indent, if_line: If_, if_span: Span,
+	             lines_iter: &mut Peekable<IntoIter<RawLine>>,
+	             diagnostics: &mut Vec<Diagnostic>) -> Result<IfBranch> {
 		let if_scope =
-			read_next_scope(parent_indent, lines_iter)
-				.map_err(|err| anyhow!(
+			read_next_scope(parent_indent, lines_iter, diagnostics)
+				.map_unlocated_err(|err| anyhow!(
 					"Could not build `if`'s child scope.\n\
 					 Fork data: `{if_line:?}`\n\
 					 Error: {err}")
@@ -98,72 +104,72 @@ impl IfBranch {
 				else_: None,
 			};
 
-		while let Some(Content::ElseIf(elseif)) =
+		while let Some((Content::ElseIf(elseif), span)) =
 			lines_iter.next_if(|next|
 				parent_indent == next.indent && matches!(next.content, Content::ElseIf(_)))
-			          .map(|next| next.content)
+			          .map(|next| (next.content, next.span))
 		{
 			let next_else_if_scope =
-				read_next_scope(parent_indent, lines_iter)
-					.map_err(|err| anyhow!(
+				read_next_scope(parent_indent, lines_iter, diagnostics)
+					.map_unlocated_err(|err| anyhow!(
 						"Could not build `elseif`'s child scope.\n\
 						 Fork data: `{elseif:?}`\n\
 						 Error: `{err}`"
 					))?;
 
-			if_branch.add_elseif(elseif, next_else_if_scope.map(Box::from))?;
+			if_branch.add_elseif(elseif, span, next_else_if_scope.map(Box::from))?;
 		}
 
 		// Else or EndIf
 		{
 			let Some(next_line) = lines_iter.next()
 			else {
-				return Err(anyhow!(
-					"Node ended before branch was closed, expected `<<endif>>`.\n\
-					 Unclosed branch started at: `{:?}`\n\n\
-					 Help: Branches are started with `<<if [condition]>>`, then ended with `<<endif>>`."
-					, if_branch.if_))
+				return Err(Diagnostic::error(ErrorCode::UnclosedBranch, "Node ended before branch was closed, expected `<<endif>>`.")
+					.with_span(if_span)
+					.with_help("Branches are started with `<<if [condition]>>`, then ended with `<<endif>>`.")
+					.into())
 			};
 
 			if next_line.indent != parent_indent {
-				return Err(anyhow!(
-					"Indentation mismatch in `<<else>>` or `<<endif>>`, expected: {parent_indent}, got: {}\n\
-					 At line `{:?}`\n\n\
-					 Help: Indentation is calculated by: [space + tabs * 4]\n\
-					 Help: Every fork(`<<if`, `<<elseif`, `<<else`) needs to have the same indentation."
-					, next_line.indent, next_line.content));
+				return Err(Diagnostic::error(ErrorCode::IndentationMismatch, format!(
+					"Indentation mismatch in `<<else>>` or `<<endif>>`, expected: {parent_indent}, got: {}", next_line.indent))
+					.with_span(next_line.span)
+					.with_source_line(next_line.text)
+					.with_help("Indentation is calculated by: [space + tabs * 4]")
+					.with_help("Every fork(`<<if`, `<<elseif`, `<<else`) needs to have the same indentation.")
+					.into());
 			}
 
 			match next_line.content {
-				invalid_content @ (
 				| Content::Speech(_)
 				| Content::Command(_)
 				| Content::OptionLine(_)
 				| Content::EndOptions(_)
+				| Content::GroupLine(_)
 				| Content::If(_)
-				| Content::ElseIf(_)) => {
-					return Err(anyhow!(
-						"Expected `<<else>>` or `<<endif>>`\n\
-						 Got: {:?}\n\n\
-						 Help: Branches are started with `<<if [condition]>>`, then closed with `<<endif>>`.\n\
-						 Help: As long as a Branch is open, parallel(same indentation, not necessarily adjacent) lines must be \
-						 either `<<elseif [condition]>>`, `<<else>>` or `<<endif>>`.\n\
-						 Help: To close a branch, use `<<endif>>`."
-						, invalid_content));
+				| Content::ElseIf(_)
+				| Content::Once(_)
+				| Content::EndOnce(_) => {
+					return Err(Diagnostic::error(ErrorCode::UnexpectedLineInBranch, "Expected `<<else>>` or `<<endif>>`.")
+						.with_span(next_line.span)
+						.with_source_line(next_line.text)
+						.with_note(format!("The branch started at line nº{}.", if_branch.if_.0.line_number))
+						.with_help(BRANCH_HELP)
+						.into());
 				}
 				Content::EndIf(_) => {
 					return Ok(if_branch);
 				}
 				Content::Else(else_) => {
 					let else_child_scope =
-						read_next_scope(parent_indent, lines_iter)
-							.map_err(|err| anyhow!(
+						read_next_scope(parent_indent, lines_iter, diagnostics)
+							.map_unlocated_err(|err| anyhow!(
 								"Could not build `else`'s child scope.\n\
 						         Else data: `{else_:?}`\n\
 								 Error: `{err}`")
 							)?;
 
-					if_branch.add_else(else_, else_child_scope.map(Box::from))?;
+					if_branch.add_else(else_, next_line.span, else_child_scope.map(Box::from))?;
 				}
 			}
 		}
@@ -172,38 +178,39 @@ impl IfBranch {
 		{
 			let Some(next_line) = lines_iter.next()
 			else {
-				return Err(anyhow!(
-					"Node ended before branch was closed, expected `<<endif>>`.\n\
-					 Unclosed branch started at: `{:?}`\n\n\
-					 Help: Branches are started with `<<if [condition]>>`, then ended with `<<endif>>`."
-					, if_branch.if_))
+				return Err(Diagnostic::error(ErrorCode::UnclosedBranch, "Node ended before branch was closed, expected `<<endif>>`.")
+					.with_span(if_span)
+					.with_help("Branches are started with `<<if [condition]>>`, then ended with `<<endif>>`.")
+					.into())
 			};
 
 			if next_line.indent != parent_indent {
-				return Err(anyhow!(
-					"Indentation mismatch in `<<endif>>`, expected: {parent_indent}, got: {}\n\n\
-					 Help: Indentation is calculated by: [space + tabs * 4]\n\
-					 Help: Every fork(`<<if`, `<<elseif`, `<<else`) needs to have the same indentation."
-					, next_line.indent));
+				return Err(Diagnostic::error(ErrorCode::IndentationMismatch, format!(
+					"Indentation mismatch in `<<endif>>`, expected: {parent_indent}, got: {}", next_line.indent))
+					.with_span(next_line.span)
+					.with_source_line(next_line.text)
+					.with_help("Indentation is calculated by: [space + tabs * 4]")
+					.with_help("Every fork(`<<if`, `<<elseif`, `<<else`) needs to have the same indentation.")
+					.into());
 			}
 
 			match next_line.content {
-				invalid_content @ (
 				| Content::Speech(_)
 				| Content::Command(_)
 				| Content::OptionLine(_)
 				| Content::EndOptions(_)
+				| Content::GroupLine(_)
 				| Content::If(_)
 				| Content::ElseIf(_)
-				| Content::Else(_)) => {
-					Err(anyhow!(
-						"Expected `<<endif>>`\n\
-						 Got: {:?}\n\n\
-						 Help: Branches are started with `<<if [condition]>>`, then closed with `<<endif>>`.\n\
-						 Help: As long as a Branch is open, parallel(same indentation, not necessarily adjacent) lines must be \
-						 either `<<elseif [condition]>>`, `<<else>>` or `<<endif>>`.\n\
-						 Help: To close a branch, use `<<endif>>`."
-						, invalid_content))
+				| Content::Else(_)
+				| Content::Once(_)
+				| Content::EndOnce(_) => {
+					Err(Diagnostic::error(ErrorCode::UnexpectedLineInBranch, "Expected `<<endif>>`.")
+						.with_span(next_line.span)
+						.with_source_line(next_line.text)
+						.with_note(format!("The branch started at line nº{}.", if_branch.if_.0.line_number))
+						.with_help(BRANCH_HELP)
+						.into())
 				}
 				Content::EndIf(_) => {
 					Ok(if_branch)
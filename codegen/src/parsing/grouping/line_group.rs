@@ -0,0 +1,86 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
+use crate::Indent;
+use anyhow::*;
+use crate::diagnostic::{Diagnostic, ErrorCode, MapUnlocatedErr, Span};
+use houtamelo_utils::prelude::CountOrMore;
+use scope::read_next_scope;
+use crate::parsing::grouping::scope;
+use crate::parsing::grouping::scope::YarnScope;
+use crate::parsing::raw::line_group::GroupLine;
+use crate::parsing::raw::{Content, RawLine};
+use serde::{Deserialize, Serialize};
+
+/// Sibling `=> lines`, one of them is picked each time the group is reached.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineGroup {
+	pub lines: CountOrMore<1, (GroupLine, Option<Box<YarnScope>>)>,
+}
+
+impl LineGroup {
+	fn lines_indent(&self) -> Option<Indent> {
+		self.lines
+			.iter()
+			.find_map(|(_, scope_option)|
+				scope_option
+					.as_ref()
+					.map(|scope| scope.indent()))
+	}
+
+	fn add_line(&mut self, group_line: GroupLine, span: Span,
+	            line_scope: Option<Box<YarnScope>>) -> Result<()> {
+		if let (Some(expected), Some(found)) =
+			(self.lines_indent(), line_scope.as_ref().map(|scope| scope.indent()))
+			&& found != expected {
+			return Err(Diagnostic::error(ErrorCode::IndentationMismatch, format!(
+				"Indentation mismatch in the scope of `=> line`, expected: {expected}, got: {found}."))
+				.with_span(span)
+				.with_help("Indentation is calculated by: [space + tabs * 4]")
+				.with_help("The contents of every line in a line group should have the same indentation.")
+				.into());
+		}
+
+		self.lines.push((group_line, line_scope));
+		Ok(())
+	}
+
+	pub fn build(parent_indent: Indent, first_line: GroupLine,
+	             lines_iter: &mut Peekable<IntoIter<RawLine>>,
+	             diagnostics: &mut Vec<Diagnostic>) -> Result<LineGroup> {
+		let first_line_scope =
+			read_next_scope(parent_indent, lines_iter, diagnostics)
+				.map_unlocated_err(|err| anyhow!(
+					"Could not build `=> line`'s child scope.\n\
+					 Line data: `{first_line:?}`\n\
+					 Error: {err}")
+				)?;
+
+		let mut group = LineGroup {
+			lines: CountOrMore::new([
+				(first_line, first_line_scope.map(Box::from))],
+				vec![])
+		};
+
+		while let Some((Content::GroupLine(group_line), span)) =
+			lines_iter.next_if(|next| {
+				next.indent == parent_indent && matches!(next.content, Content::GroupLine(_))
+			}).map(|next| (next.content, next.span))
+		{
+			let next_line_scope =
+				read_next_scope(parent_indent, lines_iter, diagnostics)
+					.map_unlocated_err(|err| anyhow!(
+						"Could not build `=> line`'s child scope.\n\
+						 Line data: `{group_line:?}`\n\
+						 Error: {err}")
+					)?;
+
+			group.add_line(group_line, span, next_line_scope.map(Box::from))?;
+		}
+
+		Ok(group)
+	}
+
+	pub fn iter_lines(&self) -> impl Iterator<Item = &(GroupLine, Option<Box<YarnScope>>)> {
+		self.lines.iter()
+	}
+}
@@ -1,14 +1,25 @@
 use crate::parsing::grouping::scope::read_next_scope;
 use crate::parsing::raw::RawNode;
-use anyhow::{anyhow, Result};
+use anyhow::anyhow;
+use crate::diagnostic::{into_diagnostics, Diagnostic, MapUnlocatedErr};
 use crate::parsing::YarnNode;
+use std::path::Path;
 
 pub mod options;
+pub mod line_group;
 pub mod if_branch;
+pub mod once_branch;
 pub mod scope;
 
-pub fn parse_node_contents(node: RawNode) -> Result<YarnNode> {
+/// Errors are reported to `diagnostics`, returns `None` if the node's structure is too broken to be recovered.
+pub fn parse_node_contents(node: RawNode, source_file: &Path, diagnostics: &mut Vec<Diagnostic>) -> Option<YarnNode> {
 	let raw_lines = node.lines;
+	let source_lines =
+		raw_lines
+			.iter()
+			.map(|line| (line.span.line, line.text.trim().to_string()))
+			.collect();
+
 	let mut stream_buffer =
 		raw_lines
 			.into_iter()
@@ -17,19 +28,31 @@ pub fn parse_node_contents(node: RawNode) -> Result<YarnNode> {
 	let mut contents = Vec::new();
 	while stream_buffer.peek().is_some() {
 		let next_scope_option =
-			read_next_scope(-1, &mut stream_buffer)
-				.map_err(|err| anyhow!(
+			read_next_scope(-1, &mut stream_buffer, diagnostics)
+				.map_unlocated_err(|err| anyhow!(
 					"Could not read next scope.\n\
 					 Node: {}\n\
-					 Error: {err}", node.metadata.title))?;
+					 File: {}\n\
+					 Error: {err}", node.metadata.title, source_file.display()));
+		
+		let next_scope_option =
+			match next_scope_option {
+				Ok(next_scope_option) => next_scope_option,
+				Err(err) => {
+					diagnostics.extend(into_diagnostics(err));
+					return None;
+				},
+			};
 
 		if let Some(next_scope) = next_scope_option {
 			contents.push(next_scope);
 		}
 	}
 
-	Ok(YarnNode {
+	Some(YarnNode {
 		metadata: node.metadata,
 		contents,
+		source_file: source_file.to_path_buf(),
+		source_lines,
 	})
 }
\ No newline at end of file
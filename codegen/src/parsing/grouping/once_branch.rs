@@ -0,0 +1,125 @@
+use crate::parsing::grouping::scope;
+use crate::parsing::grouping::scope::YarnScope;
+use crate::parsing::raw::branches::if_statement::Else_;
+use crate::parsing::raw::branches::once_statement::Once_;
+use crate::parsing::raw::{Content, RawLine};
+use crate::Indent;
+use anyhow::{anyhow, Result};
+use crate::diagnostic::{Diagnostic, ErrorCode, MapUnlocatedErr, Span};
+use scope::read_next_scope;
+use std::iter::Peekable;
+use std::vec::IntoIter;
+use serde::{Deserialize, Serialize};
+
+/// `<<once>>` ... `<<else>>` ... `<<endonce>>`, the `else` scope runs every time after the first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OnceBranch {
+	pub once_: (Once_, Option<Box<YarnScope>>),
+	pub else_: Option<(Else_, Option<Box<YarnScope>>)>,
+}
+
+const ONCE_HELP: &str =
+	"Once blocks are started with `<<once>>` or `<<once if [condition]>>`, then closed with `<<endonce>>`. \
+	 As long as a block is open, parallel(same indentation, not necessarily adjacent) lines must be \
+	 either `<<else>>` or `<<endonce>>`.";
+
+impl OnceBranch {
+	fn add_else(&mut self, else_: Else_, span: Span, scope_option: Option<Box<YarnScope>>) -> Result<()> {
+		if let (Some(expected), Some(found)) =
+			(self.once_.1.as_ref().map(|scope| scope.indent()), scope_option.as_ref().map(|scope| scope.indent()))
+			&& found != expected
+		{
+			return Err(Diagnostic::error(ErrorCode::IndentationMismatch, format!(
+				"Indentation mismatch in the scope of `<<else>>`, expected: {expected}, got: {found}."))
+				.with_span(span)
+				.with_help("Indentation is calculated by: [space + tabs * 4]")
+				.with_help("Both forks(`<<once`, `<<else`) need to have the same indentation.")
+				.into());
+		}
+
+		self.else_ = Some((else_, scope_option));
+		Ok(())
+	}
+
+	fn next_line(&self, parent_indent: Indent, once_span: &Span,
+	             lines_iter: &mut Peekable<IntoIter<RawLine>>) -> Result<RawLine> {
+		let Some(next_line) = lines_iter.next()
+			else {
+				return Err(Diagnostic::error(ErrorCode::UnclosedBranch, "Node ended before once block was closed, expected `<<endonce>>`.")
+					.with_span(once_span.clone())
+					.with_help(ONCE_HELP)
+					.into())
+			};
+
+		if next_line.indent != parent_indent {
+			return Err(Diagnostic::error(ErrorCode::IndentationMismatch, format!(
+				"Indentation mismatch in `<<else>>` or `<<endonce>>`, expected: {parent_indent}, got: {}", next_line.indent))
+				.with_span(next_line.span)
+				.with_source_line(next_line.text)
+				.with_help("Indentation is calculated by: [space + tabs * 4]")
+				.with_help("`<<else>>` and `<<endonce>>` need to have the same indentation as their `<<once>>`.")
+				.into());
+		}
+
+		Ok(next_line)
+	}
+
+	fn unexpected_line(&self, next_line: RawLine, expected: &str) -> anyhow::Error {
+		Diagnostic::error(ErrorCode::UnexpectedLineInBranch, format!("Expected {expected}."))
+			.with_span(next_line.span)
+			.with_source_line(next_line.text)
+			.with_note(format!("The once block started at line nº{}.", self.once_.0.line_number))
+			.with_help(ONCE_HELP)
+			.into()
+	}
+
+	/// `once_span` points at `once_line`, to report blocks that are never closed.
+	pub fn build(parent_indent: Indent, once_line: Once_, once_span: Span,
+	             lines_iter: &mut Peekable<IntoIter<RawLine>>,
+	             diagnostics: &mut Vec<Diagnostic>) -> Result<OnceBranch> {
+		let once_scope =
+			read_next_scope(parent_indent, lines_iter, diagnostics)
+				.map_unlocated_err(|err| anyhow!(
+					"Could not build `once`'s child scope.\n\
+					 Once data: `{once_line:?}`\n\
+					 Error: {err}")
+				)?;
+
+		let mut once_branch =
+			OnceBranch {
+				once_: (once_line, once_scope.map(Box::from)),
+				else_: None,
+			};
+
+		// Else or EndOnce
+		let next_line = once_branch.next_line(parent_indent, &once_span, lines_iter)?;
+
+		match next_line.content {
+			Content::EndOnce(_) => {
+				return Ok(once_branch);
+			}
+			Content::Else(else_) => {
+				let else_child_scope =
+					read_next_scope(parent_indent, lines_iter, diagnostics)
+						.map_unlocated_err(|err| anyhow!(
+							"Could not build `else`'s child scope.\n\
+							 Else data: `{else_:?}`\n\
+							 Error: `{err}`")
+						)?;
+
+				once_branch.add_else(else_, next_line.span, else_child_scope.map(Box::from))?;
+			}
+			_ => {
+				return Err(once_branch.unexpected_line(next_line, "`<<else>>` or `<<endonce>>`"));
+			}
+		}
+
+		// Only EndOnce
+		let next_line = once_branch.next_line(parent_indent, &once_span, lines_iter)?;
+
+		match next_line.content {
+			Content::EndOnce(_) => Ok(once_branch),
+			_ => Err(once_branch.unexpected_line(next_line, "`<<endonce>>`")),
+		}
+	}
+}
@@ -2,14 +2,16 @@ use std::iter::Peekable;
 use std::vec::IntoIter;
 use crate::Indent;
 use anyhow::*;
+use crate::diagnostic::{Diagnostic, ErrorCode, MapUnlocatedErr, Span};
 use houtamelo_utils::prelude::CountOrMore;
 use scope::read_next_scope;
 use crate::parsing::grouping::scope;
 use crate::parsing::grouping::scope::YarnScope;
 use crate::parsing::raw::branches::options::OptionLine;
 use crate::parsing::raw::{Content, RawLine};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OptionsFork {
 	pub options: CountOrMore<1, (OptionLine, Option<Box<YarnScope>>)>,
 }
@@ -24,19 +26,17 @@ impl OptionsFork {
 					.map(|scope| scope.indent()))
 	}
 
-	fn add_option(&mut self, choice_option: OptionLine,
+	fn add_option(&mut self, choice_option: OptionLine, span: Span,
 	              option_scope: Option<Box<YarnScope>>) -> Result<()> {
 		if let (Some(expected), Some(found)) =
 			(self.options_indent(), option_scope.as_ref().map(|scope| scope.indent()))
 			&& found != expected {
-			return Err(anyhow!(
-				"Cannot add `option line` to `options fork`.\n\
-				 Error: Indentation mismatch, expected: {expected}, got: {found}.\n\
-				 Trying to add: {choice_option:?}.\n\
-				 Other options: \n\t{:?}\n\n\
-				 Help: Indentation is calculated by: [space + tabs * 4]\n\
-				 Help: Every option line should have the same indentation."
-				, self.options.iter().map(|(o, _)| format!("{o:?}")).collect::<Vec<String>>().join("\n\t")));
+			return Err(Diagnostic::error(ErrorCode::IndentationMismatch, format!(
+				"Indentation mismatch in the scope of `option line`, expected: {expected}, got: {found}."))
+				.with_span(span)
+				.with_help("Indentation is calculated by: [space + tabs * 4]")
+				.with_help("The contents of every option line should have the same indentation.")
+				.into());
 		}
 
 		self.options.push((choice_option, option_scope));
@@ -44,10 +44,11 @@ impl OptionsFork {
 	}
 	
 	pub fn build(parent_indent: Indent, first_option: OptionLine,
-	             lines_iter: &mut Peekable<IntoIter<RawLine>>) -> Result<OptionsFork> {
+	             lines_iter: &mut Peekable<IntoIter<RawLine>>,
+	             diagnostics: &mut Vec<Diagnostic>) -> Result<OptionsFork> {
 		let first_option_scope =
-			read_next_scope(parent_indent, lines_iter)
-				.map_err(|err| anyhow!(
+			read_next_scope(parent_indent, lines_iter, diagnostics)
+				.map_unlocated_err(|err| anyhow!(
 					"Could not build option's child scope.\n\
 					 Option data: `{first_option:?}`\n\
 					 Error: {err}")
@@ -59,14 +60,14 @@ impl OptionsFork {
 				vec![])
 		};
 
-		while let Some(Content::OptionLine(choice_option)) = 
+		while let Some((Content::OptionLine(choice_option), span)) = 
 			lines_iter.next_if(|next| {
 				next.indent == parent_indent && matches!(next.content, Content::OptionLine(_))
-			}).map(|next| next.content)
+			}).map(|next| (next.content, next.span))
 		{
 			let next_option_scope =
-				read_next_scope(parent_indent, lines_iter)
-					.map_err(|err| anyhow!(
+				read_next_scope(parent_indent, lines_iter, diagnostics)
+					.map_unlocated_err(|err| anyhow!(
 						"Could not build option's child scope.\n\
 						 Option data: `{choice_option:?}`\n\
 						 Error: {err}\n\
@@ -74,7 +75,7 @@ impl OptionsFork {
 						, choices.options.iter().map(|(o, _)| format!("{o:?}")).collect::<Vec<String>>().join("\n\t"))
 					)?;
 			
-			choices.add_option(choice_option, next_option_scope.map(Box::from))?;
+			choices.add_option(choice_option, span, next_option_scope.map(Box::from))?;
 		}
 		
 		lines_iter.next_if(|next| 
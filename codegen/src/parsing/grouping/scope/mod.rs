@@ -4,26 +4,32 @@ use std::iter::Peekable;
 use std::vec::IntoIter;
 use crate::Indent;
 use crate::parsing::raw::command::YarnCommand;
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use crate::diagnostic::{Diagnostic, ErrorCode};
 use crate::parsing::grouping::options::OptionsFork;
+use crate::parsing::grouping::line_group::LineGroup;
 use crate::parsing::grouping::if_branch::IfBranch;
+use crate::parsing::grouping::once_branch::OnceBranch;
 use crate::parsing::raw::{Content, RawLine};
 use crate::parsing::raw::speech::Speech;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FlatLine {
 	Speech(Speech),
 	Command(YarnCommand),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Flow {
 	Flat(Vec<FlatLine>),
 	OptionsFork(OptionsFork),
+	LineGroup(LineGroup),
 	IfBranch(IfBranch),
+	OnceBranch(OnceBranch),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct YarnScope {
 	indent: Indent,
 	flows: Vec<Flow>,
@@ -41,6 +47,10 @@ impl YarnScope {
 	pub fn flows(&self) -> &[Flow] {
 		&self.flows
 	}
+
+	pub fn flows_mut(&mut self) -> &mut [Flow] {
+		&mut self.flows
+	}
 }
 
 fn peek_next_line_indent(lines_iter: &mut Peekable<IntoIter<RawLine>>)
@@ -50,8 +60,12 @@ fn peek_next_line_indent(lines_iter: &mut Peekable<IntoIter<RawLine>>)
 		.map(|line| line.indent)
 }
 
-pub fn read_next_scope(parent_indent: Indent, lines_iter: &mut Peekable<IntoIter<RawLine>>)
-                       -> Result<Option<YarnScope>> {
+/// Orphan lines are reported to `diagnostics` and skipped.
+pub fn read_next_scope(
+	parent_indent: Indent,
+	lines_iter: &mut Peekable<IntoIter<RawLine>>,
+	diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Option<YarnScope>> {
 	let Some(self_indent) = peek_next_line_indent(lines_iter)
 		else { return Ok(None) };
 	
@@ -71,7 +85,7 @@ pub fn read_next_scope(parent_indent: Indent, lines_iter: &mut Peekable<IntoIter
 		match next_line.content {
 			Content::OptionLine(first_option) => {
 				let choices =
-					OptionsFork::build(self_indent, first_option, lines_iter)?;
+					OptionsFork::build(self_indent, first_option, lines_iter, diagnostics)?;
 
 				if !flat_lines.is_empty() {
 					flows.push(Flow::Flat(flat_lines));
@@ -80,14 +94,24 @@ pub fn read_next_scope(parent_indent: Indent, lines_iter: &mut Peekable<IntoIter
 
 				flows.push(Flow::OptionsFork(choices));
 			},
-			Content::EndOptions(end_options) => {
-				return Err(anyhow!(
-					"Orphan `end options`(`<-`) detected.\n\
-					 Offending line: `{end_options:?}`\n\n\
-					 Help: `end options`(`<-`) is only allowed when parallel with a `-> Option Text` branch starter."))
+			Content::GroupLine(first_line) => {
+				let group =
+					LineGroup::build(self_indent, first_line, lines_iter, diagnostics)?;
+
+				if !flat_lines.is_empty() {
+					flows.push(Flow::Flat(std::mem::take(&mut flat_lines)));
+				}
+
+				flows.push(Flow::LineGroup(group));
+			},
+			Content::EndOptions(_) => {
+				diagnostics.push(Diagnostic::error(ErrorCode::OrphanEndOptions, "Orphan `end options`(`<-`) detected.")
+					.with_span(next_line.span)
+					.with_source_line(next_line.text)
+					.with_help("`end options`(`<-`) is only allowed when parallel with a `-> Option Text` branch starter."));
 			},
 			Content::If(if_) => {
-				let if_branch = IfBranch::build(self_indent, if_, lines_iter)?;
+				let if_branch = IfBranch::build(self_indent, if_, next_line.span, lines_iter, diagnostics)?;
 
 				if !flat_lines.is_empty() {
 					flows.push(Flow::Flat(std::mem::take(&mut flat_lines)));
@@ -95,19 +119,34 @@ pub fn read_next_scope(parent_indent: Indent, lines_iter: &mut Peekable<IntoIter
 
 				flows.push(Flow::IfBranch(if_branch));
 			},
+			Content::Once(once_) => {
+				let once_branch = OnceBranch::build(self_indent, once_, next_line.span, lines_iter, diagnostics)?;
+
+				if !flat_lines.is_empty() {
+					flows.push(Flow::Flat(std::mem::take(&mut flat_lines)));
+				}
+
+				flows.push(Flow::OnceBranch(once_branch));
+			},
 			Content::Speech(speech) => {
 				flat_lines.push(FlatLine::Speech(speech));
 			},
 			Content::Command(command) => {
 				flat_lines.push(FlatLine::Command(command));
 			},
-			fork @ (| Content::ElseIf(_)
+			| Content::ElseIf(_)
 			| Content::Else(_)
-			| Content::EndIf(_)) => {
-				return Err(anyhow!(
-					"Orphan conditional fork(`<<elseif`, `<<else`, `<<endif`) detected.\n\
-					 Offending line: `{fork:?}`\n\n\
-					 Help: Conditional forks are only allowed when parallel with a `<<if [condition]>>` branch starter."))
+			| Content::EndIf(_) => {
+				diagnostics.push(Diagnostic::error(ErrorCode::OrphanFork, "Orphan conditional fork(`<<elseif`, `<<else`, `<<endif`) detected.")
+					.with_span(next_line.span)
+					.with_source_line(next_line.text)
+					.with_help("Conditional forks are only allowed when parallel with a `<<if [condition]>>` branch starter."));
+			},
+			Content::EndOnce(_) => {
+				diagnostics.push(Diagnostic::error(ErrorCode::OrphanFork, "Orphan `<<endonce>>` detected.")
+					.with_span(next_line.span)
+					.with_source_line(next_line.text)
+					.with_help("`<<endonce>>` is only allowed when parallel with a `<<once>>` block starter."));
 			},
 		}
 	}
@@ -44,6 +44,7 @@ pub fn node_1_raw() -> RawNode {
 			tags: own_vec!["night", "outside"],
 			tracking: Some(TrackingSetting::Never),
 			customs: vec![],
+			when: vec![],
 		},
 		lines: vec![
 			RawLine { 
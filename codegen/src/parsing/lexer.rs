@@ -0,0 +1,288 @@
+//! Splits a yarn line into the tokens every line parser works with.
+//!
+//! A `//` starts a comment unless it's part of a `://`, so `https://example.com` is text
+//! while `Hello // note` and `Nice!//note` are comments,
+//! or unless it's between quotes, `She said "a // b"` has no comment.
+//! Inside `{interpolations}` and `<<commands>>`, `"string literals"` are respected,
+//! `<<set $url to "a // b">>` is a command whose argument contains `//`.
+
+use anyhow::{anyhow, Result};
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+	/// Plain text, never contains escapes.
+	Text(&'a str),
+	/// A character escaped with a backslash (`\{`, `\#`, ...).
+	Escaped(char),
+	/// The contents between `{` and its matching `}`.
+	Interpolation(&'a str),
+	/// The contents between `<<` and `>>`.
+	Command(&'a str),
+	/// Everything after the first `#`, up to the comment.
+	Tags(&'a str),
+	/// `//` and everything after it.
+	Comment(&'a str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lexeme<'a> {
+	pub token: Token<'a>,
+	/// Byte range of the token in the line, delimiters included.
+	pub span: Range<usize>,
+}
+
+/// Tracks whether the chars fed to it are part of a `"string literal"`, quotes included.
+#[derive(Default)]
+struct Literals {
+	inside: bool,
+	escaped: bool,
+}
+
+impl Literals {
+	fn feed(&mut self, ch: char) -> bool {
+		let was_inside = self.inside;
+
+		if self.escaped {
+			self.escaped = false;
+		} else if self.inside && ch == '\\' {
+			self.escaped = true;
+		} else if ch == '"' {
+			self.inside = !self.inside;
+		}
+
+		was_inside || self.inside
+	}
+}
+
+/// Each char of `code` with its byte index, and whether it's part of a string literal, quotes included.
+pub fn scan_literals(code: &str) -> Vec<(usize, char, bool)> {
+	let mut literals = Literals::default();
+
+	code.char_indices()
+	    .map(|(index, ch)| (index, ch, literals.feed(ch)))
+	    .collect()
+}
+
+pub struct Lexer<'a> {
+	line: &'a str,
+	position: usize,
+	/// Whether the previous char was a `:`, the `//` of a `://` isn't a comment.
+	after_colon: bool,
+	/// Quotes in text don't make literals, but they still hide comments.
+	quotes: Literals,
+}
+
+pub fn lex(line: &str) -> Lexer {
+	Lexer {
+		line,
+		position: 0,
+		after_colon: false,
+		quotes: Literals::default(),
+	}
+}
+
+/// Byte index where the `//` comment of `line` starts.
+///
+/// Lines the lexer rejects are left untouched, their parser reports the error.
+pub fn comment_start(line: &str) -> Option<usize> {
+	for lexeme in lex(line) {
+		match lexeme {
+			Ok(Lexeme { token: Token::Comment(_), span }) => return Some(span.start),
+			Ok(_) => {},
+			Err(_) => return None,
+		}
+	}
+
+	None
+}
+
+impl<'a> Lexer<'a> {
+	fn remaining(&self) -> &'a str {
+		&self.line[self.position..]
+	}
+
+	fn starts_comment(&self, at: usize, after_colon: bool) -> bool {
+		!after_colon && !self.quotes.inside && self.line[at..].starts_with("//")
+	}
+
+	fn lexeme(&mut self, token: Token<'a>, end: usize) -> Option<Result<Lexeme<'a>>> {
+		let span = self.position..end;
+		self.position = end;
+		Some(Ok(Lexeme { token, span }))
+	}
+
+	fn fail(&mut self, err: anyhow::Error) -> Option<Result<Lexeme<'a>>> {
+		self.position = self.line.len();
+		Some(Err(err))
+	}
+
+	fn lex_escape(&mut self) -> Option<Result<Lexeme<'a>>> {
+		let Some(escaped) = self.remaining()[1..].chars().next()
+			else {
+				return self.fail(anyhow!(
+					"Line ended with an escape character (`\\`).\n\n\
+					 Help: The escape character(`\\`) means nothing if there's no character after it."));
+			};
+
+		self.after_colon = false;
+		self.lexeme(Token::Escaped(escaped), self.position + 1 + escaped.len_utf8())
+	}
+
+	fn lex_interpolation(&mut self) -> Option<Result<Lexeme<'a>>> {
+		let start = self.position;
+		let mut nesting = vec![];
+
+		for (index, ch, in_literal) in scan_literals(self.remaining()) {
+			if in_literal {
+				continue;
+			}
+
+			match ch {
+				nest @ ('(' | '{' | '[') => nesting.push(nest),
+				un_nest @ (')' | '}' | ']') => {
+					let nest = nesting.pop().unwrap_or_default();
+
+					if !matches!((nest, un_nest), ('(', ')') | ('{', '}') | ('[', ']')) {
+						return self.fail(anyhow!(
+							"Invalid closing delimiter `{un_nest}` in `{{{argument}`.\n\n\
+							 Help: the closing delimiter `{un_nest}` does not match the most-recent opening delimiter `{nest}`.\n\
+							 Help: If you want to use '{{', '}}' as text, escape it with a backslash (`\\`).",
+							argument = &self.line[start + 1..start + index]));
+					}
+
+					if nesting.is_empty() {
+						let end = start + index + 1;
+						self.after_colon = false;
+						return self.lexeme(Token::Interpolation(&self.line[start + 1..end - 1]), end);
+					}
+				},
+				_ => {},
+			}
+		}
+
+		self.fail(anyhow!(
+			"Argument `{argument}` is not closed.\n\n\
+			 Help: For every opening delimiter(`(`, `{{`, `[`), there must be a matching closing delimiter(`)`, `}}`, `]`).\n\
+			 Help: Every string literal(started with `\"`) must end with another `\"`.\n\
+			 Help: If you want to use '{{', '}}' as text, escape it with a backslash (`\\`).",
+			argument = &self.line[start..]))
+	}
+
+	fn lex_command(&mut self) -> Option<Result<Lexeme<'a>>> {
+		let start = self.position;
+		let contents = &self.line[start + 2..];
+
+		let close =
+			scan_literals(contents)
+				.into_iter()
+				.find(|(index, _, in_literal)| !in_literal && contents[*index..].starts_with(">>"))
+				.map(|(index, ..)| index);
+
+		match close {
+			Some(close) => {
+				self.after_colon = false;
+				self.lexeme(Token::Command(&contents[..close]), start + 2 + close + 2)
+			},
+			None => self.fail(anyhow!(
+				"Command `{command}` is not closed.\n\n\
+				 Help: Commands must end with `>>`, `>>` inside string literals doesn't count.",
+				command = &self.line[start..])),
+		}
+	}
+
+	fn lex_tags(&mut self) -> Option<Result<Lexeme<'a>>> {
+		let mut after_colon = false;
+		let mut end = self.line.len();
+
+		for (index, ch) in self.remaining().char_indices().skip(1) {
+			if self.starts_comment(self.position + index, after_colon) {
+				end = self.position + index;
+				break;
+			}
+
+			after_colon = ch == ':';
+		}
+
+		self.after_colon = after_colon;
+		self.lexeme(Token::Tags(&self.line[self.position + 1..end]), end)
+	}
+
+	fn lex_text(&mut self) -> Option<Result<Lexeme<'a>>> {
+		let mut end = self.line.len();
+
+		for (index, ch) in self.remaining().char_indices() {
+			let at = self.position + index;
+
+			if index > 0
+				&& (matches!(ch, '\\' | '{' | '}' | '#')
+					|| self.line[at..].starts_with("<<")
+					|| self.starts_comment(at, self.after_colon)) {
+				end = at;
+				break;
+			}
+
+			self.after_colon = ch == ':';
+			self.quotes.feed(ch);
+		}
+
+		self.lexeme(Token::Text(&self.line[self.position..end]), end)
+	}
+}
+
+impl<'a> Iterator for Lexer<'a> {
+	type Item = Result<Lexeme<'a>>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let remaining = self.remaining();
+		let next = remaining.chars().next()?;
+
+		if self.starts_comment(self.position, self.after_colon) {
+			return self.lexeme(Token::Comment(remaining), self.line.len());
+		}
+
+		match next {
+			'\\' => self.lex_escape(),
+			'{' => self.lex_interpolation(),
+			'}' => self.fail(anyhow!(
+				"Unexpected closing delimiter `}}`.\n\n\
+				 Help: The closing delimiter `}}` does not match any opening delimiter `{{`.\n\
+				 Help: If you want to use '{{', '}}' as text, escape it with a backslash (`\\`).")),
+			'<' if remaining.starts_with("<<") => self.lex_command(),
+			'#' => self.lex_tags(),
+			_ => self.lex_text(),
+		}
+	}
+}
+
+#[test]
+fn test_lexer() {
+	use Token::*;
+
+	fn tokens(line: &str) -> Vec<Token> {
+		lex(line).map(|lexeme| lexeme.unwrap().token).collect()
+	}
+
+	assert_eq!(tokens("Check https://example.com"), [Text("Check https://example.com")]);
+	assert_eq!(tokens("Hello // a comment"), [Text("Hello "), Comment("// a comment")]);
+	assert_eq!(tokens("// only a comment"), [Comment("// only a comment")]);
+	assert_eq!(
+		tokens("<<set $url to \"a // b >> c\">> // set it"),
+		[Command("set $url to \"a // b >> c\""), Text(" "), Comment("// set it")]);
+	assert_eq!(
+		tokens("Alice: {$name} has {\"a } b\"}\\# coins #tag #url:https://x.com // done"),
+		[Text("Alice: "), Interpolation("$name"), Text(" has "), Interpolation("\"a } b\""),
+			Escaped('#'), Text(" coins "), Tags("tag #url:https://x.com "), Comment("// done")]);
+	assert_eq!(tokens("-> Go <<if $a>>//glued"), [Text("-> Go "), Command("if $a"), Comment("//glued")]);
+	assert_eq!(tokens("Nice!//note"), [Text("Nice!"), Comment("//note")]);
+	assert_eq!(tokens("word//comment"), [Text("word"), Comment("//comment")]);
+
+	assert!(lex("Hello }").any(|lexeme| lexeme.is_err()));
+	assert!(lex("Hello {(}").any(|lexeme| lexeme.is_err()));
+	assert!(lex("<<jump Start").any(|lexeme| lexeme.is_err()));
+	assert!(lex("Trailing \\").any(|lexeme| lexeme.is_err()));
+
+	assert_eq!(comment_start("Visit http://a.b // now"), Some(17));
+	assert_eq!(comment_start("<<wait \"//\">>"), None);
+	assert_eq!(comment_start("She said \"a // b\" // really"), Some(18));
+}
@@ -2,29 +2,44 @@ pub mod util;
 pub mod grouping;
 pub mod raw;
 pub mod macros;
+pub mod duplicates;
+pub mod lexer;
 
 use crate::io::read::YarnFile;
 use crate::parsing::raw::var_declaration::VarDeclaration;
-use anyhow::*;
+use crate::diagnostic::Diagnostic;
 use grouping::parse_node_contents;
 use grouping::scope::YarnScope;
 use raw::node_metadata::NodeMetadata;
 use raw::parse_raw_nodes;
+use serde::{Deserialize, Serialize};
+use crate::LineNumber;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct YarnNode {
 	pub metadata: NodeMetadata,
 	pub contents: Vec<YarnScope>,
+	/// The file this node was declared in.
+	pub source_file: PathBuf,
+	/// The trimmed yarn text of each line, generated code shows it next to what the line became.
+	pub source_lines: BTreeMap<LineNumber, String>,
 }
 
-pub fn parse_nodes(yarn_file: YarnFile) -> Result<(Vec<YarnNode>, Vec<VarDeclaration>)> {
-	let (raw_nodes, var_declarations) = parse_raw_nodes(yarn_file.lines)
-		.map_err(|err| anyhow!(
-			"Could not parse raw nodes from file.\n\
-			 Path: {}\n\
-			 Error: {err}", yarn_file.path.display())
-		)?;
+/// Parses every node of `yarn_file`, reporting errors to `diagnostics` instead of stopping at the first one.
+pub fn parse_nodes(yarn_file: YarnFile, diagnostics: &mut Vec<Diagnostic>) -> (Vec<YarnNode>, Vec<VarDeclaration>) {
+	let errors_before = diagnostics.len();
+	let (raw_nodes, var_declarations) = parse_raw_nodes(&yarn_file.path, yarn_file.lines, diagnostics);
 
-	let finished_nodes = raw_nodes.into_iter().map(parse_node_contents).try_collect()?;
+	let finished_nodes =
+		raw_nodes
+			.into_iter()
+			.filter_map(|raw_node| parse_node_contents(raw_node, &yarn_file.path, diagnostics))
+			.collect();
 
-	Ok((finished_nodes, var_declarations))
+	// Each parsing stage reports its own errors, sorting keeps them in the order they appear in the file.
+	diagnostics[errors_before..].sort_by_key(|diagnostic| diagnostic.span.as_ref().map(|span| span.line));
+
+	(finished_nodes, var_declarations)
 }
@@ -4,20 +4,21 @@ use crate::expressions::yarn_expr::YarnExpr;
 use crate::LineNumber;
 use crate::parsing::macros::{return_if_err, strip_end_then_trim, strip_start, strip_start_then_trim};
 use crate::parsing::raw::{ParseRawYarn, Content};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct If_ {
 	pub line_number: LineNumber,
 	pub condition: YarnExpr,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ElseIf_ {
 	pub line_number: LineNumber,
 	pub condition: YarnExpr,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Else_ {
 	pub line_number: LineNumber,
 }
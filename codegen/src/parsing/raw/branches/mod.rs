@@ -5,4 +5,5 @@ mod tests_options;
 mod tests_if;
 
 pub mod if_statement;
+pub mod once_statement;
 pub mod options;
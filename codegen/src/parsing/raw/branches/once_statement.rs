@@ -0,0 +1,100 @@
+use anyhow::{Result, anyhow};
+use crate::LineNumber;
+use crate::expressions::yarn_expr::YarnExpr;
+use crate::parsing::macros::{return_if_err, strip_end_then_trim, strip_start, strip_start_then_trim};
+use crate::parsing::raw::branches::options::parse_if_condition;
+use crate::parsing::raw::{ParseRawYarn, Content};
+use serde::{Deserialize, Serialize};
+
+/// `<<once>>` or `<<once if [condition]>>`, its contents only run the first time it's reached.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Once_ {
+	pub line_number: LineNumber,
+	pub condition: Option<YarnExpr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndOnce_ {
+	pub line_number: LineNumber,
+}
+
+/// `<<else>>` is shared with `<<if>>` branches, it's parsed by [BranchKind](super::if_statement::BranchKind).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OnceKind {
+	Once,
+	EndOnce,
+}
+
+impl ParseRawYarn for OnceKind {
+	fn parse_raw_yarn(line: &str, line_number: LineNumber) -> Option<Result<Content>> {
+		let mut line = line.trim();
+
+		if !strip_start_then_trim!(line, "<<") {
+			return None;
+		}
+
+		let once_kind =
+			if strip_start!(line, "once")
+				&& line.starts_with([' ', '>', '\t']) {
+				OnceKind::Once
+			} else if strip_start!(line, "endonce")
+				&& line.starts_with([' ', '>', '\t']) {
+				OnceKind::EndOnce
+			} else {
+				return None;
+			};
+
+		if !strip_end_then_trim!(line, ">>") {
+			return Some(Err(anyhow!(
+				"{once_kind:?} statement did not end with `>>`.\n\
+				 Remaining Line: `{line}`")));
+		}
+
+		match once_kind {
+			OnceKind::Once => {
+				let mut line = line.trim();
+
+				let condition =
+					if line.is_empty() {
+						None
+					} else if strip_start_then_trim!(line, "if") {
+						Some(return_if_err!(parse_if_condition(line)))
+					} else {
+						return Some(Err(anyhow!(
+							"Unexpected `{line}` after `<<once`.\n\n\
+							 Help: A once block is started with either `<<once>>` or `<<once if [condition]>>`.")));
+					};
+
+				Some(Ok(Content::Once(Once_ { line_number, condition })))
+			},
+			OnceKind::EndOnce => {
+				if !line.is_empty() {
+					return Some(Err(anyhow!(
+						"Unexpected `{line}` after `<<endonce`.\n\n\
+						 Help: Once blocks are closed with `<<endonce>>`.")));
+				}
+
+				Some(Ok(Content::EndOnce(EndOnce_ { line_number })))
+			},
+		}
+	}
+}
+
+#[test]
+fn test_once() {
+	use crate::expressions::parse_yarn_expr;
+
+	let parse = |line: &str| OnceKind::parse_raw_yarn(line, 7);
+
+	assert_eq!(parse("<<once>>").unwrap().unwrap(), Content::Once(Once_ { line_number: 7, condition: None }));
+	assert_eq!(
+		parse("<< once if $gold > 5 >>").unwrap().unwrap(),
+		Content::Once(Once_ { line_number: 7, condition: Some(parse_yarn_expr("$gold > 5").unwrap()) }));
+	assert_eq!(parse("<<endonce>>").unwrap().unwrap(), Content::EndOnce(EndOnce_ { line_number: 7 }));
+
+	assert!(parse("<<oncemore>>").is_none());
+	assert!(parse("<<endif>>").is_none());
+	assert!(matches!(parse("<<once $a>>"), Some(Err(_))));
+	assert!(matches!(parse("<<once if>>"), Some(Err(_))));
+	assert!(matches!(parse("<<endonce now>>"), Some(Err(_))));
+}
@@ -1,19 +1,21 @@
-use std::iter::Peekable;
-use std::str::Chars;
 use anyhow::{Result, anyhow};
 use trim_in_place::TrimInPlace;
 use expressions::parse_yarn_expr;
 use crate::{expressions, LineNumber};
 use crate::expressions::yarn_expr::YarnExpr;
+use crate::parsing::lexer::{lex, Token};
 use crate::parsing::macros::{return_if_err, strip_start_then_trim};
 use crate::parsing::raw::{ParseRawYarn, Content};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OptionLine {
 	pub line_number: LineNumber,
 	pub line_id: Option<String>,
 	pub text: (String, Vec<YarnExpr>),
 	pub if_condition: Option<YarnExpr>,
+	/// `<<once>>` or `<<once if [condition]>>`, the option is only available until it's picked.
+	pub once: bool,
 	pub tags: Vec<String>,
 }
 
@@ -22,194 +24,68 @@ pub struct EndOptions {
 	pub line_number: LineNumber,
 }
 
-enum CharState {
-	Std,
-	StringLiteral,
-	StringLiteralIgnoreNext,
-}
-
-enum State {
-	Lit {
-		ignore_next: bool,
-	},
-	Arg {
-		char_state: CharState,
-		previous_char: char,
-		nesting: Vec<char>,
-		sum: String,
-	},
-}
-
-fn parse_line(chars: &mut Peekable<Chars>, line_number: LineNumber) -> Result<OptionLine> {
-	let mut state = State::Lit { ignore_next: false };
+fn parse_line(line: &str, line_number: LineNumber) -> Result<OptionLine> {
 	let mut literal = String::new();
 	let mut args: Vec<String> = vec![];
 	let mut if_condition = None;
+	let mut once = false;
 	let mut metadata_option = None;
 
-	while let Some(next) = chars.next() {
-		match &mut state {
-			State::Lit { ignore_next: ignore_next @ true } => {
-				*ignore_next = false;
-				literal.push(next);
-			},
-			State::Lit { ignore_next: ignore_next @ false } => {
-				match next {
-					'\\' => {
-						*ignore_next = true;
-					},
-					'{' => {
-						state = State::Arg {
-							char_state: CharState::Std,
-							previous_char: next,
-							nesting: vec![],
-							sum: String::new(),
-						};
+	for lexeme in lex(line) {
+		let lexeme = lexeme?;
+		let has_condition = once || if_condition.is_some();
 
-						literal.push_str("{}");
-					},
-					'}' => {
-						return Err(anyhow!(
-							"Unexpected closing delimiter `}}` when parsing literal.\n\
-							 Built so far: \n\
-							 \tLiteral: `{literal}`\n\
-							 \tArguments: `{args:?}`\n\n\
-							 Help: The closing delimiter `}}` does not match any opening delimiter `{{`.\n\
-							 Help: If you want to use '{{', '}}' inside a string literal, escape it with a backslash (`\\`)."));
-					},
-					'<' => {
-						let Some('<') = chars.peek() 
-							else {
-								literal.push('<');
-								continue;
-							};
+		match lexeme.token {
+			Token::Text(text) if has_condition && !text.trim().is_empty() => {
+				let invalid_char = text.trim().chars().next().unwrap_or_default();
 
-						let remaining =
-							chars.clone()
-							     .skip(1)
-							     .collect::<String>();
-						
-						let mut remaining_str = 
-							remaining.as_str().trim();
-						
-						if strip_start_then_trim!(remaining_str, "if") { // if [condition]>>
-							let remaining_chars = 
-								remaining_str
-									.chars()
-									.peekable();
-							
-							let result = 
-								parse_if_condition_and_metadata(remaining_chars)?;
-							
-							if_condition = Some(result.0);
-							metadata_option = result.1;
-							break;
-						} else {
-							return Err(anyhow!(
-								"Invalid declaration: `<<` can only be followed by `if [condition]>>`.\n\
-								 Help: In a choice option, the `if` condition must follow the pattern `<<if [condition]>>`,\
-								  then optionally be followed by `#metadata here`"));
-						}
-					},
-					'#' => {
-						let built_metadata =
-							std::iter::once('#')
-								.chain(chars.by_ref())
-								.collect::<String>();
+				return Err(anyhow!(
+					"Invalid character `{invalid_char}` after `<<if [condition]>>` statement.\n\n\
+					 Help: In a choice option, the `if` condition must follow the pattern `<<if [condition]>>`,\
+					  then optionally be followed by `#metadata here`"));
+			},
+			Token::Escaped(_) | Token::Interpolation(_) | Token::Command(_) if has_condition => {
+				return Err(anyhow!(
+					"Unexpected `{}` after `<<if [condition]>>` statement.\n\n\
+					 Help: In a choice option, the `if` condition must follow the pattern `<<if [condition]>>`,\
+					  then optionally be followed by `#metadata here`", &line[lexeme.span]));
+			},
+			Token::Text(text) => {
+				literal.push_str(text);
+			},
+			Token::Escaped(escaped) => {
+				literal.push(escaped);
+			},
+			Token::Interpolation(arg) => {
+				literal.push_str("{}");
+				args.push(arg.to_string());
+			},
+			Token::Command(command) => {
+				let mut command = command.trim();
 
-						if built_metadata.len() > 1 {
-							metadata_option = Some(built_metadata)
-						}
+				if strip_start_then_trim!(command, "once") { // once>> or once if [condition]>>
+					once = true;
 
-						break;
-					},
-					other => {
-						literal.push(other);
-					},
+					if command.is_empty() {
+						continue;
+					}
 				}
-			},
-			State::Arg {
-				char_state: char_state @ CharState::Std,
-				previous_char, nesting, sum
-			} => {
-				match next {
-					'"' => {
-						*char_state = CharState::StringLiteral;
-						*previous_char = '"';
-						sum.push('"');
-					},
-					nest @ ('(' | '{' | '[') => {
-						*previous_char = nest;
-						nesting.push(nest);
-						sum.push(nest);
-					},
-					un_nest @ (')' | '}' | ']') => {
-						if let Some(nest) = nesting.pop() {
-							if matches!((nest, un_nest), ('(', ')') | ('{', '}') | ('[', ']')) {
-								*previous_char = un_nest;
-								sum.push(un_nest);
-							} else {
-								return Err(anyhow!(
-									"Invalid closing delimiter `{un_nest}` when parsing argument.\n\
-									 Argument: `{sum}`\n\
-									 Nesting: `{nesting:?}`\n\
-									 Built so far: \n\
-									 \tLiteral: `{literal}`\n\
-									 \tArguments: `{args:?}`\n\n\
-									 Help: the closing delimiter `{un_nest}` does not match the most-recent opening delimiter `{nest}`.\n\
-									 Help: if you want to use '{{', '}}' or '#' as a literal, escape it with a backslash (`\\`)."));
-							}
-						} else if un_nest == '}' {
-							args.push(std::mem::take(sum));
-							state = State::Lit { ignore_next: false };
-						} else {
-							return Err(anyhow!(
-								"Could not parse argument.\n\
-								 Error: Unexpected closing delimiter `{un_nest}`\n\
-								 Argument: `{sum}`\n\
-								 Nesting: `{nesting:?}`\n\
-								 Built so far: \n\
-								 \tLiteral: `{literal}`\n\
-								 \tArguments: `{args:?}`\n\
-								 \n\
-								 Remaining line: `{}`\n\n\
-								 Help: if you want to use '{{', '}}' or '#' as a literal, escape it with a backslash (`\\`)."
-								, chars.collect::<String>())); 
-						}
-					},
-					other => {
-						*previous_char = other;
-						sum.push(other);
-					},
+
+				if strip_start_then_trim!(command, "if") { // if [condition]>>
+					if_condition = Some(parse_if_condition(command)?);
+				} else {
+					return Err(anyhow!(
+						"Invalid declaration: `<<` can only be followed by `if [condition]>>`, `once>>` or `once if [condition]>>`.\n\
+						 Help: In a choice option, the `if` condition must follow the pattern `<<if [condition]>>`,\
+						  then optionally be followed by `#metadata here`"));
 				}
 			},
-			State::Arg {
-				char_state: char_state @ CharState::StringLiteral,
-				previous_char, sum, nesting: _nesting
-			} => {
-				match next {
-					'"' => {
-						*char_state = CharState::Std;
-						*previous_char = '"';
-						sum.push('"');
-					},
-					'\\' => {
-						*char_state = CharState::StringLiteralIgnoreNext;
-					},
-					other => {
-						*previous_char = other;
-						sum.push(other);
-					},
+			Token::Tags(tags) => {
+				if !tags.trim().is_empty() {
+					metadata_option = Some(tags.to_string());
 				}
 			},
-			State::Arg {
-				char_state: char_state @ CharState::StringLiteralIgnoreNext,
-				previous_char, sum, nesting: _nesting
-			} => {
-				*char_state = CharState::StringLiteral;
-				*previous_char = next;
-				sum.push(next);
-			},
+			Token::Comment(_) => {},
 		}
 	}
 
@@ -243,6 +119,7 @@ fn parse_line(chars: &mut Peekable<Chars>, line_number: LineNumber) -> Result<Op
 				line_id: None,
 				text: (literal, args_expr),
 				if_condition,
+				once,
 				tags: vec![],
 			});
 		};
@@ -277,6 +154,7 @@ fn parse_line(chars: &mut Peekable<Chars>, line_number: LineNumber) -> Result<Op
 			line_id: None,
 			text: (literal, args_expr),
 			if_condition,
+			once,
 			tags,
 		}),
 		1 => Ok(OptionLine {
@@ -284,6 +162,7 @@ fn parse_line(chars: &mut Peekable<Chars>, line_number: LineNumber) -> Result<Op
 			line_id: line_id.into_iter().next(),
 			text: (literal, args_expr),
 			if_condition,
+			once,
 			tags,
 		}),
 		_ => Err(anyhow!(
@@ -313,135 +192,17 @@ fn build_args(unparsed_args: Vec<String>) -> Result<Vec<YarnExpr>> {
 	Ok(exprs)
 }
 
-fn parse_if_condition_and_metadata(mut chars: Peekable<Chars>) -> Result<(YarnExpr, Option<String>)> {
-	let mut char_state = CharState::Std;
-	let mut nesting: Vec<char> = Vec::new();
-	let mut sum = String::new();
-
-	while let Some(next) = chars.next() {
-		match char_state {
-			CharState::Std => {
-				match next {
-					'"' => {
-						char_state = CharState::StringLiteral;
-						sum.push('"');
-					},
-					'>' => {
-						if !nesting.is_empty() || chars.next_if_eq(&'>').is_none() {
-							sum.push('>');
-							continue;
-						}
-						
-						if sum.is_empty() {
-							return Err(anyhow!(
-								"`if condition` delimiters(`<<` and `>>`) exist but argument is empty.\n\
-								 Nesting: `{nesting:?}`"));
-						}
-						
-						let remaining = chars.collect::<String>();
-						let mut remaining_str = remaining.as_str().trim();
-						let metadata = 
-							if strip_start_then_trim!(remaining_str, '#')
-								&& !remaining_str.is_empty() { 
-								Some(remaining_str.to_owned())
-							} else if remaining_str.is_empty() {
-								None
-							} else {
-								let invalid_char = 
-									remaining_str
-										.chars()
-										.next()
-										.unwrap();
-								
-								return Err(anyhow!(
-									"Invalid character `{invalid_char}` after `<<if [condition]>>` statement.\n\
-									 Argument: `{sum}`\n\n\
-									 Help: In a choice option, the `if` condition must follow the pattern `<<if [condition]>>`,\
-									  then optionally be followed by `#metadata here`"));
-							};
-						
-						let expr =
-							parse_yarn_expr(&sum)
-								.map_err(|err| anyhow!(
-									"Could not parse `if condition` as `YarnExpr`.\n\
-									 Error: `{err:?}`\n\
-									 Argument: `{sum}`")
-								)?;
-						
-						return Ok((expr, metadata));
-					},
-					nest @ ('(' | '{' | '[') => {
-						nesting.push(nest);
-						sum.push(nest);
-					},
-					un_nest @ (')' | '}' | ']') => {
-						if let Some(nest) = nesting.pop() {
-							if matches!((nest, un_nest), ('(', ')') | ('{', '}') | ('[', ']')) {
-								sum.push(un_nest);
-							} else {
-								return Err(anyhow!(
-									"Invalid closing delimiter `{un_nest}` in `if condition` argument.\n\
-									 Argument: `{sum}`\n\
-									 Nesting: `{nesting:?}`\n\n\
-									 Help: the closing delimiter `{un_nest}` does not match the most-recent opening delimiter `{nest}`."));
-							}
-						} else {
-							return Err(anyhow!(
-								"Unexpected closing delimiter `{un_nest}` in `if condition` argument.\n\
-								 Argument: `{sum}`\n\
-								 Nesting: `{nesting:?}`\n\n\
-								 Help: the characters ('{{', '(', '[') are treated as opening delimiters when inside arguments.\n\
-								 Help: for every opening delimiter, there must be a matching closing delimiter (and vice-versa)."));
-						}
-					},
-					other => {
-						sum.push(other);
-					},
-				}
-			},
-			CharState::StringLiteral => {
-				match next {
-					'"' => {
-						char_state = CharState::Std;
-						sum.push('"');
-					},
-					'\\' => {
-						char_state = CharState::StringLiteralIgnoreNext;
-					},
-					other => {
-						sum.push(other);
-					},
-				}
-			},
-			CharState::StringLiteralIgnoreNext => {
-				char_state = CharState::StringLiteral;
-				sum.push(next);
-			},
-		}
-	}
-	
-	if !nesting.is_empty() {
+pub(crate) fn parse_if_condition(condition: &str) -> Result<YarnExpr> {
+	if condition.is_empty() {
 		return Err(anyhow!(
-			"Unclosed delimiter(s) in `if condition` argument.\
-			 Delimiter(s): {nesting:?}\n\
-			 Argument: `{sum}`\n\n\
-			 Help: the characters ('{{', '(', '[') are treated as opening delimiters when inside arguments.\n\
-			 Help: for every opening delimiter, there must be a matching closing delimiter (and vice-versa)."));
-	}
-	
-	match char_state {
-		CharState::Std => Err(anyhow!(
-			"`if condition` argument is empty.\n\
-			 Argument: `{sum}`"
-		)),
-		| CharState::StringLiteral 
-		| CharState::StringLiteralIgnoreNext => {
-			Err(anyhow!(
-				"Unclosed string literal in `if condition` argument.\n\
-				 Argument: `{sum}`"
-			))
-		},
+			"`if condition` delimiters(`<<` and `>>`) exist but argument is empty."));
 	}
+
+	parse_yarn_expr(condition)
+		.map_err(|err| anyhow!(
+			"Could not parse `if condition` as `YarnExpr`.\n\
+			 Error: `{err:?}`\n\
+			 Argument: `{condition}`"))
 }
 
 impl ParseRawYarn for OptionLine {
@@ -467,18 +228,13 @@ impl ParseRawYarn for OptionLine {
 			return None;
 		}
 		
-		let mut chars = 
-			line.chars()
-				.peekable();
-
 		let choice_option =
 			return_if_err!(
-				parse_line(&mut chars, line_number)
+				parse_line(line, line_number)
 					.map_err(|err| anyhow!(
 						"Could not parse line as `ChoiceOption`.\n\
 						 Error: `{err:?}`\n\
-						 Remaining line: `{}`"
-						, chars.collect::<String>()))
+						 Line: `{line}`"))
 			);
 		
 		Some(Ok(Content::OptionLine(choice_option)))
@@ -53,6 +53,7 @@ macro_rules! option_line {
 			text: (own!($text), vec![$($(expr!($args)),*)?]),
 			tags: vec![$($(own!($tags)),*)?],
 		    if_condition: if_cd!($($if_cd)?),
+		    once: false,
 		}
     };
 }
@@ -103,6 +104,22 @@ fn test_with_if() {
 	);
 }
 
+#[test]
+fn test_once() {
+	assert_eq!(
+		parse_unwrap!("-> hello <<once>> #metadata"),
+		OptionLine { once: true, ..option_line!("hello", tags["metadata"]) }
+	);
+
+	assert_eq!(
+		parse_unwrap!("-> hello <<once if $condition>>"),
+		OptionLine { once: true, ..option_line!("hello", if expr!("$condition")) }
+	);
+
+	assert_matches!(parse!("-> hello <<once>> <<if $condition>>"), Some(Err(_)));
+	assert_matches!(parse!("-> hello <<once $condition>>"), Some(Err(_)));
+}
+
 #[test]
 fn test_invalid() {
 	assert_matches!(
@@ -13,22 +13,27 @@ use proc_macro2::{TokenStream, TokenTree};
 use std::any::type_name;
 use std::collections::VecDeque;
 use std::str::FromStr;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct YarnCommand {
 	pub line_number: LineNumber,
 	pub variant: CommandVariant,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CommandVariant {
 	Set { var_name: String, op: SetOperation, value: YarnExpr },
 	Jump { node_name: String },
+	/// Runs the node, then comes back to the line after the detour.
+	Detour { node_name: String },
+	/// Leaves the current detour early, behaves like `<<stop>>` outside of one.
+	Return,
 	Stop,
 	Other { variant: String, args: Vec<YarnExpr> },
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SetOperation {
 	Assign,
 	Add,
@@ -156,7 +161,7 @@ impl ParseRawYarn for YarnCommand {
 			return None;
 		}
 
-		if starts_with_any!(line, "if" | "elseif" | "else" | "endif" | "declare") {
+		if starts_with_any!(line, "if" | "elseif" | "else" | "endif" | "once" | "endonce" | "declare") {
 			return None;
 		}
 
@@ -219,6 +224,19 @@ impl ParseRawYarn for YarnCommand {
 						 Error: `{err}`")))
 				}
 			}
+			"detour" => {
+				match parse_jump_command(&mut args_iter) {
+					Ok(node_name) => Some(Ok(
+						Content::Command(YarnCommand {
+							line_number,
+							variant: CommandVariant::Detour { node_name },
+						}))),
+					Err(err) => Some(Err(anyhow!(
+						"Could not parse line as `detour` command(`<<detour [NodeName]>>`).\n\
+					     Remaining Line: {args_iter}.\n\
+						 Error: `{err}`")))
+				}
+			}
 			"stop" => {
 				Some(Ok(
 					Content::Command(YarnCommand {
@@ -226,6 +244,21 @@ impl ParseRawYarn for YarnCommand {
 						variant: CommandVariant::Stop,
 					})))
 			}
+			"return" => {
+				let remaining_line = args_iter.to_string();
+
+				if !remaining_line.trim().is_empty() {
+					return Some(Err(anyhow!(
+						"Unexpected `{remaining_line}` after `<<return`.\n\n\
+						 Help: `<<return>>` takes no arguments, it leaves the current detour.")));
+				}
+
+				Some(Ok(
+					Content::Command(YarnCommand {
+						line_number,
+						variant: CommandVariant::Return,
+					})))
+			}
 			_ => {
 				match parse_other_command(&mut args_iter, command_name) {
 					Ok((variant, args)) => Some(Ok(
@@ -234,7 +267,7 @@ impl ParseRawYarn for YarnCommand {
 							variant: CommandVariant::Other { variant, args },
 						}))),
 					Err(err) => Some(Err(anyhow!(
-						"Could not parse line as `other`(not `set`, `jump` or `stop`) command(`{}`).\n\
+						"Could not parse line as `other`(not `set`, `jump`, `detour`, `stop` or `return`) command(`{}`).\n\
 					     Remaining Line: `{args_iter}`.\n\
 						 Error: `{err}`", type_name::<YarnCommand>())))
 				}
@@ -184,4 +184,13 @@ fn test_valid_command() {
 	assert_eq!(parse_unwrap!("<<   stop>>"), stop_cmd!());
 	assert_eq!(parse_unwrap!("<<   stop   >>"), stop_cmd!());
 	assert_eq!(parse_unwrap!("<<   stop\t>>"), stop_cmd!());
+	
+	assert_eq!(
+		parse_unwrap!("<<detour Shop>>"),
+		Content::Command(YarnCommand { line_number: 0, variant: CommandVariant::Detour { node_name: own!("Shop") } }));
+	assert_eq!(
+		parse_unwrap!("<< return >>"),
+		Content::Command(YarnCommand { line_number: 0, variant: CommandVariant::Return }));
+	assert_matches!(parse!("<<return Shop>>"), Some(Err(_)));
+	assert_matches!(parse!("<<detour 5>>"), Some(Err(_)));
 }
@@ -0,0 +1,98 @@
+use crate::expressions::yarn_expr::YarnExpr;
+use crate::parsing::lexer::{lex, Token};
+use crate::parsing::macros::strip_start_then_trim;
+use crate::parsing::raw::branches::options::parse_if_condition;
+use crate::parsing::raw::speech::{self, Speech};
+use crate::parsing::raw::{Content, ParseRawYarn};
+use crate::LineNumber;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// One `=> line` of a line group, only one line of the group is shown each time the group is reached.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupLine {
+	pub speech: Speech,
+	/// The line can only be picked if this evaluates to `true`.
+	pub condition: Option<YarnExpr>,
+}
+
+/// The `<<if [condition]>>` is taken out, the rest of the line is parsed as a regular speech line.
+fn parse_line(line: &str, line_number: LineNumber) -> Result<GroupLine> {
+	let mut speech_text = String::new();
+	let mut condition = None;
+	let mut copied = 0;
+
+	for lexeme in lex(line) {
+		let lexeme = lexeme?;
+
+		let Token::Command(command) = lexeme.token
+			else { continue };
+
+		let mut command = command.trim();
+
+		if !strip_start_then_trim!(command, "if") {
+			return Err(anyhow!(
+				"Invalid command `<<{command}>>` in line group.\n\n\
+				 Help: The only command allowed in a `=> line` is its condition: `<<if [condition]>>`."));
+		}
+
+		if condition.is_some() {
+			return Err(anyhow!(
+				"More than one `<<if [condition]>>` in the same line.\n\n\
+				 Help: Combine the conditions with `and`/`or`: `<<if $a and $b>>`."));
+		}
+
+		condition = Some(parse_if_condition(command)?);
+		speech_text.push_str(&line[copied..lexeme.span.start]);
+		copied = lexeme.span.end;
+	}
+
+	speech_text.push_str(&line[copied..]);
+
+	let speech = speech::parse_line(&speech_text, line_number)?;
+	Ok(GroupLine { speech, condition })
+}
+
+impl ParseRawYarn for GroupLine {
+	fn parse_raw_yarn(line: &str, line_number: LineNumber) -> Option<Result<Content>> {
+		let mut line = line.trim();
+
+		if !strip_start_then_trim!(line, "=>") {
+			return None;
+		}
+
+		Some(parse_line(line, line_number)
+			.map(Content::GroupLine)
+			.map_err(|err| anyhow!(
+				"Could not parse line as `GroupLine`.\n\
+				 Error: `{err:?}`\n\
+				 Line: `{line}`")))
+	}
+}
+
+#[test]
+fn test_group_line() {
+	use crate::expressions::parse_yarn_expr;
+	use crate::parsing::raw::speech::Speaker;
+
+	let parse = |line: &str| GroupLine::parse_raw_yarn(line, 3);
+
+	let Some(Ok(Content::GroupLine(group_line))) = parse("=> Guard: Halt, {$name}! <<if $gold > 5>> #bark #line:g1")
+		else { panic!() };
+
+	assert_eq!(group_line.condition, Some(parse_yarn_expr("$gold > 5").unwrap()));
+	assert_eq!(group_line.speech.speaker, Some(Speaker::Literal("Guard".to_string())));
+	assert_eq!(group_line.speech.text.0, "Halt, {}!");
+	assert_eq!(group_line.speech.tags, ["bark"]);
+	assert_eq!(group_line.speech.line_id.as_deref(), Some("g1"));
+
+	let Some(Ok(Content::GroupLine(group_line))) = parse("=>   Move along.")
+		else { panic!() };
+
+	assert_eq!((group_line.condition, group_line.speech.text.0.as_str()), (None, "Move along."));
+
+	assert!(parse("Guard: Halt!").is_none());
+	assert!(matches!(parse("=> Halt! <<jump Start>>"), Some(Err(_))));
+	assert!(matches!(parse("=> Halt! <<if $a>> <<if $b>>"), Some(Err(_))));
+	assert!(matches!(parse("=> <<if $a>>"), Some(Err(_))));
+}
@@ -8,21 +8,26 @@ pub mod node_metadata;
 pub mod splitting;
 pub mod var_declaration;
 pub mod arg_parser;
+pub mod line_group;
 
 use crate::parsing::macros::{strip_start_then_trim, trim};
 use crate::parsing::raw::branches::if_statement::{BranchKind, Else_, EndIf_};
+use crate::parsing::raw::branches::once_statement::{EndOnce_, OnceKind, Once_};
 use crate::parsing::raw::branches::options::EndOptions;
 use crate::parsing::raw::node_metadata::NodeMetadata;
 use crate::parsing::raw::var_declaration::VarDeclaration;
 use crate::parsing::util;
+use crate::diagnostic::{into_diagnostics, Diagnostic, ErrorCode, Span};
 use crate::{Indent, LineNumber, UnparsedLine};
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use branches::if_statement::{ElseIf_, If_};
 use branches::options::OptionLine;
 use command::YarnCommand;
+use line_group::GroupLine;
 use node_metadata::parse_metadata;
 use speech::Speech;
 use splitting::split_into_unparsed_nodes;
+use std::path::Path;
 
 pub trait ParseRawYarn {
 	fn parse_raw_yarn(line: &str, line_number: LineNumber) -> Option<Result<Content>>;
@@ -34,16 +39,21 @@ pub enum Content {
 	Command(YarnCommand),
 	OptionLine(OptionLine),
 	EndOptions(EndOptions),
+	GroupLine(GroupLine),
 	If(If_),
 	ElseIf(ElseIf_),
 	Else(Else_),
 	EndIf(EndIf_),
+	Once(Once_),
+	EndOnce(EndOnce_),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RawLine {
 	pub indent: Indent,
 	pub content: Content,
+	pub span: Span,
+	pub text: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -60,17 +70,22 @@ impl RawLine {
 			Content::Command(command) => command.line_number,
 			Content::OptionLine(option_line) => option_line.line_number,
 			Content::EndOptions(end_options) => end_options.line_number,
+			Content::GroupLine(group_line) => group_line.speech.line_number,
 			Content::If(if_struct) => if_struct.line_number,
 			Content::ElseIf(else_if_struct) => else_if_struct.line_number,
 			Content::Else(else_struct) => else_struct.line_number,
 			Content::EndIf(end_if_struct) => end_if_struct.line_number,
+			Content::Once(once_struct) => once_struct.line_number,
+			Content::EndOnce(end_once_struct) => end_once_struct.line_number,
 		}
 	}
 }
 
-fn parse_line(line_number: LineNumber, indent: Indent, line: &impl AsRef<str>)
-	-> Result<RawLine> {
-	let line = line.as_ref();
+/// `line` is the trimmed contents of `source_line`.
+fn parse_line(file: &Path, source_line: &UnparsedLine, indent: Indent, line: &str) -> Result<RawLine> {
+	let line_number = source_line.line_number;
+	let indent_chars = source_line.text.chars().take_while(|ch| ch.is_whitespace()).count();
+	let span = source_line.span(file).with_columns(indent_chars..indent_chars + line.chars().count());
 
 	macro_rules! try_parse {
 	    ($parse_ty: ty) => {
@@ -78,15 +93,19 @@ fn parse_line(line_number: LineNumber, indent: Indent, line: &impl AsRef<str>)
 				Some(Ok(content)) => { 
 					return Ok(RawLine {
 						content, 
-						indent 
+						indent,
+						span,
+						text: source_line.text.clone(),
 					})
 				},
 				Some(Err(err)) => {
-					return Err(anyhow!(
-						"Could not parse line as `{}`\n\
-						 Line nº{line_number}: `{line}`\n\
-						 Error: {err}", std::any::type_name::<$parse_ty>())
-					);
+					let type_name = std::any::type_name::<$parse_ty>().rsplit("::").next().unwrap_or_default();
+					
+					return Err(Diagnostic::error(ErrorCode::UnparsableLine, format!("Could not parse line as `{type_name}`."))
+						.at_line(file, source_line)
+						.with_span(span)
+						.with_note(err.to_string())
+						.into());
 				},
 			    None => {}
 			}
@@ -96,46 +115,49 @@ fn parse_line(line_number: LineNumber, indent: Indent, line: &impl AsRef<str>)
 	try_parse!(Speech);
 	try_parse!(YarnCommand);
 	try_parse!(OptionLine);
+	try_parse!(GroupLine);
 	try_parse!(BranchKind);
+	try_parse!(OnceKind);
 
-	Err(anyhow!(
-		"Line could not be parsed as any YarnSyntax.\n\
-		 Line nº{line_number}: `{line}"
-	))
+	Err(Diagnostic::error(ErrorCode::UnknownSyntax, "Line could not be parsed as any YarnSyntax.")
+		.at_line(file, source_line)
+		.with_span(span)
+		.into())
 }
 
-pub fn parse_raw_nodes(mut source_lines: Vec<UnparsedLine>) -> Result<(Vec<RawNode>, Vec<VarDeclaration>)> {
-	source_lines
-		.retain_mut(|line| {
-			if let Some(comment_index) = line.text.find("//") {
-				line.text.truncate(comment_index);
-			}
-
-			!line.text.is_empty()
-		});
-
+/// Lines that could not be parsed are reported to `diagnostics` and skipped,
+/// so a single run reports every invalid line of the file.
+pub fn parse_raw_nodes(
+	file: &Path,
+	mut source_lines: Vec<UnparsedLine>,
+	diagnostics: &mut Vec<Diagnostic>,
+) -> (Vec<RawNode>, Vec<VarDeclaration>) {
 	let var_declarations: Vec<VarDeclaration> =
 		source_lines
 			.extract_if(|unparsed_line| {
 				let mut temp = unparsed_line.text.as_str().trim();
 				strip_start_then_trim!(temp, "<<") && temp.starts_with("declare")
 			})
-			.map(|unparsed_line|
-				VarDeclaration::try_parse(&unparsed_line)
-					.ok_or(anyhow!(
-						"Could not parse line as variable declaration(`<<declare $var_name (=) [default_value]>>`).\n\
-						 Line nº{}: `{}`", unparsed_line.line_number, unparsed_line.text)
-					)?
-			).try_collect()?;
-
-	let unparsed_nodes = split_into_unparsed_nodes(&source_lines)
-		.map_err(|err| anyhow!("Could not split file into nodes.\nError: {err}"))?;
+			.filter_map(|unparsed_line| {
+				let result =
+					VarDeclaration::try_parse(file, &unparsed_line)
+						.ok_or_else(|| Diagnostic::error(ErrorCode::InvalidVarDeclaration, "Could not parse line as variable declaration.")
+							.at_line(file, &unparsed_line)
+							.with_help("Variables are declared like: `<<declare $var_name = [default_value]>>`"))
+						.and_then(|result|
+							result.map_err(|err| Diagnostic::error(ErrorCode::InvalidVarDeclaration, "Could not parse variable declaration.")
+								.at_line(file, &unparsed_line)
+								.with_note(err.to_string())));
+				
+				result.map_err(|diagnostic| diagnostics.push(diagnostic)).ok()
+			}).collect();
+
+	let unparsed_nodes = split_into_unparsed_nodes(file, &source_lines, diagnostics);
 
 	let raw_nodes = unparsed_nodes
 		.into_iter()
-		.map(|unparsed_node| {
-			let metadata = parse_metadata(unparsed_node.outer_lines)
-				.map_err(|err| anyhow!("Could not parse node metadata.\nError: {err}"))?;
+		.filter_map(|unparsed_node| {
+			let metadata = parse_metadata(file, Some(unparsed_node.start), unparsed_node.outer_lines, diagnostics);
 
 			let lines: Vec<RawLine> = unparsed_node
 				.inner_lines
@@ -145,15 +167,18 @@ pub fn parse_raw_nodes(mut source_lines: Vec<UnparsedLine>) -> Result<(Vec<RawNo
 					let indent = util::indent_level(&text);
 					trim!(text);
 
-					if !text.is_empty() {
-						Some(parse_line(line.line_number, indent, &text))
-					} else {
-						None
+					if text.is_empty() {
+						return None;
 					}
-				}).try_collect()?;
+					
+					parse_line(file, line, indent, text)
+						.map_err(|err| diagnostics.extend(into_diagnostics(err)))
+						.ok()
+				}).collect();
 
-			Result::<_>::Ok(RawNode { metadata, lines })
-		}).try_collect()?;
+			// Lines are parsed even without metadata, to report their errors too.
+			metadata.map(|metadata| RawNode { metadata, lines })
+		}).collect();
 
-	Ok((raw_nodes, var_declarations))
+	(raw_nodes, var_declarations)
 }
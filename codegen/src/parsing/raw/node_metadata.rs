@@ -1,10 +1,15 @@
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use genco::prelude::{FormatInto, Rust};
-use genco::{quote_in, Tokens};
+use genco::Tokens;
 use crate::parsing::macros::strip_start_then_trim;
-use crate::UnparsedLine;
+use crate::diagnostic::{into_diagnostics, Diagnostic, ErrorCode};
+use crate::{LineNumber, UnparsedLine};
+use crate::expressions::parse_yarn_expr;
+use crate::expressions::yarn_expr::YarnExpr;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TrackingSetting {
 	Always,
 	Never,
@@ -12,19 +17,34 @@ pub enum TrackingSetting {
 
 impl FormatInto<Rust> for TrackingSetting {
 	fn format_into(self, tokens: &mut Tokens<Rust>) {
-		quote_in!(*tokens => $(match self {
+		tokens.append(match self {
 			TrackingSetting::Always => "TrackingSetting::Always",
 			TrackingSetting::Never => "TrackingSetting::Never",
-		}))
+		});
 	}
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// A `when:` header, nodes that have one share their title with the other nodes of their group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WhenCondition {
+	/// `when: always`
+	Always,
+	/// `when: once`, the node is only eligible until it runs once.
+	Once,
+	/// `when: [condition]`
+	Condition(YarnExpr),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NodeMetadata {
 	pub title: String,
+	/// The line of the `title:` declaration.
+	pub title_line_number: LineNumber,
 	pub tags: Vec<String>,
 	pub tracking: Option<TrackingSetting>,
 	pub customs: Vec<String>,
+	/// All of them must pass for the node to be picked when its group is started.
+	pub when: Vec<(LineNumber, WhenCondition)>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -32,10 +52,11 @@ enum MetaLine {
 	Title(String),
 	Tags(Vec<String>),
 	Tracking(TrackingSetting),
+	When(WhenCondition),
 	Custom(String),
 }
 
-fn parse_meta_line(source_line: &UnparsedLine) -> Result<MetaLine> {
+fn parse_meta_line(file: &Path, source_line: &UnparsedLine) -> Result<MetaLine> {
 	let mut text = source_line.text.trim();
 
 	if strip_start_then_trim!(text, "title" | "Title" | "TITLE")
@@ -44,11 +65,11 @@ fn parse_meta_line(source_line: &UnparsedLine) -> Result<MetaLine> {
 		if !text.is_empty() {
 			Ok(MetaLine::Title(text.to_string()))
 		} else {
-			Err(anyhow!(
-				"Missing title name in declaration.\n\
-				 A `title:` declaration was found but no title was provided.\n\
-				 At line nº{}, `{}`\n\n\
-				 Help: Provide a title.", source_line.line_number, source_line.text))
+			Err(Diagnostic::error(ErrorCode::MissingTitleName, "Missing title name in declaration.")
+				.at_line(file, source_line)
+				.with_note("A `title:` declaration was found but no title was provided.")
+				.with_help("Provide a title.")
+				.into())
 		}
 	} else if strip_start_then_trim!(text, "tags" | "Tags" | "TAGS")
 		&& strip_start_then_trim!(text, ':') {
@@ -65,42 +86,75 @@ fn parse_meta_line(source_line: &UnparsedLine) -> Result<MetaLine> {
 		} else if text.to_lowercase().as_str() == "never" {
 			Ok(MetaLine::Tracking(TrackingSetting::Never))
 		} else {
-			Err(anyhow!(
-				"Invalid tracking setting in Node metadata: {text}\n\n\
-				 Help: valid values are either `always` or `never`.\n\
-				 At line nº{}, `{}`", source_line.line_number, source_line.text))
+			Err(Diagnostic::error(ErrorCode::InvalidTracking, format!("Invalid tracking setting in Node metadata: {text}"))
+				.at_line(file, source_line)
+				.with_help("valid values are either `always` or `never`.")
+				.into())
+		}
+	} else if strip_start_then_trim!(text, "when" | "When" | "WHEN")
+		&& strip_start_then_trim!(text, ':') {
+		
+		match text.to_lowercase().as_str() {
+			"always" => Ok(MetaLine::When(WhenCondition::Always)),
+			"once" => Ok(MetaLine::When(WhenCondition::Once)),
+			"" => 
+				Err(Diagnostic::error(ErrorCode::InvalidWhen, "Missing condition in `when:` header.")
+					.at_line(file, source_line)
+					.with_help("valid values are `always`, `once` or a condition, like `when: $gold > 5`.")
+					.into()),
+			_ =>
+				parse_yarn_expr(text)
+					.map(|condition| MetaLine::When(WhenCondition::Condition(condition)))
+					.map_err(|err| 
+						Diagnostic::error(ErrorCode::InvalidWhen, format!("Invalid condition in `when:` header: {text}"))
+							.at_line(file, source_line)
+							.with_note(format!("Parsing error: {err}"))
+							.with_help("valid values are `always`, `once` or a condition, like `when: $gold > 5`.")
+							.into()),
 		}
 	} else {
 		Ok(MetaLine::Custom(text.to_string()))
 	}
 }
 
-pub fn parse_metadata<'a>(lines: impl IntoIterator<Item = &'a UnparsedLine>) -> Result<NodeMetadata> {
-	let meta_lines =
-		lines.into_iter()
-			 .map(|line| (line.line_number, parse_meta_line(line)));
-	
-	let mut title = None;
+/// `node_start` is the `---` line, used to locate errors about missing declarations.
+///
+/// Invalid lines are reported to `diagnostics` and skipped,
+/// returns `None` if the node has no valid title.
+pub fn parse_metadata<'a>(
+	file: &Path,
+	node_start: Option<&UnparsedLine>,
+	lines: impl IntoIterator<Item = &'a UnparsedLine>,
+	diagnostics: &mut Vec<Diagnostic>,
+) -> Option<NodeMetadata> {
+	let mut title: Option<(&UnparsedLine, String)> = None;
 	let mut tags = vec![];
-	let mut tracking = None;
+	let mut tracking: Option<(&UnparsedLine, TrackingSetting)> = None;
 	let mut customs = vec![];
+	let mut when = vec![];
 
-	for (line_number, result) in meta_lines {
-		let meta_line = result?;
+	for line in lines {
+		let meta_line = 
+			match parse_meta_line(file, line) {
+				Ok(meta_line) => meta_line,
+				Err(err) => {
+					diagnostics.extend(into_diagnostics(err));
+					continue;
+				},
+			};
 		
 		match meta_line {
 			MetaLine::Title(title_to_set) => {
-				match title {
-					Some((old_number, old_title)) => {
-						return Err(anyhow!(
-							"Found double `node title` declaration.\n\
-							 First: `{old_title}` at line nº{old_number}\n\
-							 Second: `{title_to_set}` at line nº{line_number}\n\n\
-							 Help: Delete one of the declarations.\n\
-							 Help: Nodes cannot have more than one title."))
+				match &title {
+					Some((old_line, old_title)) => {
+						diagnostics.push(Diagnostic::error(ErrorCode::DuplicateTitle, format!("Found double `node title` declaration: `{title_to_set}`."))
+							.at_line(file, line)
+							.with_note(format!("First declared as `{old_title}` at line nº{}.", old_line.line_number))
+							.with_help("Delete one of the declarations.")
+							.with_help("Nodes cannot have more than one title."));
 					},
 					None => {
-						title = Some((line_number, title_to_set))
+						title = Some((line, title_to_set))
 					},
 				}
 			},
@@ -108,60 +162,78 @@ pub fn parse_metadata<'a>(lines: impl IntoIterator<Item = &'a UnparsedLine>) ->
 				tags.extend(tags_to_add);
 			},
 			MetaLine::Tracking(tracking_to_set) => {
-				match tracking {
-					Some((old_number, old_tracking)) => {
-						return Err(anyhow!(
-							"Found double `tracking setting` declaration.\n\
-							 First: `{old_tracking:?}` at line nº{old_number}\n\
-							 Second: `{tracking:?}` at line nº{line_number}\n\n\
-							 Help: Delete one of the declarations.\n\
-							 Help: It doesn't make sense to set the same setting twice."))
+				match &tracking {
+					Some((old_line, old_tracking)) => {
+						diagnostics.push(Diagnostic::error(ErrorCode::DuplicateTracking, format!("Found double `tracking setting` declaration: `{tracking_to_set:?}`."))
+							.at_line(file, line)
+							.with_note(format!("First declared as `{old_tracking:?}` at line nº{}.", old_line.line_number))
+							.with_help("Delete one of the declarations.")
+							.with_help("It doesn't make sense to set the same setting twice."));
 					},
 					None => {
-						tracking = Some((line_number, tracking_to_set));
+						tracking = Some((line, tracking_to_set));
 					},
 				}
 			},
+			MetaLine::When(condition) => {
+				when.push((line.line_number, condition));
+			},
 			MetaLine::Custom(custom) => {
 				customs.push(custom);
 			},
 		}
 	}
 
-	let Some((title_number, title_name)) = title
+	let Some((title_line, title_name)) = title
 		else {
-			return Err(anyhow!(
-				"Missing `node title` declaration in node.\n\n\
-				 Help: To declare a title, write a line with the syntax: `title: MyNodeTitleHere`\n\
-				 Help: The title should be the first metadata line."))
+			let mut diagnostic = 
+				Diagnostic::error(ErrorCode::MissingTitle, "Missing `node title` declaration in node.")
+					.with_help("To declare a title, write a line with the syntax: `title: MyNodeTitleHere`")
+					.with_help("The title should be the first metadata line.");
+			
+			if let Some(node_start) = node_start {
+				diagnostic = diagnostic.at_line(file, node_start);
+			}
+			
+			diagnostics.push(diagnostic);
+			return None;
 		};
 	
+	// Points at the offending char, the title is always the last thing in its line.
+	let title_column = title_line.text.trim_end().chars().count() - title_name.chars().count();
+	
 	let first_char = title_name.chars().next().unwrap();
 	
 	if !first_char.is_ascii_alphabetic() && first_char != '_' {
-		return Err(anyhow!(
-			"Invalid first character in `node title`.\n\
-			 At line nº{title_number}, title: {title_name}\n\n\
-			 Help: The first character of a title needs to be a ASCII letter or a underscore('_').\n\
-			 Help: Titles cannot start with numbers or other special characters ('*', '/', '+', '-', ..)."))
+		diagnostics.push(Diagnostic::error(ErrorCode::InvalidTitle, format!("Invalid first character in `node title`: `{title_name}`."))
+			.at_line(file, title_line)
+			.with_span(title_line.span(file).with_columns(title_column..title_column + 1))
+			.with_help("The first character of a title needs to be a ASCII letter or a underscore('_').")
+			.with_help("Titles cannot start with numbers or other special characters ('*', '/', '+', '-', ..)."));
+		return None;
 	}
 	
-	if let Some(invalid_char) = 
+	if let Some((index, invalid_char)) = 
 		title_name
 			.chars()
-			.find(|ch| !ch.is_ascii_alphanumeric() && *ch != '_') {
-		return Err(anyhow!(
-				"Invalid character `{invalid_char}` in `node title`.\n\
-				 Full Name: `{title_name}`
-				 At line nº{title_number}\n\n\
-				 Help: Titles can only contain letters, digits and underscores('_')."))
+			.enumerate()
+			.find(|(_, ch)| !ch.is_ascii_alphanumeric() && *ch != '_') {
+		let column = title_column + index;
+		
+		diagnostics.push(Diagnostic::error(ErrorCode::InvalidTitle, format!("Invalid character `{invalid_char}` in `node title`: `{title_name}`."))
+			.at_line(file, title_line)
+			.with_span(title_line.span(file).with_columns(column..column + 1))
+			.with_help("Titles can only contain letters, digits and underscores('_')."));
+		return None;
 	}
 
-	Ok(NodeMetadata {
+	Some(NodeMetadata {
 		title: title_name,
+		title_line_number: title_line.line_number,
 		tags,
 		tracking: tracking.map(|(_, t)| t),
 		customs,
+		when,
 	})
 }
 
@@ -169,7 +241,7 @@ pub fn parse_metadata<'a>(lines: impl IntoIterator<Item = &'a UnparsedLine>) ->
 fn test_parsing() {
 	macro_rules! assert_eq_ok {
 	    ($str: literal, $pattern: expr) => {
-		    pretty_assertions::assert_eq!(parse_meta_line(&UnparsedLine { line_number: 0, text: $str.to_string() }).unwrap(), $pattern)
+		    pretty_assertions::assert_eq!(parse_meta_line(Path::new("test.yarn"), &UnparsedLine { line_number: 0, text: $str.to_string() }).unwrap(), $pattern)
 	    };
 	}
 	
@@ -190,6 +262,13 @@ fn test_parsing() {
 	assert_eq_ok!("tracking:ALWAYS", MetaLine::Tracking(TrackingSetting::Always));
 	assert_eq_ok!("tracking:NeVeR", MetaLine::Tracking(TrackingSetting::Never));
 	assert_eq_ok!("tracking:AlWaYS", MetaLine::Tracking(TrackingSetting::Always));
+	assert_eq_ok!("when: always", MetaLine::When(WhenCondition::Always));
+	assert_eq_ok!("when:Once", MetaLine::When(WhenCondition::Once));
+	assert_eq_ok!("when: $gold > 5", MetaLine::When(WhenCondition::Condition(parse_yarn_expr("$gold > 5").unwrap())));
+	
+	let parse_err = |text: &str| parse_meta_line(Path::new("test.yarn"), &UnparsedLine { line_number: 0, text: text.to_string() }).is_err();
+	assert!(parse_err("when:"));
+	assert!(parse_err("when: $gold >"));
 	
 	let valid_text = [
 		"title: Ch01_Awakening",
@@ -199,6 +278,8 @@ fn test_parsing() {
 		"custom_tag: any info here",
 		"\tanother custom_tag: other info   ",
 		"tracking: always",
+		"when: once",
+		"when: visited(\"Intro\")",
 	];
 	
 	let unparsed_lines =
@@ -209,15 +290,17 @@ fn test_parsing() {
 			.collect::<Vec<UnparsedLine>>();
 
 	let valid_meta = 
-		parse_metadata(&unparsed_lines)
+		parse_metadata(Path::new("test.yarn"), None, &unparsed_lines, &mut vec![])
 			.unwrap();
 	
 	assert_eq!(valid_meta, 
 		NodeMetadata {
 			title: own!("Ch01_Awakening"),
+			title_line_number: 0,
 			tags: own_vec!["more", "night", "day", "light", "less", "stuff"],
 			tracking: Some(TrackingSetting::Always),
 			customs: own_vec!["custom_tag: any info here", "another custom_tag: other info"],
+			when: vec![(7, WhenCondition::Once), (8, WhenCondition::Condition(parse_yarn_expr("visited(\"Intro\")").unwrap()))],
 		}
 	);
 }
\ No newline at end of file
@@ -2,6 +2,7 @@
 mod tests;
 
 use crate::expressions::yarn_expr::YarnExpr;
+use crate::parsing::lexer::{lex, Token};
 use crate::parsing::macros::{return_if_err, starts_with_any, strip_start_then_trim};
 use crate::parsing::raw::{Content, ParseRawYarn};
 use crate::{expressions, LineNumber};
@@ -11,12 +12,11 @@ use genco::lang::Rust;
 use genco::prelude::{quoted, FormatInto};
 use genco::{quote_in, Tokens};
 use houtamelo_utils::prelude::None;
-use std::iter::Peekable;
 use std::mem;
-use std::str::Chars;
 use trim_in_place::TrimInPlace;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Speaker {
 	Literal(String),
 	Variable(String),
@@ -33,7 +33,7 @@ impl FormatInto<Rust> for &Speaker {
 	}
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Speech {
 	pub line_number: LineNumber,
 	pub line_id: Option<String>,
@@ -42,232 +42,81 @@ pub struct Speech {
 	pub tags: Vec<String>,
 }
 
-enum CharState {
-	Std,
-	StringLiteral,
-	StringLiteralIgnoreNext,
-}
-
-enum State {
-	Lit {
-		ignore_next: bool,
-	},
-	Arg {
-		char_state: CharState,
-		previous_char: char,
-		nesting: Vec<char>,
-		sum: String,
-	},
-}
-
-fn parse_line(chars: &mut Peekable<Chars>, line_number: LineNumber) -> Result<Speech> {
-	let mut state = State::Lit { ignore_next: false };
+pub(crate) fn parse_line(line: &str, line_number: LineNumber) -> Result<Speech> {
 	let mut speaker = None;
 	let mut literal = String::new();
 	let mut args: Vec<String> = vec![];
 	let mut metadata = None;
 
-	while let Some(next) = chars.next() {
-		match &mut state {
-			State::Lit { ignore_next: ignore_next @ true } => {
-				*ignore_next = false;
-				literal.push(next);
-			}
-			State::Lit { ignore_next: ignore_next @ false } => {
-				match next {
-					'\\' => {
-						*ignore_next = true;
-					}
-					'{' => {
-						state = State::Arg {
-							char_state: CharState::Std,
-							previous_char: next,
-							nesting: vec![],
-							sum: String::new(),
-						};
-
-						literal.push_str("{}");
-					}
-					'}' => {
-						return Err(anyhow!(
-							"Unexpected closing delimiter `}}` when parsing literal.\n\
-							 Built so far: \n\
-							 \tLiteral: `{literal}`\n\
-							 \tArguments: `{args:?}`\n\n\
-							 Help: The closing delimiter `}}` does not match any opening delimiter `{{`.\n\
-							 Help: If you want to use '{{', '}}' inside a string literal, escape it with a backslash (`\\`)."));
-					}
-					'#' => {
-						let built_metadata =
-							std::iter::once('#')
-								.chain(chars.by_ref())
-								.collect::<String>();
-
-						if !built_metadata.is_empty() {
-							metadata = Some(built_metadata)
-						}
-
-						break;
-					}
-					':' => {
-						if speaker.is_none()
-							&& !literal.is_empty()
-							&& literal.trim().chars().none(char::is_whitespace)
-							&& args.is_empty() {
-							let mut speaker_str = mem::take(&mut literal);
-							speaker_str.trim_in_place();
-							speaker = Some(Speaker::Literal(speaker_str));
-						} else if speaker.is_none()
-							&& literal.trim() == "{}"
-							&& args.len() == 1
-						{
-							literal.clear();
-
-							let unparsed_speaker =
-								args.remove(0);
-							let expr =
-								parse_yarn_expr(&unparsed_speaker)
-									.map_err(|err| anyhow!(
-										"Could not parse `speaker variable` as `YarnExpr`.\n\
-										 Error: {err:?}\n\
-										 Unparsed: `{unparsed_speaker}`\n\
-										 Built so far: \n\
-										 \tLiteral: `{literal}`\n\
-										 \tArguments: `{args:?}`\n")
-									)?;
-
-							let YarnExpr::GetVar(speaker_var_name) = expr
-							else {
-								return Err(anyhow!(
-										"Invalid `speaker variable` argument.\n\
-										 Expected expression of type `YarnExpr::VarGet(var_name)`,\
-										 Got: {expr:?}\n\
-										 Unparsed: `{unparsed_speaker}`\n\
-										 Built so far: \n\
-										 \tLiteral: `{literal}`\n\
-										 \tArguments: `{args:?}`\n\
-										 \n\
-										 Help: the speaker argument can only be a string literal(`John`) \
-										 or a variable name(`{{$variable_name}}`)."));
-							};
+	for lexeme in lex(line) {
+		match lexeme?.token {
+			Token::Text(text) => {
+				for next in text.chars() {
+					if next != ':' {
+						literal.push(next);
+					} else if speaker.is_none()
+						&& !literal.is_empty()
+						&& literal.trim().chars().none(char::is_whitespace)
+						&& args.is_empty() {
+						let mut speaker_str = mem::take(&mut literal);
+						speaker_str.trim_in_place();
+						speaker = Some(Speaker::Literal(speaker_str));
+					} else if speaker.is_none()
+						&& literal.trim() == "{}"
+						&& args.len() == 1
+					{
+						literal.clear();
+
+						let unparsed_speaker =
+							args.remove(0);
+						let expr =
+							parse_yarn_expr(&unparsed_speaker)
+								.map_err(|err| anyhow!(
+									"Could not parse `speaker variable` as `YarnExpr`.\n\
+									 Error: {err:?}\n\
+									 Unparsed: `{unparsed_speaker}`\n\
+									 Built so far: \n\
+									 \tLiteral: `{literal}`\n\
+									 \tArguments: `{args:?}`\n")
+								)?;
 
-							speaker = Some(Speaker::Variable(speaker_var_name));
-						} else {
-							literal.push(next);
-						}
-					}
-					other => {
-						literal.push(other);
-					}
-				}
-			}
-			State::Arg {
-				char_state: char_state @ CharState::Std, previous_char,
-				nesting, sum
-			} => {
-				match next {
-					'"' => {
-						*char_state = CharState::StringLiteral;
-						*previous_char = '"';
-						sum.push('"');
-					}
-					nest @ ('(' | '{' | '[') => {
-						*previous_char = nest;
-						nesting.push(nest);
-						sum.push(nest);
-					}
-					un_nest @ (')' | '}' | ']') =>
-						if let Some(nest) = nesting.pop() {
-							if matches!((nest, un_nest), ('(', ')') | ('{', '}') | ('[', ']')) {
-								*previous_char = un_nest;
-								sum.push(un_nest);
-							} else {
-								return Err(anyhow!(
-									"Invalid closing delimiter `{un_nest}` when parsing argument.\n\
-									 Argument: `{sum}`\n\
-									 Nesting: `{nesting:?}`\n\
+						let YarnExpr::GetVar(speaker_var_name) = expr
+						else {
+							return Err(anyhow!(
+									"Invalid `speaker variable` argument.\n\
+									 Expected expression of type `YarnExpr::VarGet(var_name)`,\
+									 Got: {expr:?}\n\
+									 Unparsed: `{unparsed_speaker}`\n\
 									 Built so far: \n\
 									 \tLiteral: `{literal}`\n\
 									 \tArguments: `{args:?}`\n\
 									 \n\
-									 Help: the closing delimiter `{un_nest}` does not match the most-recent opening delimiter `{nest}`.\n\
-									 Help: if you want to use '{{', '}}' inside a string literal, escape it with a backslash (`\\`)."));
-							}
-						} else if un_nest == '}' {
-							args.push(mem::take(sum));
-							state = State::Lit { ignore_next: false };
-						} else {
-							return Err(anyhow!(
-								"Unexpected closing delimiter `{un_nest}` when parsing argument.\n\
-								 Argument: `{sum}`\n\
-								 Nesting: `{nesting:?}`\n\
-								 Built so far: \n\
-								 \tLiteral: `{literal}`\n\
-								 \tArguments: `{args:?}`\n\
-								 \n\
-								 Help: if you want to use '{{', '}}' inside a string literal, escape it with a backslash (`\\`)."));
-						},
-					other => {
-						*previous_char = other;
-						sum.push(other);
+									 Help: the speaker argument can only be a string literal(`John`) \
+									 or a variable name(`{{$variable_name}}`)."));
+						};
+
+						speaker = Some(Speaker::Variable(speaker_var_name));
+					} else {
+						literal.push(next);
 					}
 				}
 			}
-			State::Arg {
-				char_state: char_state @ CharState::StringLiteral,
-				previous_char, sum, nesting: _nesting
-			} => {
-				match next {
-					'"' => {
-						*char_state = CharState::Std;
-						*previous_char = '"';
-						sum.push('"');
-					}
-					'\\' => {
-						*char_state = CharState::StringLiteralIgnoreNext;
-					}
-					other => {
-						*previous_char = other;
-						sum.push(other);
-					}
-				}
+			Token::Escaped(escaped) => {
+				literal.push(escaped);
 			}
-			State::Arg {
-				char_state: char_state @ CharState::StringLiteralIgnoreNext,
-				previous_char, sum, nesting: _nesting
-			} => {
-				*char_state = CharState::StringLiteral;
-				*previous_char = next;
-				sum.push(next);
+			Token::Interpolation(arg) => {
+				literal.push_str("{}");
+				args.push(arg.to_string());
 			}
-		}
-	}
-
-	match state {
-		State::Lit { ignore_next } => {
-			if ignore_next {
-				return Err(anyhow!(
-					"Speech ended with an escape character (`\\`).\n\
-					 Built so far: \n\
-					 \tLiteral: `{literal}`\n\
-					 \tArguments: `{args:?}`\n\n\
-					 Help: The escape character(`\\`) means nothing if there's no character after it."));
+			Token::Command(command) => {
+				literal.push_str("<<");
+				literal.push_str(command);
+				literal.push_str(">>");
 			}
-		}
-		State::Arg {
-			char_state: _char_state, previous_char: _previous_char,
-			nesting, sum
-		} => {
-			return Err(anyhow!(
-				"Speech ended with an open delimiter (building an argument).\n\
-				 Argument: `{sum}`\n\
-				 Nesting: `{nesting:?}`\n\
-				 Built so far: \n\
-				 \tLiteral: `{literal}`\n\
-				 \tArguments: `{args:?}`\n\n\
-				 Help: The argument `{sum}` is not closed.\n\
-				 Help: For every opening delimiter(`(`, `{{`, `[`), there must be a matching closing delimiter(`)`, `}}`, `]`).\n\
-				 Help: If you want to use '{{', '}}' inside a string literal, escape it with a backslash (`\\`)."));
+			Token::Tags(tags) => {
+				metadata = Some(tags.to_string());
+			}
+			Token::Comment(_) => {}
 		}
 	}
 
@@ -367,17 +216,15 @@ impl ParseRawYarn for Speech {
 	fn parse_raw_yarn(line: &str, line_number: LineNumber) -> Option<Result<Content>> {
 		let line = line.trim();
 
-		if starts_with_any!(line, "<<" | "->" | "<-") {
+		if starts_with_any!(line, "<<" | "->" | "<-" | "=>") {
 			return None;
 		}
 
-		let mut chars = line.chars().peekable();
-
-		let speech = return_if_err!(parse_line(&mut chars, line_number)
+		let speech = return_if_err!(parse_line(line, line_number)
 			.map_err(|err|anyhow!(
 				"Could not parse line as `Speech`.\n\
-				 Remaining line: `{}`\n\
-				 Error: `{err:?}`", chars.collect::<String>())));
+				 Line: `{line}`\n\
+				 Error: `{err:?}`")));
 
 		Some(Ok(Content::Speech(speech)))
 	}
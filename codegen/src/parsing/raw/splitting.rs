@@ -1,15 +1,18 @@
-use crate::{LineNumber, UnparsedLine};
-use anyhow::{Result, anyhow};
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::UnparsedLine;
+use std::path::Path;
 use State::{Outside, Inside};
 
 pub struct UnparsedNode<'a> {
+	/// The `---` line.
+	pub start: &'a UnparsedLine,
 	pub outer_lines: Vec<&'a UnparsedLine>, // metadata
 	pub inner_lines: Vec<&'a UnparsedLine>,
 }
 
 enum NodeDelimiter<'a> {
-	NodeStart(LineNumber),
-	NodeEnd(LineNumber),
+	NodeStart(&'a UnparsedLine),
+	NodeEnd(&'a UnparsedLine),
 	Not(&'a UnparsedLine),
 }
 
@@ -17,8 +20,8 @@ fn as_delimiter(line: &UnparsedLine) -> NodeDelimiter {
 	let trimmed_line = line.text.trim();
 	
 	return match trimmed_line {
-		"---" => NodeDelimiter::NodeStart(line.line_number),
-		"===" => NodeDelimiter::NodeEnd(line.line_number),
+		"---" => NodeDelimiter::NodeStart(line),
+		"===" => NodeDelimiter::NodeEnd(line),
 		_ => NodeDelimiter::Not(line),
 	};
 }
@@ -28,12 +31,22 @@ enum State<'a> {
 		lines: Vec<&'a UnparsedLine>,
 	},
 	Inside {
+		start: &'a UnparsedLine,
 		outer_lines: Vec<&'a UnparsedLine>,
 		inner_lines: Vec<&'a UnparsedLine>,
 	},
 }
 
-pub fn split_into_unparsed_nodes(lines: &[UnparsedLine]) -> Result<Vec<UnparsedNode>> {
+const DELIMITERS_HELP: &str =
+	"A node is started by writing a line with three hyphens (`---`), \
+	 and ended by writing a line with three equals signs (`===`).";
+
+/// Misplaced delimiters are reported to `diagnostics` and skipped.
+pub fn split_into_unparsed_nodes<'a>(
+	file: &Path,
+	lines: &'a [UnparsedLine],
+	diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<UnparsedNode<'a>> {
 	let mut nodes = Vec::new();
 	let mut state = Outside { lines: vec![] };
 	
@@ -41,38 +54,35 @@ pub fn split_into_unparsed_nodes(lines: &[UnparsedLine]) -> Result<Vec<UnparsedN
 		match &mut state {
 			Outside { lines } =>
 				match maybe_delimiter {
-					NodeDelimiter::NodeStart(_) => {
+					NodeDelimiter::NodeStart(start) => {
 						state = Inside { 
+							start,
 							outer_lines: std::mem::take(lines), 
 							inner_lines: vec![],
 						};
 					},
-					NodeDelimiter::NodeEnd(line_number) => {
-						return Err(anyhow!(
-							"Node end delimiter found before start delimiter.\n\
-							 At line nº{line_number}\n\n\
-							 Help: Maybe you meant to start a node? \n\
-							 Help: A node is started by writing a line with three hyphens (`---`), \
-							 and ended by writing a line with three equals signs (`===`)."
-						));
+					NodeDelimiter::NodeEnd(end) => {
+						diagnostics.push(Diagnostic::error(ErrorCode::MisplacedEndDelimiter, "Node end delimiter found before start delimiter.")
+							.at_line(file, end)
+							.with_help("Maybe you meant to start a node?")
+							.with_help(DELIMITERS_HELP));
 					},
 					NodeDelimiter::Not(unparsed_line) => {
 						lines.push(unparsed_line);
 					},
 				}
-			Inside { outer_lines, inner_lines } =>
+			Inside { start, outer_lines, inner_lines } =>
 				match maybe_delimiter {
-					NodeDelimiter::NodeStart(line_number) => {
-						return Err(anyhow!(
-							"Node start delimiter found before end delimiter.\n\
-							 At line nº{line_number}\n\n\
-							 Help: Maybe you meant to end a node? \n\
-							 Help: A node is started by writing a line with three hyphens (`---`), \
-							 and ended by writing a line with three equals signs (`===`)."
-						));
+					NodeDelimiter::NodeStart(second_start) => {
+						diagnostics.push(Diagnostic::error(ErrorCode::MisplacedStartDelimiter, "Node start delimiter found before end delimiter.")
+							.at_line(file, second_start)
+							.with_note(format!("The current node started at line nº{}.", start.line_number))
+							.with_help("Maybe you meant to end a node?")
+							.with_help(DELIMITERS_HELP));
 					},
 					NodeDelimiter::NodeEnd(_) => {
 						nodes.push(UnparsedNode {
+							start,
 							outer_lines: std::mem::take(outer_lines),
 							inner_lines: std::mem::take(inner_lines),
 						});
@@ -89,27 +99,28 @@ pub fn split_into_unparsed_nodes(lines: &[UnparsedLine]) -> Result<Vec<UnparsedN
 	} 
 	
 	match state {
-		Outside { lines } =>
-			if lines.is_empty() {
-				Ok(nodes)
-			} else {
-				Err(anyhow!(
-					"File ended with orphan lines. (Orphan lines are not allowed)\n\
-					 Lines: \n\t{}\n\
-					 Help: If you want to ignore a line, you can comment it out by starting it with `//`.\n\
-					 Help: Lines above a node are considered metadata.\n\
-					 Lines bellow a node are only allowed if there's another node bellow them.\n\
-					 ", lines.into_iter().map(|u| format!("nº{}: `{}`", u.line_number, u.text)).collect::<Vec<_>>().join("\n\t")
-				))
-			},
-		Inside { .. } =>
-			Err(anyhow!(
-				"File ended without ending node.\n\
-				 Help: The last line of a file needs to be made of three `equals signs` (`===`) \n\
-				 Help: A node is started by writing a line with three `hyphens` (`---`), \
-				 and ended by writing a line with three `equals signs` (`===`)."
-			)),
+		Outside { lines } => {
+			if let Some(first) = lines.first() {
+				diagnostics.push(Diagnostic::error(ErrorCode::OrphanLines, "File ended with orphan lines. (Orphan lines are not allowed)")
+					.at_line(file, first)
+					.with_note(format!("Orphan lines: {}", lines.iter().map(|u| format!("nº{}", u.line_number)).collect::<Vec<_>>().join(", ")))
+					.with_help("If you want to ignore a line, you can comment it out by starting it with `//`.")
+					.with_help("Lines above a node are considered metadata. \
+					            Lines bellow a node are only allowed if there's another node bellow them."));
+			}
+		},
+		Inside { start, outer_lines, inner_lines } => {
+			diagnostics.push(Diagnostic::error(ErrorCode::UnterminatedNode, "File ended without ending node.")
+				.at_line(file, start)
+				.with_help("The last line of a file needs to be made of three `equals signs` (`===`).")
+				.with_help(DELIMITERS_HELP));
+			
+			// Still parsed, so errors inside the unfinished node get reported too.
+			nodes.push(UnparsedNode { start, outer_lines, inner_lines });
+		},
 	}
+	
+	nodes
 }
 
 /*
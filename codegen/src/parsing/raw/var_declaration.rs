@@ -1,13 +1,17 @@
-use std::iter::Peekable;
-use std::str::Chars;
+use std::path::{Path, PathBuf};
 use crate::expressions::yarn_expr::YarnExpr;
 use crate::{LineNumber, UnparsedLine};
 use anyhow::{anyhow, Result};
 use crate::expressions::declaration_ty::DeclarationTy;
 use crate::expressions::parse_yarn_expr;
-use crate::parsing::macros::{strip_end_then_trim, strip_start_then_trim, trim};
+use crate::parsing::lexer::{lex, scan_literals, Lexeme, Token};
+use crate::parsing::macros::strip_start_then_trim;
+use serde::{Deserialize, Serialize};
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct VarDeclaration {
+	/// The file this variable was declared in.
+	pub source_file: PathBuf,
 	pub line_number: LineNumber,
 	pub var_name: String,
 	pub default_value: YarnExpr,
@@ -24,289 +28,35 @@ impl VarDeclaration {
 	}
 }
 
-struct ArgBuilder<'a> {
-	chars: Peekable<Chars<'a>>,
-	allow_yarn_set_syntax: bool,
+/// Byte index of the ` as [type]` cast at the end of `value`, ignoring `as` inside string literals.
+fn cast_start(value: &str) -> Option<usize> {
+	scan_literals(value)
+		.into_iter()
+		.filter(|(index, _, in_literal)| {
+			!in_literal
+				&& value[..*index].ends_with(char::is_whitespace)
+				&& value[*index..].strip_prefix("as").is_some_and(|after| after.starts_with(char::is_whitespace))
+		})
+		.map(|(index, ..)| index)
+		.last()
 }
 
-enum CharState {
-	Std,
-	StringLiteral,
-	StringLiteralIgnoreNext,
-}
-
-enum ArgState {
-	Empty,
-	Building {
-		char_state: CharState,
-		previous_char: char,
-		nesting: Vec<char>,
-		sum: String,
-	},
-}
-
-type EndedWithDoubleAngleBracket = bool;
+/// `declaration` is the contents of the command, after `declare`.
+fn parse_declaration(declaration: &str) -> Result<(String, YarnExpr, Option<DeclarationTy>)> {
+	let name_end =
+		declaration
+			.find(|ch: char| ch.is_whitespace() || ch == '=')
+			.unwrap_or(declaration.len());
 
-impl<'a> Iterator for ArgBuilder<'a> {
-	type Item = Result<(String, EndedWithDoubleAngleBracket)>;
-
-	fn next(&mut self) -> Option<Self::Item> {
-		let chars = &mut self.chars;
-		let mut state = ArgState::Empty;
-		let mut ended_with_double_angle_bracket = false;
-		while let Some(next) = chars.next() {
-			match &mut state {
-				ArgState::Empty => {
-					match (next, self.allow_yarn_set_syntax) {
-						('t', true) =>
-							if chars.next_if_eq(&'o')
-							        .is_some_and(|_| matches!(chars.peek(), Some(' ' | '\t'))) {
-								chars.next();
-								self.allow_yarn_set_syntax = false;
-							} else {
-								state = ArgState::Building {
-									char_state: CharState::Std,
-									previous_char: 't',
-									nesting: vec![],
-									sum: String::from('t'),
-								};
-							},
-						('=', true) => {
-							self.allow_yarn_set_syntax = false;
-						},
-						(' ' | '\t' | ',', _) => {}, // ignore whitespace when empty
-						('"', _) => {
-							state = ArgState::Building {
-								char_state: CharState::StringLiteral,
-								previous_char: '"',
-								nesting: vec![],
-								sum: String::from('"'),
-							};
-						},
-						(nest @ ('(' | '{' | '['), _) => {
-							state = ArgState::Building {
-								char_state: CharState::Std,
-								previous_char: nest,
-								nesting: vec![nest],
-								sum: String::from(nest),
-							};
-						},
-						(unexpected_nest @ (')' | '}' | ']'), _) => {
-							return Some(Err(anyhow!(
-								"Unexpected closing delimiter `{unexpected_nest}`\n\n\
-								 Help: Closing delimiters must be preceded by a matching opening delimiter."
-							)));
-						},
-						('>', _) => {
-							if chars.next_if_eq(&'>').is_some() {
-								let remaining = chars.by_ref().collect::<String>();
-								let remaining_str = remaining.trim();
-
-								return if remaining_str.is_empty() {
-									None
-								} else {
-									Some(Err(anyhow!(
-										"Unexpected characters after closing command(`>>`).\n\
-										 Remaining: `{remaining_str}`\n\n\
-										 Help: Extra characters are not allowed after `>>`.\n\
-										 Help: Command statements cannot have metadata (which is started with `#`)"
-									)))
-								};
-							} else {
-								state = ArgState::Building {
-									char_state: CharState::Std,
-									previous_char: '>',
-									nesting: vec![],
-									sum: String::from('>'),
-								};
-							}
-						},
-						(other, _) => {
-							state = ArgState::Building {
-								char_state: CharState::Std,
-								previous_char: other,
-								nesting: vec![],
-								sum: String::from(other),
-							};
-						},
-					}
-				},
-				ArgState::Building {
-					char_state: char_state @ CharState::Std,
-					previous_char, nesting, sum
-				} => {
-					match next {
-						'"' => {
-							*char_state = CharState::StringLiteral;
-							*previous_char = '"';
-							sum.push('"');
-						},
-						nest @ ('(' | '{' | '[') => {
-							*previous_char = nest;
-							nesting.push(nest);
-							sum.push(nest);
-						},
-						un_nest @ (')' | '}' | ']') => {
-							if let Some(nest) = nesting.pop() {
-								if matches!((nest, un_nest), ('(', ')') | ('{', '}') | ('[', ']')) {
-									*previous_char = un_nest;
-									sum.push(un_nest);
-								} else {
-									return Some(Err(anyhow!(
-										"Invalid closing delimiter `{un_nest}` when parsing argument.\n\
-										 Argument: `{sum}`\n\
-										 Nesting: `{nesting:?}`\n\
-										 Built so far: `{sum}`\n\
-										 Help: the closing delimiter `{un_nest}` does not match the most-recent opening delimiter `{nest}`.\n\
-										 Help: if you want to use '{{', '}}' inside a string literal, escape it with a backslash (`\\`)."
-									)));
-								}
-							} else {
-								return Some(Err(anyhow!(
-									"Unexpected closing delimiter `{un_nest}` when parsing argument.\n\
-									 Argument: `{sum}`\n\
-									 Nesting: `{nesting:?}`\n\
-									 Built so far: {sum}\n\
-									 Help: if you want to use '{{', '}}' inside a string literal, escape it with a backslash (`\\`)."
-								)));
-							}
-						},
-						'>' => {
-							if !nesting.is_empty()
-								|| chars.next_if_eq(&'>').is_none() {
-								*previous_char = '>';
-								sum.push('>');
-							}
+	let (unparsed_name, remaining) = declaration.split_at(name_end);
 
-							let remaining = chars.by_ref().collect::<String>();
-							let remaining_str = remaining.trim();
-
-							if remaining_str.is_empty() {
-								ended_with_double_angle_bracket = true;
-								break;
-							} else {
-								return Some(Err(anyhow!(
-									"Unexpected characters after closing command(`>>`).\n\
-									 Remaining: `{remaining_str}`\n\
-									 Built so far: `{sum}`\n\n\
-									 Help: Extra characters are not allowed after `>>`.\n\
-									 Help: Branch statements cannot have metadata (which is started with `#`)"
-								)));
-							}
-						},
-						' ' | '\t' => {
-							if !nesting.is_empty() { //whitespace is ignored inside nesting
-								continue;
-							}
-
-							// skip whitespace
-							while chars.next_if(|ch| matches!(*ch, ' ' | '\t')).is_some() {}
-
-							if !matches!(*previous_char, '+' | '-' | '/' | '*' | '%' | '>' | '<' | '!' | '=')
-								|| chars.peek().is_none() {
-								break;
-							}
-						},
-						',' => {
-							if nesting.is_empty() {
-								break;
-							}
-						},
-						other => {
-							*previous_char = other;
-							sum.push(other);
-						},
-					}
-				},
-				ArgState::Building {
-					char_state: char_state @ CharState::StringLiteral,
-					previous_char, sum, nesting: _nesting
-				} => {
-					match next {
-						'"' => {
-							*char_state = CharState::Std;
-							*previous_char = '"';
-							sum.push('"');
-						},
-						'\\' => {
-							*char_state = CharState::StringLiteralIgnoreNext;
-						},
-						other => {
-							*previous_char = other;
-							sum.push(other);
-						},
-					}
-				},
-				ArgState::Building {
-					char_state: char_state @ CharState::StringLiteralIgnoreNext,
-					previous_char, sum, nesting: _nesting
-				} => {
-					*char_state = CharState::StringLiteral;
-					*previous_char = next;
-					sum.push(next);
-				},
-			}
-		}
-
-		match state {
-			ArgState::Building { char_state, previous_char: _previous_char, nesting, sum } => {
-				if !nesting.is_empty() {
-					return Some(Err(anyhow!(
-						"Argument ended without closing previous nesting.\n\
-						 Argument: `{sum:?}`\n\
-						 Nesting: `{nesting:?}`\n\n\
-						 Help: The argument `{sum}` is not closed.\n\
-				         Help: For every opening delimiter(`(`, `{{`, `[`), there must be a matching closing delimiter(`)`, `}}`, `]`).\n\
-				         Help: If you want to use `{{` or `}}` inside a string literal, escape it with a backslash (`\\`)."
-					)));
-				}
-
-				if sum.is_empty() {
-					return None;
-				}
-
-				match char_state {
-					CharState::Std =>
-						Some(Ok((sum, ended_with_double_angle_bracket))),
-					| CharState::StringLiteral
-					| CharState::StringLiteralIgnoreNext =>
-						Some(Err(anyhow!(
-							"Argument ended without closing string literal\n\
-							 Argument: `{sum}`\n\
-							 Nesting: `{nesting:?}`\n\n\
-							 Help: every string literal(started with `\"`) must end with another `\"`."
-						))),
-				}
-			}
-			ArgState::Empty => None,
-		}
-	}
-}
-
-fn parse_args(args_iter: &mut ArgBuilder) -> Result<(String, YarnExpr, Option<DeclarationTy>)> {
 	let var_name = {
-		let unparsed_string =
-			args_iter.next()
-				.unwrap_or_else(|| Err(anyhow!(
-					"Could not build `variable name` argument.\n\
-				     Error: `ArgBuilder` returned None.")))
-				.and_then(|(var_name, ended)|
-					if !ended {
-						Ok(var_name)
-					} else {
-						Err(anyhow!(
-							"Ended with `>>` after only one argument.\n\
-							 Expected two: `variable name` then `default value`.\n\
-							 Variable name: {var_name}"))
-					}
-				)?;
-
 		let expr =
-			parse_yarn_expr(unparsed_string.as_str())
+			parse_yarn_expr(unparsed_name)
 				.map_err(|err| anyhow!(
 					"Could not parse `variable name` argument as `YarnExpr`.\n\
 					 Error: {err}\n\
-					 Variable name: {unparsed_string}")
+					 Variable name: {unparsed_name}")
 				)?;
 
 		let YarnExpr::GetVar(name) = expr 
@@ -320,72 +70,82 @@ fn parse_args(args_iter: &mut ArgBuilder) -> Result<(String, YarnExpr, Option<De
 		name
 	};
 
-	let (value_expr, already_ended) = {
-		args_iter.allow_yarn_set_syntax = true;
+	let mut remaining = remaining.trim();
+	let _ = strip_start_then_trim!(remaining, "=") || strip_start_then_trim!(remaining, "to ");
 
-		let (unparsed_string, ended) =
-			args_iter
-				.next()
-				.unwrap_or_else(|| Err(anyhow!(
-					"Could not build `variable value` argument.\n\
-					 Error: `ArgBuilder` returned None.
-		             Variable name: {var_name:?}"))
-				)?;
+	let (unparsed_value, cast_ty) =
+		match cast_start(remaining) {
+			Some(start) => {
+				let unparsed_ty = remaining[start + 2..].trim();
 
-		let expr =
-			parse_yarn_expr(&unparsed_string)
-				.map_err(|err| anyhow!(
-					"Could not parse `variable value` argument as `YarnExpr`.\n\
-					 Error: `{err}`\n\
-					 Variable name: `{var_name:?}`")
-				)?;
+				let Some(cast_ty) = DeclarationTy::from_str(unparsed_ty)
+					else {
+						return Err(anyhow!(
+							"Invalid cast type: `{unparsed_ty}`.\n\
+							 Expected one of(case-insensitive): `string`, `number`, `bool`,\
+							  any rust int (`i8`, `u32`, ..) or a float (`f32`, `f64`)"));
+					};
 
-		args_iter.allow_yarn_set_syntax = false;
+				(remaining[..start].trim(), Some(cast_ty))
+			},
+			None => (remaining, None),
+		};
 
-		(expr, ended)
-	};
-	
-	if already_ended {
-		return Ok((var_name, value_expr, None));
-	}
-	
-	let remaining = args_iter.chars.by_ref().collect::<String>();
-	let mut remaining_str = remaining.as_str();
-	trim!(remaining_str);
-	
-	if !strip_end_then_trim!(remaining_str, ">>")
-		|| !strip_start_then_trim!(remaining_str, "as"){
+	if unparsed_value.is_empty() {
 		return Err(anyhow!(
-			"Invalid argument after `variable value`. Expected nothing or `as [type]`.\n\
-			 Instead got: `{remaining_str}`"));
-	}
-	
-	if let Some(cast_ty) = DeclarationTy::from_str(remaining_str) {
-		Ok((var_name, value_expr, Some(cast_ty)))
-	} else {
-		Err(anyhow!(
-			"Invalid cast type: `{remaining_str}`.\n\
-			 Expected one of(case-insensitive): `string`, `number`, `bool`,\
-			  any rust int (`i8`, `u32`, ..) or a float (`f32`, `f64`)"))
+			"Missing `variable value` argument.\n\
+			 Variable name: `{var_name}`\n\n\
+			 Help: Expected two arguments: `variable name` then `default value`."));
 	}
+
+	let value_expr =
+		parse_yarn_expr(unparsed_value)
+			.map_err(|err| anyhow!(
+				"Could not parse `variable value` argument as `YarnExpr`.\n\
+				 Error: `{err}`\n\
+				 Variable name: `{var_name:?}`")
+			)?;
+
+	Ok((var_name, value_expr, cast_ty))
 }
 
 impl VarDeclaration {
-	pub fn try_parse(unparsed_line: &UnparsedLine) -> Option<Result<VarDeclaration>> {
-		let mut line = unparsed_line.text.trim();
+	pub fn try_parse(file: &Path, unparsed_line: &UnparsedLine) -> Option<Result<VarDeclaration>> {
+		let line = unparsed_line.text.trim();
 
-		if !strip_start_then_trim!(line, "<<")
-			&& !strip_start_then_trim!(line, "declare") {
+		if !line.starts_with("<<") {
 			return None;
 		}
 
-		let mut args_iter =
-			ArgBuilder {
-				chars: line.chars().peekable(),
-				allow_yarn_set_syntax: false,
+		let mut lexemes = lex(line);
+
+		let mut declaration =
+			match lexemes.next()? {
+				Ok(Lexeme { token: Token::Command(command), .. }) => command.trim(),
+				Ok(_) => return None,
+				Err(err) => return Some(Err(err)),
 			};
 
-		match parse_args(&mut args_iter) {
+		if !strip_start_then_trim!(declaration, "declare") {
+			return None;
+		}
+
+		for lexeme in lexemes {
+			match lexeme {
+				Ok(Lexeme { token: Token::Text(text), .. }) if text.trim().is_empty() => {},
+				Ok(Lexeme { token: Token::Comment(_), .. }) => {},
+				Ok(Lexeme { span, .. }) => {
+					return Some(Err(anyhow!(
+						"Unexpected characters after closing command(`>>`).\n\
+						 Remaining: `{}`\n\n\
+						 Help: Extra characters are not allowed after `>>`.\n\
+						 Help: Command statements cannot have metadata (which is started with `#`)", &line[span.start..])));
+				},
+				Err(err) => return Some(Err(err)),
+			}
+		}
+
+		match parse_declaration(declaration) {
 			Ok((var_name, default_value, cast_ty)) => {
 				if default_value.iter_exprs().any(|expr| matches!(expr, YarnExpr::GetVar(_))) {
 					Some(Err(anyhow!(
@@ -394,6 +154,7 @@ impl VarDeclaration {
 				} else {
 					Some(Ok(
 						VarDeclaration {
+							source_file: file.to_path_buf(),
 							line_number: unparsed_line.line_number,
 							var_name,
 							default_value,
@@ -404,9 +165,8 @@ impl VarDeclaration {
 			Err(err) =>
 				Some(Err(anyhow!(
 					"Could not parse line as `declare` statement(`<<declare $var_name [default_value]>>`).\n\
-				     Remaining Line: {}.\n\
-					 Error: `{err}`"
-					, args_iter.chars.collect::<String>())))
+					 Declaration: `{declaration}`.\n\
+					 Error: `{err}`")))
 		}
 	}
-}
\ No newline at end of file
+}
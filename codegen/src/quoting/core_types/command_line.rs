@@ -26,6 +26,7 @@ fn tokens_imports_and_trait(cfg: &YarnConfig) -> Tokens {
 		
 		pub trait ICommandLine {
 			fn line_id(&self) -> &'static str;
+			fn source_location(&self) -> (&'static str, u32);
 			fn command(&self, storage: &$(&cfg.storage_direct)) -> $(&cfg.command_direct);
 			fn advance(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield;
 		}
@@ -58,6 +59,7 @@ fn tokens_enum(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Tokens
 			DELEGATES: {
 				impl trait ICommandLine {
 					[fn line_id(&self) -> &'static str]
+					[fn source_location(&self) -> (&'static str, u32)]
 					[fn command(&self, storage: &$(&cfg.storage_direct)) -> $(&cfg.command_direct)]
 					[fn advance(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield]
 				}
@@ -1,33 +1,28 @@
 use crate::config::YarnConfig;
 use crate::expressions::declaration_ty::DeclarationTy;
 use crate::expressions::yarn_expr::YarnExpr;
-use crate::parsing::raw::speech::Speaker;
 use crate::parsing::raw::var_declaration::VarDeclaration;
-use crate::quoting::quotable_types::line_ids::{BuiltInCommand, IDFlatLine, IDFlow};
-use crate::quoting::quotable_types::node::IDNode;
-use crate::quoting::quotable_types::scope::IDScope;
 use crate::quoting::util::{Comments, SeparatedItems};
-use anyhow::{anyhow, Result};
 use genco::prelude::rust::Tokens;
 use genco::quote;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 pub fn all_tokens(
 	cfg: &YarnConfig,
-	nodes: &[IDNode],
 	var_declarations: &[VarDeclaration],
-) -> Result<Tokens> {
+	var_types: &HashMap<String, Option<DeclarationTy>>,
+) -> Tokens {
 	let imports = tokens_imports(cfg);
 	let macro_declaration = tokens_macro_declaration();
 
-	let inferred_vars = assemble_inferred_vars(nodes, var_declarations)?;
+	let inferred_vars = assemble_inferred_vars(var_declarations, var_types);
 	let macro_expansion = tokens_macro_expansion(cfg, inferred_vars);
 
-	Ok(quote! {
+	quote! {
 		$imports
 		$macro_declaration
 		$macro_expansion
-	})
+	}
 }
 
 fn tokens_imports(cfg: &YarnConfig) -> Tokens {
@@ -36,7 +31,7 @@ fn tokens_imports(cfg: &YarnConfig) -> Tokens {
 		#![allow(non_snake_case)]
 		#![allow(unused)]
 		
-		use std::collections::HashMap;
+		use std::collections::{HashMap, HashSet};
 		use serde::{Deserialize, Serialize};
 		use $(&cfg.shared_qualified)::*;
 		use rand::{Rng, SeedableRng};
@@ -62,6 +57,14 @@ fn tokens_macro_declaration() -> Tokens {
 				pub struct $storage_name {
 					rng: Xoshiro256PlusPlus,
 					visited_counters: HashMap<NodeTitle, usize>,
+					#[serde(default)]
+					once_seen: HashSet<String>,
+					#[serde(default)]
+					last_seen: HashMap<NodeTitle, usize>,
+					#[serde(default)]
+					seen_clock: usize,
+					#[serde(default)]
+					detour_stack: Vec<DetourReturn>,
 				    vars: StorageVars,
 				}
 				
@@ -70,6 +73,10 @@ fn tokens_macro_declaration() -> Tokens {
 					    Self {
 							rng: Xoshiro256PlusPlus::from_entropy(),
 						    visited_counters: HashMap::new(),
+						    once_seen: HashSet::new(),
+						    last_seen: HashMap::new(),
+						    seen_clock: 0,
+						    detour_stack: Vec::new(),
 						    vars: StorageVars {
 							    $($name: <$var_ty>::from($default)),*
 						    }
@@ -114,6 +121,42 @@ fn tokens_macro_declaration() -> Tokens {
 					pub fn visited_count(&self, node_title: NodeTitle) -> usize {
 						*self.visited_counters.get(&node_title).unwrap_or(&0)
 					}
+				
+					/// Returns `true` if the `<<once>>` block or option identified by `key` already ran.
+					pub fn once_seen(&self, key: &str) -> bool {
+						self.once_seen.contains(key)
+					}
+				
+					/// Marks the `<<once>>` block or option identified by `key` as ran, it will be skipped from now on.
+					pub fn mark_once_seen(&mut self, key: &str) {
+						self.once_seen.insert(key.to_string());
+					}
+				
+					/// When the node was last picked from its node group, `None` if never, higher values are more recent.
+					pub fn last_seen(&self, node_title: NodeTitle) -> Option<usize> {
+						self.last_seen.get(&node_title).copied()
+					}
+				
+					/// Marks the node as the most recently picked one, for the `least_recently_seen` saliency strategy.
+					pub fn mark_node_seen(&mut self, node_title: NodeTitle) {
+						self.seen_clock += 1;
+						self.last_seen.insert(node_title, self.seen_clock);
+					}
+				
+					/// Saves where to come back to when the node started by `<<detour>>` ends.
+					pub fn push_detour(&mut self, detour: DetourReturn) {
+						self.detour_stack.push(detour);
+					}
+				
+					/// Takes the latest detour, `None` if the current node wasn't started by `<<detour>>`.
+					pub fn pop_detour(&mut self) -> Option<DetourReturn> {
+						self.detour_stack.pop()
+					}
+				
+					/// Forgets every pending detour, `<<stop>>` ends the dialogue even inside of one.
+					pub fn clear_detours(&mut self) {
+						self.detour_stack.clear();
+					}
 		
 					pub fn random(&mut self) -> f64 {
 						self.rng.gen_range(0.0..1.0)
@@ -153,181 +196,23 @@ fn tokens_macro_declaration() -> Tokens {
 	quote! { $declaration_str }
 }
 
+/// Pairs the types resolved by [check_nodes](crate::type_check::check_nodes) with the default value of declared variables.
 fn assemble_inferred_vars<'a>(
-	nodes: &'a [IDNode],
 	var_declarations: &'a [VarDeclaration],
-) -> Result<HashMap<&'a str, (Option<&'a YarnExpr>, Option<DeclarationTy>)>> {
-	let mut assembled_vars: HashMap<&str, (Option<&YarnExpr>, Option<DeclarationTy>)> = {
-		let mut temp: HashMap<&str, &VarDeclaration> = HashMap::new();
-
-		var_declarations.iter().try_for_each(|declaration| {
-			let var_name = declaration.var_name.as_str();
-
-			match temp.get_key_value(var_name) {
-				Some((_, already_declaration)) => {
-					Err(anyhow!(
-						"Variable `{var_name}` is declared more than once.\n\
-						 First time at line nº{first_num}, with default value: `{first_expr:?}`\n\
-						 Second time at line nº{second_num}, with default value: `{second_expr:?}`", 
-						 first_num = already_declaration.line_number, second_num = declaration.line_number,
-						 first_expr = already_declaration.default_value, second_expr = declaration.default_value))
-				}
-				None => {
-					temp.insert(var_name, declaration);
-					Ok(())
-				}
-			}
-		})?;
-
-		temp.into_iter()
-		    .map(|(var_name, declaration)| (var_name, (Some(&declaration.default_value), declaration.infer_ty())))
-		    .collect()
-	};
-
-	let inferred_usages = infer_types_from_usages(nodes);
-
-	for (inferred_name, possible_types) in inferred_usages {
-		if !assembled_vars.contains_key(inferred_name) {
-			if possible_types.len() == 1 {
-				assembled_vars.insert(inferred_name, (None, possible_types.into_iter().next()));
-			} else { // if 0 or 2+, leave as None
-				assembled_vars.insert(inferred_name, (None, None));
-			}
-		}
-	}
-
-	Ok(assembled_vars)
-}
-
-fn all_var_usages(nodes: &[IDNode]) -> (Vec<(&str, &YarnExpr)>, Vec<&str>) {
-	let mut in_exprs = vec![];
-	let mut in_speakers = vec![];
-
-	for node in nodes {
-		for scope in &node.scopes {
-			insert_var_usages(scope, &mut in_exprs, &mut in_speakers);
-		}
-	}
-
-	(in_exprs, in_speakers)
-}
-
-fn iter_insert_args_usages<'a>(
-	root_arg: &'a YarnExpr,
-	in_exprs: &mut Vec<(&'a str, &'a YarnExpr)>,
-) {
-	for expr in root_arg.iter_exprs() {
-		if let YarnExpr::GetVar(var_name) = expr {
-			in_exprs.push((var_name, root_arg))
-		}
-	}
-}
-
-fn insert_var_usages<'a>(
-	scope: &'a IDScope,
-	in_exprs: &mut Vec<(&'a str, &'a YarnExpr)>,
-	in_speakers: &mut Vec<&'a str>,
-) {
-	for flow in &scope.flows {
-		match flow {
-			IDFlow::Flat(lines) => {
-				for line in lines {
-					match line {
-						IDFlatLine::Speech(speech) => {
-							let (_, args) = &speech.text;
-
-							for arg in args {
-								iter_insert_args_usages(arg, in_exprs);
-							}
-
-							if let Some(Speaker::Variable(var_name)) = &speech.speaker {
-								in_speakers.push(var_name);
-							}
-						}
-						IDFlatLine::CustomCommand(cmd) => {
-							for arg in &cmd.args {
-								iter_insert_args_usages(arg, in_exprs);
-							}
-						}
-						IDFlatLine::BuiltInCommand(built_in) => {
-							match built_in {
-								BuiltInCommand::Set {
-									var_name, value,
-									op: _, line_number: _,
-								} => {
-									in_exprs.push((var_name, value))
-								}
-								BuiltInCommand::Jump { .. } => {}
-								BuiltInCommand::Stop { .. } => {}
-							}
-						}
-					}
-				}
-			}
-			IDFlow::OptionsFork(options_fork) => {
-				for (line, scope_option) in options_fork.options.iter() {
-					let (_, args) = &line.text;
-
-					for arg in args {
-						iter_insert_args_usages(arg, in_exprs);
-					}
-
-					if let Some(if_condition) = &line.if_condition {
-						iter_insert_args_usages(if_condition, in_exprs);
-					}
-
-					if let Some(scope) = scope_option {
-						insert_var_usages(scope, in_exprs, in_speakers);
-					}
-				}
-			}
-			IDFlow::IfBranch(if_branch) => {
-				iter_insert_args_usages(&if_branch.if_.0.condition, in_exprs);
-
-				if let Some(scope) = &if_branch.if_.1 {
-					insert_var_usages(scope, in_exprs, in_speakers);
-				}
-
-				for (else_if, else_if_scope_option) in &if_branch.else_ifs {
-					iter_insert_args_usages(&else_if.condition, in_exprs);
-
-					if let Some(else_if_scope) = else_if_scope_option {
-						insert_var_usages(else_if_scope, in_exprs, in_speakers);
-					}
-				}
-
-				if let Some((_, Some(else_scope))) = &if_branch.else_ {
-					insert_var_usages(else_scope, in_exprs, in_speakers);
-				}
-			}
-		}
-	}
-}
-
-fn infer_types_from_usages(nodes: &[IDNode]) -> HashMap<&str, HashSet<DeclarationTy>> {
-	let (in_exprs, in_speakers) = all_var_usages(nodes);
-
-	let mut inferred_vars = HashMap::new();
-
-	let expr_tys = in_exprs
-		.into_iter()
-		.filter_map(|(var_name, expr)| expr.infer_ty().map(|ty| (var_name, ty)));
-
-	for (var_name, ty) in expr_tys {
-		inferred_vars
-			.entry(var_name)
-			.or_insert_with(HashSet::new)
-			.insert(ty);
-	}
-
-	for var_name in in_speakers {
-		inferred_vars
-			.entry(var_name)
-			.or_insert_with(HashSet::new)
-			.insert(DeclarationTy::String);
-	}
-
-	inferred_vars
+	var_types: &'a HashMap<String, Option<DeclarationTy>>,
+) -> HashMap<&'a str, (Option<&'a YarnExpr>, Option<DeclarationTy>)> {
+	var_types
+		.iter()
+		.map(|(var_name, inferred_ty)| {
+			// Duplicate declarations were already reported by `find_duplicates`.
+			let default_value =
+				var_declarations
+					.iter()
+					.find(|declaration| declaration.var_name == *var_name)
+					.map(|declaration| &declaration.default_value);
+
+			(var_name.as_str(), (default_value, *inferred_ty))
+		}).collect()
 }
 
 fn tokens_macro_expansion(
@@ -403,6 +288,14 @@ fn tokens_macro_expansion(
 		pub struct $(&cfg.storage_direct) {
 			rng: Xoshiro256PlusPlus,
 			visited_counters: HashMap<NodeTitle, usize>,
+			#[serde(default)]
+			once_seen: HashSet<String>,
+			#[serde(default)]
+			last_seen: HashMap<NodeTitle, usize>,
+			#[serde(default)]
+			seen_clock: usize,
+			#[serde(default)]
+			detour_stack: Vec<DetourReturn>,
 			vars: StorageVars,
 		}
 	
@@ -411,6 +304,10 @@ fn tokens_macro_expansion(
 				Self {
 					rng: Xoshiro256PlusPlus::from_entropy(),
 					visited_counters: HashMap::new(),
+					once_seen: HashSet::new(),
+					last_seen: HashMap::new(),
+					seen_clock: 0,
+					detour_stack: Vec::new(),
 					vars: StorageVars {
 						$(SeparatedItems(vars_default_value_tokens, ",\n"))
 					}
@@ -459,6 +356,58 @@ fn tokens_macro_expansion(
 			pub fn visited_count(&self, node_title: NodeTitle) -> usize {
 				*self.visited_counters.get(&node_title).unwrap_or(&0)
 			}
+
+			$(Comments([
+				r#"Returns `true` if the `<<once>>` block or option identified by `key` already ran."#,
+				r#"The flags are saved with the storage, so they survive save/load."#,
+			]))
+			pub fn once_seen(&self, key: &str) -> bool {
+				self.once_seen.contains(key)
+			}
+
+			$(Comments([
+				r#"Marks the `<<once>>` block or option identified by `key` as ran, it will be skipped from now on."#,
+			]))
+			pub fn mark_once_seen(&mut self, key: &str) {
+				self.once_seen.insert(key.to_string());
+			}
+
+			$(Comments([
+				r#"When the node was last picked from its node group, `None` if never, higher values are more recent."#,
+			]))
+			pub fn last_seen(&self, node_title: NodeTitle) -> Option<usize> {
+				self.last_seen.get(&node_title).copied()
+			}
+
+			$(Comments([
+				r#"Marks the node as the most recently picked one, for the `least_recently_seen` saliency strategy."#,
+			]))
+			pub fn mark_node_seen(&mut self, node_title: NodeTitle) {
+				self.seen_clock += 1;
+				self.last_seen.insert(node_title, self.seen_clock);
+			}
+
+			$(Comments([
+				r#"Saves where to come back to when the node started by `<<detour>>` ends."#,
+				r#"The return stack is saved with the storage, so a detour can be resumed after save/load."#,
+			]))
+			pub fn push_detour(&mut self, detour: DetourReturn) {
+				self.detour_stack.push(detour);
+			}
+
+			$(Comments([
+				r#"Takes the latest detour, `None` if the current node wasn't started by `<<detour>>`."#,
+			]))
+			pub fn pop_detour(&mut self) -> Option<DetourReturn> {
+				self.detour_stack.pop()
+			}
+
+			$(Comments([
+				r#"Forgets every pending detour, `<<stop>>` ends the dialogue even inside of one."#,
+			]))
+			pub fn clear_detours(&mut self) {
+				self.detour_stack.clear();
+			}
 			
 			pub fn random(&mut self) -> f64 {
 				self.rng.gen_range(0.0..1.0)
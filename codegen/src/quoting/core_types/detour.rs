@@ -0,0 +1,85 @@
+use crate::config::YarnConfig;
+use crate::quoting::quotable_types::enums::SUFFIX_DETOUR;
+use crate::quoting::quotable_types::node::{IDNode, LinesMap};
+use crate::quoting::util::{Comments, SeparatedItems};
+use genco::lang::rust::Tokens;
+use genco::quote;
+
+pub fn all_tokens(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Tokens {
+	let imports_and_trait = tokens_imports_and_trait(cfg);
+	let enum_tokens = tokens_enum(cfg, nodes_mapped);
+	let finish_node_tokens = tokens_finish_node(cfg);
+
+	quote! {
+		$imports_and_trait
+		$enum_tokens
+		$finish_node_tokens
+	}
+}
+
+fn tokens_imports_and_trait(cfg: &YarnConfig) -> Tokens {
+	quote! {
+		#![allow(non_camel_case_types)]
+		#![allow(non_snake_case)]
+		#![allow(unused)]
+
+		use serde::{Deserialize, Serialize};
+		use $(&cfg.shared_qualified)::*;
+
+		pub trait IDetourReturn {
+			$(Comments([
+				r#"Continues the node that started the detour, from the line after its `<<detour>>`."#]))
+			#[must_use]
+			fn resume(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield;
+		}
+	}
+}
+
+fn tokens_enum(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Tokens {
+	let titles = nodes_mapped
+		.iter()
+		.filter_map(|(node, lines_map)| {
+			if !lines_map.detours.is_empty() {
+				let title = node.metadata.title.clone() + SUFFIX_DETOUR;
+				Some(quote! { $(title) })
+			} else {
+				None
+			}
+		});
+
+	quote! {
+		declarative_type_state::delegated_enum! {
+			ENUM_OUT: {
+				#[derive(Debug, Copy, Clone)]
+				#[derive(PartialEq, Eq, Hash)]
+				#[derive(Serialize, Deserialize)]
+				pub enum DetourReturn {
+					$(SeparatedItems(titles, ",\n"))
+				}
+			}
+
+			DELEGATES: {
+				impl trait IDetourReturn {
+					[fn resume(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield]
+				}
+			}
+		}
+	}
+}
+
+fn tokens_finish_node(cfg: &YarnConfig) -> Tokens {
+	quote! {
+		$(Comments([
+			r#"Called when `node_title` ends, either by reaching its last line or by `<<return>>`."#,
+			r#"Resumes the latest `<<detour>>` in the storage's return stack, or finishes the dialogue if there's none."#]))
+		pub fn finish_node(storage: &mut $(&cfg.storage_direct), node_title: NodeTitle) -> YarnYield {
+			match storage.pop_detour() {
+				Some(detour) => {
+					count_visit(storage, node_title);
+					detour.resume(storage)
+				},
+				None => YarnYield::Finished,
+			}
+		}
+	}
+}
@@ -8,6 +8,7 @@ pub mod var_trait;
 pub mod nodes;
 pub mod default_storage;
 pub mod built_in_functions;
+pub mod detour;
 
 use genco::lang::rust::Tokens;
 use genco::quote;
@@ -35,6 +36,7 @@ pub fn tokens_root_module(cfg: &YarnConfig) -> Tokens {
 		pub mod speech;
 		pub mod command_line;
 		pub mod built_in_functions;
+		pub mod detour;
 		$default_storage_mod
 		
 		pub type PlayerDecision = usize;
@@ -59,6 +61,7 @@ pub fn tokens_root_module(cfg: &YarnConfig) -> Tokens {
 			pub use super::var_trait::*;
 			pub use super::speech::*;
 			pub use super::command_line::*;
+			pub use super::detour::*;
 			pub use super::built_in_functions;
 			pub use super::nodes::*;
 		}
@@ -5,7 +5,7 @@ use crate::quoting::quotable_types::enums::LineEnum;
 use crate::quoting::quotable_types::line_ids::{IDCustomCommand, IDFlatLine, IDFlow};
 use crate::quoting::quotable_types::node::{IDNode, LinesMap};
 use crate::quoting::quotable_types::scope::IDScope;
-use crate::quoting::util::SeparatedItems;
+use crate::quoting::util::{source_comments, source_location_fn, SeparatedItems};
 use genco::prelude::quoted;
 use genco::prelude::rust::Tokens;
 use genco::quote;
@@ -22,7 +22,7 @@ pub fn all_tokens(
 	let enum_name = enums::enum_type_command(&node.metadata.title);
 
 	let tokens_imports = tokens_imports(cfg);
-	let tokens_enum = tokens_enum(cfg, &lines_map.commands, &enum_name);
+	let tokens_enum = tokens_enum(cfg, node, &lines_map.commands, &enum_name);
 	let tokens_trait_impl = tokens_trait_impl(cfg, &lines_map.commands, node);
 
 	Some(quote! {
@@ -45,15 +45,17 @@ fn tokens_imports(cfg: &YarnConfig) -> Tokens {
 
 fn tokens_enum(
 	cfg: &YarnConfig,
+	node: &IDNode,
 	commands: &[(&IDCustomCommand, LineEnum)],
 	enum_name: &str,
 ) -> Tokens {
 	let enum_variants = commands.iter().map(|(_, line_enum)| line_enum.variant_name());
 
-	let structs = commands.iter().map(|(_, line_enum)| {
+	let structs = commands.iter().map(|(command, line_enum)| {
 		let name = line_enum.variant_name();
 
 		quote! {
+			$(source_comments(cfg, node, &command.span))
 			#[derive(Debug, Copy, Clone)]
 			#[derive(PartialEq, Eq, Hash)]
 			#[derive(Serialize, Deserialize)]
@@ -74,6 +76,7 @@ fn tokens_enum(
 			DELEGATES: {
 				impl trait ICommandLine {
 					[fn line_id(&self) -> &'static str]
+					[fn source_location(&self) -> (&'static str, u32)]
 					[fn command(&self, storage: &$(&cfg.storage_direct)) -> $(&cfg.command_direct)]
 					[fn advance(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield]
 				}
@@ -105,7 +108,7 @@ fn tokens_trait_impl(
 						}
 					} else {
 						quote! {
-							$(&cfg.command_direct)::$(&command.variant)($(SeparatedItems(&command.args, ", "))),
+							$(&cfg.command_direct)::$(&command.variant)($(SeparatedItems(&command.args, ", ")))
 						}
 					};
 				
@@ -125,6 +128,7 @@ fn tokens_trait_impl(
 			quote! {
 				impl ICommandLine for $(line_enum.variant_name()) {
 					$line_id_impl
+					$(source_location_fn(cfg, &command.span))
 					$command_impl
 					$advance_impl
 				}
@@ -206,6 +210,13 @@ fn insert_scope_advance_fns<'a>(
 					}
 				}
 			}
+			IDFlow::LineGroup(line_group) => {
+				for (_, maybe_scope) in line_group.lines.iter() {
+					if let Some(line_scope) = maybe_scope {
+						insert_scope_advance_fns(next_fns, line_scope, next_scopes, title);
+					}
+				}
+			}
 			IDFlow::IfBranch(if_branch) => {
 				if let Some(if_scope) = &if_branch.if_.1 {
 					insert_scope_advance_fns(next_fns, if_scope, next_scopes, title);
@@ -221,6 +232,15 @@ fn insert_scope_advance_fns<'a>(
 					insert_scope_advance_fns(next_fns, else_scope, next_scopes, title);
 				}
 			}
+			IDFlow::OnceBranch(once_branch) => {
+				if let Some(once_scope) = &once_branch.once_.1 {
+					insert_scope_advance_fns(next_fns, once_scope, next_scopes, title);
+				}
+
+				if let Some((_, Some(else_scope))) = &once_branch.else_ {
+					insert_scope_advance_fns(next_fns, else_scope, next_scopes, title);
+				}
+			}
 		}
 	}
 }
@@ -0,0 +1,306 @@
+use crate::config::YarnConfig;
+use crate::quoting::quotable_types::advance::build_next_fn;
+use crate::quoting::quotable_types::enums;
+use crate::quoting::quotable_types::enums::DetourEnum;
+use crate::quoting::quotable_types::line_ids::{BuiltInCommand, IDFlatLine, IDFlow};
+use crate::quoting::quotable_types::node::{IDNode, LinesMap};
+use crate::quoting::quotable_types::scope::IDScope;
+use crate::quoting::util::SeparatedItems;
+use genco::prelude::rust::Tokens;
+use genco::quote;
+
+pub fn all_tokens(
+	cfg: &YarnConfig,
+	node: &IDNode,
+	lines_map: &LinesMap,
+) -> Option<Tokens> {
+	if lines_map.detours.is_empty() {
+		return None;
+	}
+
+	let enum_name = enums::enum_type_detour(&node.metadata.title);
+
+	let tokens_imports = tokens_imports(cfg);
+	let tokens_enum = tokens_enum(cfg, &lines_map.detours, &enum_name);
+	let tokens_trait_impl = tokens_trait_impl(cfg, &lines_map.detours, node);
+
+	Some(quote! {
+		$tokens_imports
+		$tokens_enum
+		$tokens_trait_impl
+	})
+}
+
+fn tokens_imports(cfg: &YarnConfig) -> Tokens {
+	quote! {
+		#![allow(non_camel_case_types)]
+		#![allow(non_snake_case)]
+		#![allow(unused)]
+
+		use serde::{Deserialize, Serialize};
+		use $(&cfg.shared_qualified)::*;
+	}
+}
+
+fn tokens_enum(
+	cfg: &YarnConfig,
+	detours: &[DetourEnum],
+	enum_name: &str,
+) -> Tokens {
+	let enum_variants = detours.iter().map(DetourEnum::variant_name);
+
+	let structs = detours.iter().map(|detour_enum| {
+		quote! {
+			#[derive(Debug, Copy, Clone)]
+			#[derive(PartialEq, Eq, Hash)]
+			#[derive(Serialize, Deserialize)]
+			pub struct $(detour_enum.variant_name());
+		}
+	});
+
+	quote! {
+		declarative_type_state::unit_enum_delegated! {
+			ENUM_OUT: {
+				#[derive(Debug, Copy, Clone)]
+				#[derive(PartialEq, Eq, Hash)]
+				#[derive(Serialize, Deserialize)]
+				pub enum $enum_name {
+					$(SeparatedItems(enum_variants, ",\n"))
+				}
+			}
+			DELEGATES: {
+				impl trait IDetourReturn {
+					[fn resume(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield]
+				}
+			}
+		}
+
+		$(SeparatedItems(structs, "\n"))
+	}
+}
+
+fn tokens_trait_impl(
+	cfg: &YarnConfig,
+	detours: &[DetourEnum],
+	node: &IDNode,
+) -> Tokens {
+	let impls = all_resume_fns(detours, node)
+		.map(|(detour_enum, resume_fn)| {
+			quote! {
+				impl IDetourReturn for $(detour_enum.variant_name()) {
+					fn resume(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield {
+						$resume_fn
+					}
+				}
+			}
+		});
+
+	quote! { $(SeparatedItems(impls, "\n")) }
+}
+
+fn all_resume_fns<'a>(
+	detours: &'a [DetourEnum],
+	node: &'a IDNode,
+) -> impl Iterator<Item = (&'a DetourEnum<'a>, Tokens)> {
+	let title = node.metadata.title.as_str();
+	let mut resume_fns = Vec::new();
+	let mut scopes: Vec<&IDScope> = node.scopes.iter().collect();
+
+	while !scopes.is_empty() {
+		let scope = scopes.remove(0);
+		insert_scope_resume_fns(&mut resume_fns, scope, &scopes, title);
+	}
+
+	if resume_fns.len() != detours.len() {
+		panic!(
+			"The number of resume functions that came from detours(`{}`) \
+			 does not match the number of detours in the input(`{}`).\n\
+			 Node: `{title}`", detours.len(), resume_fns.len());
+	}
+
+	resume_fns
+		.into_iter()
+		.map(|(virtual_id, resume_fn)| {
+			let detour_enum = detours
+				.iter()
+				.find(|detour_enum| detour_enum.raw_id == virtual_id)
+				.unwrap_or_else(|| panic!(
+					"A resume function was generated for a detour that is not included in the input.\n\
+					 Virtual id: {virtual_id}\n\
+					 Resume function: {resume_fn:?}"));
+
+			(detour_enum, resume_fn)
+		})
+}
+
+/// The code after each `<<detour>>`, built the same way as the `advance` of the lines before it.
+fn insert_scope_resume_fns<'a>(
+	resume_fns: &mut Vec<(&'a str, Tokens)>,
+	current_scope: &'a IDScope,
+	next_scopes: &[&IDScope],
+	title: &str,
+) {
+	let mut flows: Vec<&IDFlow> = current_scope.flows.iter().collect();
+
+	while !flows.is_empty() {
+		let flow = flows.remove(0);
+
+		match flow {
+			IDFlow::Flat(flat_lines) => {
+				let mut flat_lines: Vec<&IDFlatLine> = flat_lines.iter().collect();
+
+				while !flat_lines.is_empty() {
+					let flat_line = flat_lines.remove(0);
+
+					if let IDFlatLine::BuiltInCommand(BuiltInCommand::Detour { virtual_id, .. }) = flat_line {
+						let resume_fn = build_next_fn(
+							flat_lines.iter().copied(),
+							flows.iter().copied(),
+							next_scopes.iter().copied(),
+							title,
+						);
+
+						resume_fns.push((virtual_id.as_str(), resume_fn));
+					}
+				}
+			}
+			IDFlow::OptionsFork(options_fork) => {
+				for (_, maybe_scope) in options_fork.options.iter() {
+					if let Some(option_scope) = maybe_scope {
+						insert_scope_resume_fns(resume_fns, option_scope, next_scopes, title);
+					}
+				}
+			}
+			IDFlow::LineGroup(line_group) => {
+				for (_, maybe_scope) in line_group.lines.iter() {
+					if let Some(line_scope) = maybe_scope {
+						insert_scope_resume_fns(resume_fns, line_scope, next_scopes, title);
+					}
+				}
+			}
+			IDFlow::IfBranch(if_branch) => {
+				if let Some(if_scope) = &if_branch.if_.1 {
+					insert_scope_resume_fns(resume_fns, if_scope, next_scopes, title);
+				}
+
+				for (_, maybe_scope) in if_branch.else_ifs.iter() {
+					if let Some(else_if_scope) = maybe_scope {
+						insert_scope_resume_fns(resume_fns, else_if_scope, next_scopes, title);
+					}
+				}
+
+				if let Some((_, Some(else_scope))) = &if_branch.else_ {
+					insert_scope_resume_fns(resume_fns, else_scope, next_scopes, title);
+				}
+			}
+			IDFlow::OnceBranch(once_branch) => {
+				if let Some(once_scope) = &once_branch.once_.1 {
+					insert_scope_resume_fns(resume_fns, once_scope, next_scopes, title);
+				}
+
+				if let Some((_, Some(else_scope))) = &once_branch.else_ {
+					insert_scope_resume_fns(resume_fns, else_scope, next_scopes, title);
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn test_detour_tokens() {
+	use crate::quoting::core_types::nodes::enums::speech;
+	use crate::quoting::test_util::{parse_id_nodes, render};
+
+	let nodes = parse_id_nodes(
+		"title: Tour\n\
+		 ---\n\
+		 Before\n\
+		 <<detour Side>>\n\
+		 After\n\
+		 <<if $done>>\n\
+		 \x20   <<return>>\n\
+		 <<endif>>\n\
+		 Done\n\
+		 <<stop>>\n\
+		 ===\n\
+		 title: Side\n\
+		 ---\n\
+		 Aside\n\
+		 ===");
+
+	let cfg = YarnConfig::for_tests();
+	let nodes_mapped = nodes.iter().map(|node| (node, node.map_lines())).collect::<Vec<_>>();
+	let (tour, tour_lines) = &nodes_mapped[0];
+
+	// The line before `<<detour>>` pushes where to come back, then starts the other node.
+	let speech = render(speech::all_tokens(&cfg, tour, tour_lines).unwrap());
+	assert!(speech.contains(
+		"        storage.push_detour(Tour_Detour::D1.into());\n\
+		 \x20       return Side.start(storage);\n"), "{speech}");
+	assert!(speech.contains(
+		"        if storage.get_var::<done>() {\n\
+		 \x20           return finish_node(storage, NodeTitle::Tour);\n\
+		 \x20       } \n"), "{speech}");
+	assert!(speech.contains(
+		"        storage.clear_detours();\n\
+		 \x20       return YarnYield::Finished;\n"), "{speech}");
+
+	// Resuming continues from the line after `<<detour>>`.
+	let continuations = render(all_tokens(&cfg, tour, tour_lines).unwrap());
+	assert!(continuations.contains("        pub enum Tour_Detour {\n            D1\n        }\n"), "{continuations}");
+	assert!(continuations.contains(
+		"impl IDetourReturn for D1 {\n\
+		 \x20   fn resume(&self, storage: &mut MyVariablesStorage) -> YarnYield {\n\
+		 \x20       return YarnYield::Instruction(Tour_Line_Any::L_to1.into());\n"), "{continuations}");
+
+	let (side, side_lines) = &nodes_mapped[1];
+	assert!(all_tokens(&cfg, side, side_lines).is_none());
+
+	let side_speech = render(speech::all_tokens(&cfg, side, side_lines).unwrap());
+	assert!(side_speech.contains("        return finish_node(storage, NodeTitle::Side);\n"), "{side_speech}");
+
+	// Only nodes with detours are part of `DetourReturn`, `finish_node` resumes the latest one.
+	let shared = render(crate::quoting::core_types::detour::all_tokens(&cfg, &nodes_mapped));
+	assert!(shared.contains("        pub enum DetourReturn {\n            Tour_Detour\n        }\n"), "{shared}");
+	assert!(shared.contains(
+		"    match storage.pop_detour() {\n\
+		 \x20       Some(detour) => {\n\
+		 \x20           count_visit(storage, node_title);\n\
+		 \x20           detour.resume(storage)\n\
+		 \x20       },\n\
+		 \x20       None => YarnYield::Finished,\n\
+		 \x20   }\n"), "{shared}");
+}
+
+#[test]
+fn test_detours_ignore_other_nodes() {
+	use crate::quoting::test_util::parse_id_nodes;
+	use pretty_assertions::assert_eq;
+
+	let tour =
+		"title: Tour\n\
+		 ---\n\
+		 <<detour Side>>\n\
+		 <<if $done>>\n\
+		 \x20   <<detour Side>>\n\
+		 <<endif>>\n\
+		 Done\n\
+		 ===\n\
+		 title: Side\n\
+		 ---\n\
+		 Aside\n\
+		 ===";
+
+	let saved_names = |text: &str| {
+		let nodes = parse_id_nodes(text);
+		let tour = nodes.iter().find(|node| node.metadata.title == "Tour").unwrap();
+		tour.map_lines().detours.iter().map(DetourEnum::qualified).collect::<Vec<_>>()
+	};
+
+	// A detour stack saved before the edit still names continuations of the new build.
+	let saved = saved_names(tour);
+	assert_eq!(saved, ["Tour_Detour::D1", "Tour_Detour::D2"]);
+
+	let edited = saved_names(&format!("title: Intro\n---\nHello\n<<detour Side>>\nBye\n===\n{tour}"));
+	assert_eq!(edited, saved);
+}
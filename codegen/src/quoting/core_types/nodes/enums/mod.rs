@@ -3,3 +3,4 @@ pub mod speech;
 pub mod command;
 pub mod options_fork;
 pub mod option_line;
+pub mod detour;
@@ -5,7 +5,7 @@ use crate::quoting::quotable_types::enums::OptionLineEnum;
 use crate::quoting::quotable_types::line_ids::{IDFlow, IDOptionLine};
 use crate::quoting::quotable_types::node::{IDNode, LinesMap};
 use crate::quoting::quotable_types::scope::IDScope;
-use crate::quoting::util::SeparatedItems;
+use crate::quoting::util::{source_comments, source_location_fn, SeparatedItems};
 use genco::prelude::quoted;
 use genco::prelude::rust::Tokens;
 use genco::quote;
@@ -22,7 +22,7 @@ pub fn all_tokens(
 	let enum_name = enums::enum_type_option_line(&node.metadata.title);
 
 	let tokens_imports = tokens_imports(cfg);
-	let tokens_enum = tokens_enum(cfg, &lines_map.option_lines, &enum_name);
+	let tokens_enum = tokens_enum(cfg, node, &lines_map.option_lines, &enum_name);
 	let tokens_trait_impl = tokens_trait_impl(cfg, &lines_map.option_lines, node);
 
 	Some(quote! {
@@ -47,15 +47,17 @@ fn tokens_imports(cfg: &YarnConfig) -> Tokens {
 
 fn tokens_enum(
 	cfg: &YarnConfig,
+	node: &IDNode,
 	options: &[(&IDOptionLine, OptionLineEnum)],
 	enum_name: &str,
 ) -> Tokens {
 	let enum_variants = options.iter().map(|(_, line_enum)| line_enum.variant_name());
 
-	let structs = options.iter().map(|(_, line_enum)| {
+	let structs = options.iter().map(|(option, line_enum)| {
 		let name = line_enum.variant_name();
 
 		quote! {
+			$(source_comments(cfg, node, &option.span))
 			#[derive(Debug, Copy, Clone)]
 			#[derive(PartialEq, Eq, Hash)]
 			#[derive(Serialize, Deserialize)]
@@ -76,6 +78,7 @@ fn tokens_enum(
 			DELEGATES: {
 				impl trait IOptionLine {
 					[fn line_id(&self) -> &'static str]
+					[fn source_location(&self) -> (&'static str, u32)]
 					[fn tags(&self) -> &'static [&'static str]]
 					[fn is_available(&self, storage: &$(&cfg.storage_direct)) -> Option<bool>]
 					[fn text(&self, storage: &$(&cfg.storage_direct)) -> Cow<'static, str>]
@@ -135,11 +138,21 @@ fn tokens_trait_impl(
 				}
 			};
 			
+			let once_key = &option.once_key;
+
+			let availability =
+				match (once_key, &option.if_condition) {
+					(Some(key), Some(condition)) => Some(quote!(!storage.once_seen($(quoted(key))) && $condition)),
+					(Some(key), None) => Some(quote!(!storage.once_seen($(quoted(key))))),
+					(None, Some(condition)) => Some(quote!($condition)),
+					(None, None) => None,
+				};
+
 			let is_available_impl = 
-				if let Some(condition) = &option.if_condition {
+				if let Some(availability) = availability {
 					quote! {
 						fn is_available(&self, storage: &$(&cfg.storage_direct)) -> Option<bool> {
-							Some($condition)
+							Some($availability)
 						}
 					}
 				} else {
@@ -158,8 +171,14 @@ fn tokens_trait_impl(
 				}
 			};
 			
+			let mark_once = match once_key {
+				Some(key) => quote!(storage.mark_once_seen($(quoted(key)));),
+				None => Tokens::new(),
+			};
+
 			let advance_impl = quote! {
 				fn advance(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield {
+					$mark_once
 					$advance_fn
 				}
 			};
@@ -167,6 +186,7 @@ fn tokens_trait_impl(
 			quote! {
 				impl IOptionLine for $(line_enum.variant_name()) {
 					$line_id_impl
+					$(source_location_fn(cfg, &option.span))
 					$tags_impl
 					$text_impl
 					$is_available_impl
@@ -241,6 +261,13 @@ fn insert_scope_advance_fns<'a>(
 					}
 				}
 			}
+			IDFlow::LineGroup(line_group) => {
+				for (_, maybe_scope) in line_group.lines.iter() {
+					if let Some(line_scope) = maybe_scope {
+						insert_scope_advance_fns(next_fns, line_scope, next_scopes, title);
+					}
+				}
+			}
 			IDFlow::IfBranch(if_branch) => {
 				if let Some(if_scope) = &if_branch.if_.1 {
 					insert_scope_advance_fns(next_fns, if_scope, next_scopes, title);
@@ -256,6 +283,15 @@ fn insert_scope_advance_fns<'a>(
 					insert_scope_advance_fns(next_fns, else_scope, next_scopes, title);
 				}
 			}
+			IDFlow::OnceBranch(once_branch) => {
+				if let Some(once_scope) = &once_branch.once_.1 {
+					insert_scope_advance_fns(next_fns, once_scope, next_scopes, title);
+				}
+
+				if let Some((_, Some(else_scope))) = &once_branch.else_ {
+					insert_scope_advance_fns(next_fns, else_scope, next_scopes, title);
+				}
+			}
 			IDFlow::Flat(_) => {}
 		}
 	}
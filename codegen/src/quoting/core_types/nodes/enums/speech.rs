@@ -5,7 +5,7 @@ use crate::quoting::quotable_types::enums::LineEnum;
 use crate::quoting::quotable_types::line_ids::{IDFlatLine, IDFlow, IDSpeech};
 use crate::quoting::quotable_types::node::{IDNode, LinesMap};
 use crate::quoting::quotable_types::scope::IDScope;
-use crate::quoting::util::SeparatedItems;
+use crate::quoting::util::{source_comments, source_location_fn, SeparatedItems};
 use genco::prelude::quoted;
 use genco::prelude::rust::Tokens;
 use genco::quote;
@@ -22,7 +22,7 @@ pub fn all_tokens(
 	let enum_name = enums::enum_type_speech(&node.metadata.title);
 
 	let tokens_imports = tokens_imports(cfg);
-	let tokens_enum = tokens_enum(cfg, &lines_map.speeches, &enum_name);
+	let tokens_enum = tokens_enum(cfg, node, &lines_map.speeches, &enum_name);
 	let tokens_trait_impl = tokens_trait_impl(cfg, &lines_map.speeches, node);
 
 	Some(quote! {
@@ -47,6 +47,7 @@ fn tokens_imports(cfg: &YarnConfig) -> Tokens {
 
 fn tokens_enum(
 	cfg: &YarnConfig,
+	node: &IDNode,
 	speeches: &[(&IDSpeech, LineEnum)],
 	enum_name: &str,
 ) -> Tokens {
@@ -56,10 +57,11 @@ fn tokens_enum(
 
 	let structs = speeches
 		.iter()
-		.map(|(_, line_enum)| {
+		.map(|(speech, line_enum)| {
 			let name = line_enum.variant_name();
 
 			quote! {
+				$(source_comments(cfg, node, &speech.span))
 				#[derive(Debug, Copy, Clone)]
 				#[derive(PartialEq, Eq, Hash)]
 				#[derive(Serialize, Deserialize)]
@@ -80,6 +82,7 @@ fn tokens_enum(
 			DELEGATES: {
 				impl trait ISpeechLine {
 					[fn line_id(&self) -> &'static str]
+					[fn source_location(&self) -> (&'static str, u32)]
 					[fn tags(&self) -> &'static [&'static str]]
 					[fn speaker(&self, storage: &$(&cfg.storage_direct)) -> Option<Cow<'static, str>>]
 					[fn text(&self, storage: &$(&cfg.storage_direct)) -> Cow<'static, str>]
@@ -157,6 +160,7 @@ fn tokens_trait_impl(
 			quote! {
 				impl ISpeechLine for $(line_enum.variant_name()) {
 					$line_id_impl
+					$(source_location_fn(cfg, &speech.span))
 					$tags_impl
 					$speaker_impl
 					$text_impl
@@ -238,6 +242,24 @@ fn insert_scope_advance_fns<'a>(
 					}
 				}
 			}
+			IDFlow::LineGroup(line_group) => {
+				for (line, maybe_scope) in line_group.lines.iter() {
+					let scope_flows = maybe_scope.iter().flat_map(|scope| scope.flows.iter());
+
+					let next_fn = build_next_fn(
+						&[],
+						scope_flows.chain(flows.iter().copied()),
+						next_scopes.iter().copied(),
+						title,
+					);
+
+					next_fns.push((line.speech.line_id.as_str(), next_fn));
+
+					if let Some(line_scope) = maybe_scope {
+						insert_scope_advance_fns(next_fns, line_scope, next_scopes, title);
+					}
+				}
+			}
 			IDFlow::IfBranch(if_branch) => {
 				if let Some(if_scope) = &if_branch.if_.1 {
 					insert_scope_advance_fns(next_fns, if_scope, next_scopes, title);
@@ -253,6 +275,15 @@ fn insert_scope_advance_fns<'a>(
 					insert_scope_advance_fns(next_fns, else_scope, next_scopes, title);
 				}
 			}
+			IDFlow::OnceBranch(once_branch) => {
+				if let Some(once_scope) = &once_branch.once_.1 {
+					insert_scope_advance_fns(next_fns, once_scope, next_scopes, title);
+				}
+
+				if let Some((_, Some(else_scope))) = &once_branch.else_ {
+					insert_scope_advance_fns(next_fns, else_scope, next_scopes, title);
+				}
+			}
 		}
 	}
 }
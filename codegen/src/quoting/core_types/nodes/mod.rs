@@ -6,15 +6,18 @@ use genco::quote;
 use crate::quoting::util::SeparatedItems;
 use crate::quoting::quotable_types::enums::{
 	enum_type_command, 
+	enum_type_detour,
 	enum_type_option_line, 
 	enum_type_options_fork, 
 	enum_type_speech
 };
-use crate::quoting::quotable_types::node::{IDNode, LinesMap};
+use crate::quoting::quotable_types::node::{node_groups, IDNode, LinesMap};
 
 pub fn tokens_all_nodes_root(nodes: &[IDNode]) -> Tokens {
-	let module_declarations = nodes.iter().map(|node| quote! { mod $(&node.metadata.title); });
-	let module_exports = nodes.iter().map(|node| quote! { pub use super::$(&node.metadata.title)::*; });
+	let groups = node_groups(nodes);
+	let titles = || nodes.iter().map(|node| node.metadata.title.as_str()).chain(groups.iter().map(|group| group.title));
+	let module_declarations = titles().map(|title| quote! { mod $title; });
+	let module_exports = titles().map(|title| quote! { pub use super::$title::*; });
 	
 	quote! {
 		#![allow(non_camel_case_types)]
@@ -29,6 +32,14 @@ pub fn tokens_all_nodes_root(nodes: &[IDNode]) -> Tokens {
 	}
 }
 
+/// A node group only has a title, the lines are in its members' modules.
+pub fn tokens_group_root(group_title: &str) -> Tokens {
+	quote! {
+		mod title;
+		pub use title::$group_title;
+	}
+}
+
 pub fn tokens_node_root(node: &IDNode, lines_map: &LinesMap) -> Tokens {
 	let node_title = node.metadata.title.as_str();
 
@@ -91,6 +102,17 @@ pub fn tokens_node_root(node: &IDNode, lines_map: &LinesMap) -> Tokens {
 			Tokens::new()
 		};
 
+	let detour_enum_tokens =
+		if !lines_map.detours.is_empty() {
+			let detour_enum = enum_type_detour(&node.metadata.title);
+			quote! { 
+				mod enum_detour;
+				pub use enum_detour::$detour_enum;
+			}
+		} else {
+			Tokens::new()
+		};
+
 	quote! {
 		mod title;
 		pub use title::$node_title;
@@ -99,6 +121,7 @@ pub fn tokens_node_root(node: &IDNode, lines_map: &LinesMap) -> Tokens {
 		$command_enum_tokens
 		$options_fork_enum_tokens
 		$option_line_enum_tokens
+		$detour_enum_tokens
 		//$any_enum_tokens
 	}
 }
\ No newline at end of file
@@ -1,12 +1,15 @@
 use crate::config::YarnConfig;
 use crate::expressions::built_in_calls::BuiltInFunctionCall;
 use crate::expressions::yarn_expr::YarnExpr;
-use crate::parsing::raw::node_metadata::TrackingSetting;
+use crate::config::NodeGroupSaliency;
+use crate::parsing::raw::node_metadata::{TrackingSetting, WhenCondition};
 use crate::quoting::quotable_types;
-use crate::quoting::quotable_types::node::IDNode;
+use crate::quoting::quotable_types::enums;
+use crate::quoting::quotable_types::node::{IDNode, IDNodeGroup};
 use crate::quoting::quotable_types::scope::IDScope;
 use crate::quoting::util::SeparatedItems;
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use crate::diagnostic::{Diagnostic, ErrorCode};
 use genco::lang::rust::Tokens;
 use genco::prelude::quoted;
 use genco::quote;
@@ -56,6 +59,19 @@ fn tokens_title_trait_impl(
 		&node.metadata.title,
 	);
 
+	let mark_once =
+		if metadata.when.iter().any(|(_, when)| *when == WhenCondition::Once) {
+			quote!(storage.mark_once_seen($(quoted(enums::when_once_key(node.group.as_deref().unwrap_or(title), &metadata.when))));)
+		} else {
+			Tokens::new()
+		};
+
+	let group =
+		match &node.group {
+			Some(group_title) => quote!(Some(NodeTitle::$group_title)),
+			None => quote!(None),
+		};
+
 	quote! {
 		#[derive(Debug, Copy, Clone)]
 		#[derive(PartialEq, Eq, Hash)]
@@ -77,13 +93,86 @@ fn tokens_title_trait_impl(
 				] 
 			}
 			
+			fn group(&self) -> Option<NodeTitle> { $group }
+			
 			fn start(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield { 
+				$mark_once
 				$tokens_first_line
 			}
 		}
 	}
 }
 
+pub fn group_tokens(
+	cfg: &YarnConfig,
+	group: &IDNodeGroup,
+	tracking: TrackingSetting,
+) -> Tokens {
+	let imports = tokens_imports(cfg);
+	let title = group.title;
+
+	let candidates = group.members.iter().map(|member| {
+		let member_title = &member.metadata.title;
+		let once_key = enums::when_once_key(title, &member.metadata.when);
+		let conditions =
+			member.metadata
+			      .when
+			      .iter()
+			      .filter_map(|(_, when)| match when {
+				      WhenCondition::Always => None,
+				      WhenCondition::Once => Some(quote!(!storage.once_seen($(quoted(&once_key))))),
+				      WhenCondition::Condition(condition) => Some(quote!(($condition))),
+			      })
+			      .collect::<Vec<_>>();
+
+		if conditions.is_empty() {
+			quote!(Some(NodeTitle::$member_title))
+		} else {
+			quote!(if $(SeparatedItems(conditions, " && ")) { Some(NodeTitle::$member_title) } else { None })
+		}
+	});
+
+	let mark_seen =
+		if cfg.node_group_saliency == NodeGroupSaliency::LeastRecentlySeen {
+			quote!(storage.mark_node_seen(chosen);)
+		} else {
+			Tokens::new()
+		};
+
+	quote! {
+		$imports
+		
+		#[derive(Debug, Copy, Clone)]
+		#[derive(PartialEq, Eq, Hash)]
+		#[derive(Serialize, Deserialize)]
+		pub struct $title;
+		
+		impl INodeTitle for $title {
+			fn tags(&self) -> &'static [&'static str] { &[] }
+			
+			fn tracking(&self) -> TrackingSetting { $tracking }
+			
+			fn custom_metadata(&self) -> &'static [&'static str] { &[] }
+			
+			fn group(&self) -> Option<NodeTitle> { None }
+			
+			fn start(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield {
+				let candidates: Vec<NodeTitle> = [
+					$(SeparatedItems(candidates, ",\n"))
+				].into_iter().flatten().collect();
+				
+				if candidates.is_empty() {
+					return finish_node(storage, NodeTitle::$title);
+				}
+				
+				let chosen = candidates[select_node(storage, &candidates)];
+				$mark_seen
+				chosen.start(storage)
+			}
+		}
+	}
+}
+
 fn node_names_in_args(node: &IDNode) -> impl Iterator<Item = &str> {
 	node.scopes
 	    .iter()
@@ -104,7 +193,7 @@ pub fn infer_all_nodes_tracking(nodes: &[IDNode]) -> Result<Vec<(&IDNode, Tracki
 	let nodes_to_track: HashSet<&str> = {
 		let node_names_in_visited_calls: HashSet<&str> = nodes.iter().flat_map(node_names_in_args).collect();
 
-		let titles_in_files: HashSet<&str> = nodes.iter().map(|node| node.metadata.title.as_str()).collect();
+		let titles_in_files: HashSet<&str> = nodes.iter().map(IDNode::jump_title).collect();
 
 		let used_nodes_that_dont_exist = node_names_in_visited_calls
 			.iter()
@@ -117,11 +206,11 @@ pub fn infer_all_nodes_tracking(nodes: &[IDNode]) -> Result<Vec<(&IDNode, Tracki
 			.collect::<Vec<&str>>();
 
 		if !used_nodes_that_dont_exist.is_empty() {
-			return Err(anyhow!(
-				"Found node names in `visited([name])` or `visited_count([name])` that do not exist in any of the provided files.\n\
-				 Node names: {}\n\
-				 Please make sure that the node names are correct."
-				, used_nodes_that_dont_exist.join(", ")));
+			return Err(Diagnostic::error(ErrorCode::UnknownVisitedNode,
+				"Found node names in `visited([name])` or `visited_count([name])` that do not exist in any of the provided files.")
+				.with_note(format!("Node names: {}", used_nodes_that_dont_exist.join(", ")))
+				.with_help("Please make sure that the node names are correct.")
+				.into());
 		}
 
 		node_names_in_visited_calls
@@ -132,7 +221,7 @@ pub fn infer_all_nodes_tracking(nodes: &[IDNode]) -> Result<Vec<(&IDNode, Tracki
 			.metadata
 			.tracking
 			.unwrap_or_else(||
-				if nodes_to_track.contains(node.metadata.title.as_str()) {
+				if nodes_to_track.contains(node.jump_title()) {
 					TrackingSetting::Always
 				} else {
 					TrackingSetting::Never
@@ -141,3 +230,88 @@ pub fn infer_all_nodes_tracking(nodes: &[IDNode]) -> Result<Vec<(&IDNode, Tracki
 		(node, inferred)
 	}).collect())
 }
+
+/// A group is tracked if any of its members is.
+pub fn infer_group_tracking(group: &IDNodeGroup, inferred_tracking: &[(&IDNode, TrackingSetting)]) -> TrackingSetting {
+	let any_tracked =
+		inferred_tracking
+			.iter()
+			.any(|(node, tracking)| node.group.as_deref() == Some(group.title) && *tracking == TrackingSetting::Always);
+
+	if any_tracked {
+		TrackingSetting::Always
+	} else {
+		TrackingSetting::Never
+	}
+}
+
+#[test]
+fn test_group_tokens() {
+	use crate::quoting::quotable_types::node::node_groups;
+	use crate::quoting::test_util::{parse_id_nodes, render};
+
+	let nodes = parse_id_nodes(
+		"title: Chat\n\
+		 when: once\n\
+		 ---\n\
+		 First time\n\
+		 ===\n\
+		 title: Chat\n\
+		 when: $gold > 5\n\
+		 when: once\n\
+		 ---\n\
+		 Rich\n\
+		 ===\n\
+		 title: Chat\n\
+		 when: always\n\
+		 ---\n\
+		 Again\n\
+		 ===");
+
+	let mut cfg = YarnConfig::for_tests();
+	let groups = node_groups(&nodes);
+	let code = render(group_tokens(&cfg, &groups[0], TrackingSetting::Always));
+
+	assert!(code.contains(
+		"        let candidates: Vec<NodeTitle> = [\n\
+		 \x20           if !storage.once_seen(\"Chat.when_2be0b0feb26a44e4\") { Some(NodeTitle::Chat__1) } else { None },\n\
+		 \x20           if (storage.get_var::<gold>() > 5) && !storage.once_seen(\"Chat.when_c1e81f25632fa363\") { Some(NodeTitle::Chat__2) } else { None },\n\
+		 \x20           Some(NodeTitle::Chat__3)\n\
+		 \x20       ].into_iter().flatten().collect();\n\
+		 \n\
+		 \x20       if candidates.is_empty() {\n\
+		 \x20           return finish_node(storage, NodeTitle::Chat);\n\
+		 \x20       }\n\
+		 \n\
+		 \x20       let chosen = candidates[select_node(storage, &candidates)];\n\
+		 \x20       chosen.start(storage)\n"), "{code}");
+
+	// Visits are counted when the chosen member ends.
+	assert!(!code.contains("increment_visited"), "{code}");
+
+	cfg.node_group_saliency = NodeGroupSaliency::LeastRecentlySeen;
+	let code = render(group_tokens(&cfg, &groups[0], TrackingSetting::Always));
+	assert!(code.contains("        storage.mark_node_seen(chosen);\n        chosen.start(storage)\n"), "{code}");
+
+	// Members with `when: once` are marked as seen when they start.
+	let code = render(all_tokens(&cfg, &nodes[1], TrackingSetting::Always));
+	assert!(code.contains("    fn group(&self) -> Option<NodeTitle> { Some(NodeTitle::Chat) }\n"), "{code}");
+	assert!(code.contains("        storage.mark_once_seen(\"Chat.when_c1e81f25632fa363\");\n"), "{code}");
+
+	// The keys don't depend on the members' order.
+	let nodes = parse_id_nodes(
+		"title: Chat\n\
+		 when: $gold > 5\n\
+		 when: once\n\
+		 ---\n\
+		 Rich\n\
+		 ===\n\
+		 title: Chat\n\
+		 when: always\n\
+		 ---\n\
+		 Again\n\
+		 ===");
+
+	let code = render(all_tokens(&cfg, &nodes[0], TrackingSetting::Always));
+	assert!(code.contains("        storage.mark_once_seen(\"Chat.when_c1e81f25632fa363\");\n"), "{code}");
+}
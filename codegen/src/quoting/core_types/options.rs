@@ -38,6 +38,12 @@ fn tokens_traits_and_imports(cfg: &YarnConfig) -> Tokens {
 			#[must_use]
 			fn line_id(&self) -> &'static str;
 
+			$(Comments([
+				r#"The yarn file and line number this line was written at, for debugging."#,
+				r#"The path is relative to the folder of the config file that generated this code."#]))
+			#[must_use]
+			fn source_location(&self) -> (&'static str, u32);
+
 			$(Comments([
 				r#"The list of tags this line has, if any."#,
 				r#"Each element contains everything between two hashtags (`#` ~ `#`) or (# ~ end of line)."#,
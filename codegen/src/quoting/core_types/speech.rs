@@ -1,4 +1,4 @@
-use crate::config::YarnConfig;
+use crate::config::{LineGroupSelection, YarnConfig};
 use crate::quoting::quotable_types::enums::SUFFIX_SPEECH;
 use crate::quoting::quotable_types::node::{IDNode, LinesMap};
 use crate::quoting::util::{Comments, SeparatedItems};
@@ -8,10 +8,12 @@ use genco::quote;
 pub fn all_tokens(cfg: &YarnConfig, nodes_mapped: &[(&IDNode, LinesMap)]) -> Tokens {
 	let imports_and_trait = tokens_imports_and_trait(cfg);
 	let enum_tokens = tokens_enum(cfg, nodes_mapped);
+	let select_line_tokens = tokens_select_line(cfg);
 
 	quote! {
 		$imports_and_trait
 		$enum_tokens
+		$select_line_tokens
 	}
 }
 
@@ -32,6 +34,12 @@ fn tokens_imports_and_trait(cfg: &YarnConfig) -> Tokens {
 			#[must_use]
 			fn line_id(&self) -> &'static str;
 		
+			$(Comments([
+				r#"The yarn file and line number this line was written at, for debugging."#,
+				r#"The path is relative to the folder of the config file that generated this code."#]))
+			#[must_use]
+			fn source_location(&self) -> (&'static str, u32);
+		
 			$(Comments([
 				r#"The list of tags this line has, if any."#,
 				r#"Each element contains everything between two hashtags (`#` ~ `#`) or (# ~ end of line)."#,
@@ -110,6 +118,7 @@ fn tokens_enum(
 				impl trait ISpeechLine {
 					[fn advance(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield]
 					[fn line_id(&self) -> &'static str]
+					[fn source_location(&self) -> (&'static str, u32)]
 					[fn tags(&self) -> &'static [&'static str]]
 					[fn speaker(&self, storage: &$(&cfg.storage_direct)) -> Option<Cow<'static, str>>]
 					[fn text(&self, storage: &$(&cfg.storage_direct)) -> Cow<'static, str>]
@@ -118,3 +127,21 @@ fn tokens_enum(
 		}
 	}
 }
+
+fn tokens_select_line(cfg: &YarnConfig) -> Tokens {
+	let body =
+		match cfg.line_group_selection {
+			LineGroupSelection::Random => quote!(storage.dice(candidates.len()) - 1),
+			LineGroupSelection::First => quote!(0),
+			LineGroupSelection::Custom => quote!(storage.select_line(candidates)),
+		};
+
+	quote! {
+		$(Comments([
+			r#"Picks which line of a `=> line` group is shown, `candidates` are the lines whose condition passed."#,
+			r#"Never called with an empty list."#]))
+		pub fn select_line(storage: &mut $(&cfg.storage_direct), candidates: &[SpeechLine]) -> usize {
+			$body
+		}
+	}
+}
@@ -1,5 +1,5 @@
-use crate::config::YarnConfig;
-use crate::quoting::quotable_types::node::IDNode;
+use crate::config::{NodeGroupSaliency, YarnConfig};
+use crate::quoting::quotable_types::node::{node_groups, IDNode};
 use crate::quoting::util::{Comments, SeparatedItems};
 use genco::lang::rust::Tokens;
 use genco::quote;
@@ -7,10 +7,14 @@ use genco::quote;
 pub fn all_tokens(cfg: &YarnConfig, nodes: &[IDNode]) -> Tokens {
 	let imports_and_trait = tokens_imports_and_trait(cfg);
 	let enum_tokens = tokens_enum(cfg, nodes);
+	let select_node_tokens = tokens_select_node(cfg);
+	let count_visit_tokens = tokens_count_visit(cfg);
 
 	quote! {
 		$imports_and_trait
 		$enum_tokens
+		$select_node_tokens
+		$count_visit_tokens
 	}
 }
 
@@ -31,6 +35,9 @@ fn tokens_imports_and_trait(cfg: &YarnConfig) -> Tokens {
 			fn tracking(&self) -> TrackingSetting;
 			#[must_use]
 			fn custom_metadata(&self) -> &'static[&'static str];
+			$(Comments(["The node group this node is a member of, if it has a `when:` header."]))
+			#[must_use]
+			fn group(&self) -> Option<NodeTitle>;
 			#[must_use]
 			fn start(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield;
 		}
@@ -47,9 +54,12 @@ fn tokens_imports_and_trait(cfg: &YarnConfig) -> Tokens {
 }
 
 fn tokens_enum(cfg: &YarnConfig, nodes: &[IDNode]) -> Tokens {
+	let groups = node_groups(nodes);
 	let variants = nodes
 		.iter()
-		.map(|node| quote! { $(&node.metadata.title) });
+		.map(|node| node.metadata.title.as_str())
+		.chain(groups.iter().map(|group| group.title))
+		.map(|title| quote! { $title });
 
 	quote! {
 		declarative_type_state::unit_enum_delegated! {
@@ -67,9 +77,78 @@ fn tokens_enum(cfg: &YarnConfig, nodes: &[IDNode]) -> Tokens {
 					[fn tags(&self) -> &'static [&'static str]]
 					[fn tracking(&self) -> TrackingSetting]
 					[fn custom_metadata(&self) -> &'static [&'static str]]
+					[fn group(&self) -> Option<NodeTitle>]
 					[fn start(&self, storage: &mut $(&cfg.storage_direct)) -> YarnYield]
 				}
 			}
 		}
 	}
 }
+
+fn tokens_select_node(cfg: &YarnConfig) -> Tokens {
+	let body =
+		match cfg.node_group_saliency {
+			NodeGroupSaliency::First => quote!(0),
+			NodeGroupSaliency::Random => quote!(storage.dice(candidates.len()) - 1),
+			NodeGroupSaliency::LeastRecentlySeen => quote! {
+				candidates
+					.iter()
+					.enumerate()
+					.min_by_key(|(_, title)| storage.last_seen(**title))
+					.map(|(index, _)| index)
+					.unwrap_or(0)
+			},
+			NodeGroupSaliency::Custom => quote!(storage.select_node(candidates)),
+		};
+
+	quote! {
+		$(Comments([
+			r#"Picks which node of a node group is started, `candidates` are the nodes whose `when:` headers passed."#,
+			r#"Never called with an empty list."#]))
+		pub fn select_node(storage: &mut $(&cfg.storage_direct), candidates: &[NodeTitle]) -> usize {
+			$body
+		}
+	}
+}
+
+fn tokens_count_visit(cfg: &YarnConfig) -> Tokens {
+	quote! {
+		$(Comments([
+			r#"Counts a visit to `node_title`, and to its node group, when they're tracked."#,
+			r#"Called when a node ends, by `<<jump>>` or [finish_node]."#]))
+		pub fn count_visit(storage: &mut $(&cfg.storage_direct), node_title: NodeTitle) {
+			for title in [Some(node_title), node_title.group()].into_iter().flatten() {
+				if title.tracking() == TrackingSetting::Always {
+					storage.increment_visited(title);
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn test_select_node() {
+	use crate::quoting::test_util::render;
+	use pretty_assertions::assert_eq;
+
+	let body = |saliency| {
+		let mut cfg = YarnConfig::for_tests();
+		cfg.node_group_saliency = saliency;
+
+		let code = render(tokens_select_node(&cfg));
+		let start = code.find("-> usize {\n").unwrap() + "-> usize {\n".len();
+		let end = code.rfind("\n}").unwrap();
+		code[start..end].to_string()
+	};
+
+	assert_eq!(body(NodeGroupSaliency::First), "    0");
+	assert_eq!(body(NodeGroupSaliency::Random), "    storage.dice(candidates.len()) - 1");
+	assert_eq!(body(NodeGroupSaliency::LeastRecentlySeen),
+		"    candidates\n\
+		 \x20       .iter()\n\
+		 \x20       .enumerate()\n\
+		 \x20       .min_by_key(|(_, title)| storage.last_seen(**title))\n\
+		 \x20       .map(|(index, _)| index)\n\
+		 \x20       .unwrap_or(0)");
+	assert_eq!(body(NodeGroupSaliency::Custom), "    storage.select_node(candidates)");
+}
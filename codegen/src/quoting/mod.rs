@@ -2,3 +2,6 @@ pub mod core_types;
 pub mod util;
 pub mod quotable_types;
 
+
+#[cfg(test)]
+pub mod test_util;
@@ -1,10 +1,12 @@
 use genco::lang::rust::Tokens;
-use genco::quote_in;
+use genco::prelude::quoted;
+use genco::{quote, quote_in};
 use crate::expressions::yarn_expr::YarnExpr;
 use crate::parsing::raw::command::SetOperation;
-use crate::quoting::quotable_types::enums::LineEnum;
+use crate::quoting::quotable_types::enums::{DetourEnum, LineEnum};
 use crate::quoting::quotable_types::line_ids::*;
 use crate::quoting::quotable_types::scope::IDScope;
+use crate::quoting::util::SeparatedItems;
 
 trait NextFn {
 	fn quote_next_fn(self, tokens: &mut Tokens, node_title: &str) -> bool;
@@ -47,7 +49,7 @@ impl NextFn for &IDFlatLine {
 				match built_in_command {
 					BuiltInCommand::Set { 
 						var_name, op, value,
-						line_number: _, 
+						span: _, 
 					} => {
 						if !tokens.is_empty() {
 							tokens.push();
@@ -87,24 +89,44 @@ impl NextFn for &IDFlatLine {
 						}
 						
 						quote_in!(*tokens =>
-							match $node_title.tracking() {
-								TrackingSetting::Always => {
-									storage.increment_visited(NodeTitle::$node_title);
-								},
-								TrackingSetting::Never => {},
-							}
-										
+							count_visit(storage, NodeTitle::$node_title);
 							return $node_destination_title.start(storage);
 						);
 
 						true
 					},
+					BuiltInCommand::Detour { node_destination_title, virtual_id, .. } => {
+						if !tokens.is_empty() {
+							tokens.push();
+						}
+						
+						let detour_enum = DetourEnum { node_title, raw_id: virtual_id };
+						
+						quote_in!(*tokens =>
+							storage.push_detour($(detour_enum.qualified()).into());
+							return $node_destination_title.start(storage);
+						);
+						
+						true
+					},
+					BuiltInCommand::Return { .. } => {
+						if !tokens.is_empty() {
+							tokens.push();
+						}
+						
+						quote_in!(*tokens => return finish_node(storage, NodeTitle::$node_title););
+						true
+					},
 					BuiltInCommand::Stop { .. } => {
 						if !tokens.is_empty() {
 							tokens.push();
 						}
 						
-						quote_in!(*tokens => return YarnYield::Finished;);
+						quote_in!(*tokens =>
+							storage.clear_detours();
+							return YarnYield::Finished;
+						);
+						
 						true
 					},
 				}
@@ -130,6 +152,54 @@ impl NextFn for &IDOptionsFork {
 	}
 }
 
+impl NextFn for &IDLineGroup {
+	/// Only returns for sure if at least one of the lines has no condition.
+	fn quote_next_fn(self, tokens: &mut Tokens, node_title: &str) -> bool {
+		if !tokens.is_empty() {
+			tokens.line();
+		}
+
+		let candidates = self.lines.iter().map(|(line, _)| {
+			let line_enum = LineEnum {
+				node_title,
+				raw_id: line.speech.line_id.as_str(),
+				instruction_kind: InstructionKind::Speech
+			};
+
+			match &line.condition {
+				Some(condition) => quote!(if $condition { Some($(line_enum.typed_qualified()).into()) } else { None }),
+				None => quote!(Some($(line_enum.typed_qualified()).into())),
+			}
+		});
+
+		let always_returns = self.lines.iter().any(|(line, _)| line.condition.is_none());
+
+		let pick = quote! {
+			let candidates: Vec<SpeechLine> = [
+				$(SeparatedItems(candidates, ",\n"))
+			].into_iter().flatten().collect();
+		};
+
+		if always_returns {
+			quote_in!(*tokens =>
+				$pick
+				return YarnYield::Instruction(Instruction::Speech(candidates[select_line(storage, &candidates)]));
+			);
+		} else {
+			quote_in!(*tokens =>
+				{
+					$pick
+					if !candidates.is_empty() {
+						return YarnYield::Instruction(Instruction::Speech(candidates[select_line(storage, &candidates)]));
+					}
+				}
+			);
+		}
+
+		always_returns
+	}
+}
+
 impl NextFn for &IDIfBranch {
 	fn quote_next_fn(self, tokens: &mut Tokens, node_title: &str) -> bool {
 		if !tokens.is_empty() { 
@@ -207,6 +277,55 @@ impl NextFn for &IDIfBranch {
 	}
 }
 
+impl NextFn for &IDOnceBranch {
+	fn quote_next_fn(self, tokens: &mut Tokens, node_title: &str) -> bool {
+		if !tokens.is_empty() {
+			tokens.line();
+		}
+
+		let key = &self.once_key;
+
+		let condition =
+			match &self.once_.0.condition {
+				Some(condition) => quote!(!storage.once_seen($(quoted(key))) && $condition),
+				None => quote!(!storage.once_seen($(quoted(key)))),
+			};
+
+		let mut once_tokens = quote!(storage.mark_once_seen($(quoted(key))););
+
+		let mut all_returned =
+			match &self.once_.1 {
+				Some(scope) => scope.quote_next_fn(&mut once_tokens, node_title),
+				None => false,
+			};
+
+		quote_in!(*tokens =>
+			if $condition {
+				$once_tokens
+			}
+		);
+
+		match &self.else_ {
+			Some((_, Some(scope))) => {
+				let mut else_tokens = Tokens::new();
+				all_returned &= scope.quote_next_fn(&mut else_tokens, node_title);
+
+				tokens.append(" ");
+				quote_in!(*tokens =>
+					else {
+						$else_tokens
+					}
+				);
+			},
+			Some((_, None)) | None => {
+				all_returned = false;
+			},
+		}
+
+		all_returned
+	}
+}
+
 fn flows_next_fn<'a>(flows: impl IntoIterator<Item = &'a IDFlow>, tokens: &mut Tokens, node_title: &str) -> bool {
 	for flow in flows {
 		match flow {
@@ -222,11 +341,21 @@ fn flows_next_fn<'a>(flows: impl IntoIterator<Item = &'a IDFlow>, tokens: &mut T
 					return true;
 				}
 			},
+			IDFlow::LineGroup(line_group) => {
+				if line_group.quote_next_fn(tokens, node_title) {
+					return true;
+				}
+			},
 			IDFlow::IfBranch(if_branch) => {
 				if if_branch.quote_next_fn(tokens, node_title) {
 					return true;
 				}
 			},
+			IDFlow::OnceBranch(once_branch) => {
+				if once_branch.quote_next_fn(tokens, node_title) {
+					return true;
+				}
+			},
 		}
 	}
 	
@@ -267,7 +396,7 @@ pub fn build_next_fn<'a>(flats_after: impl IntoIterator<Item = &'a IDFlatLine>,
 	}
 	
 	quote_in!(tokens => 
-		return YarnYield::Finished;
+		return finish_node(storage, NodeTitle::$node_title);
 	);
 	
 	tokens
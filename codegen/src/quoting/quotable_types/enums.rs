@@ -1,3 +1,5 @@
+use crate::LineNumber;
+use crate::parsing::raw::node_metadata::WhenCondition;
 use crate::quoting::quotable_types::line_ids::InstructionKind;
 
 //pub const SUFFIX_ANY: &str = "_Line_Any";
@@ -5,6 +7,7 @@ pub const SUFFIX_SPEECH: &str = "_Line_Speech";
 pub const SUFFIX_COMMAND: &str = "_Line_Command";
 pub const SUFFIX_OPTIONS_FORK: &str = "_VirtualLine_OptionsFork";
 pub const SUFFIX_OPTION_LINE: &str = "_Line_Option";
+pub const SUFFIX_DETOUR: &str = "_Detour";
 
 pub struct LineEnum<'a> {
 	pub node_title: &'a str,
@@ -69,6 +72,51 @@ impl<'a> OptionLineEnum<'a> {
 	}
 }
 
+/// The line after a `<<detour>>`, where the node resumes once the detour ends.
+///
+/// Variants are named by the detour's ordinal in the node(`D1`, `D2`, ...),
+/// a saved detour stack stays valid when other nodes are edited.
+pub struct DetourEnum<'a> {
+	pub node_title: &'a str,
+	pub raw_id: &'a str,
+}
+
+pub fn enum_type_detour(node_title: &str) -> String {
+	format!("{}_Detour", node_title)
+}
+
+impl<'a> DetourEnum<'a> {
+	pub fn qualified(&self) -> String {
+		format!("{}::{}", enum_type_detour(self.node_title), self.variant_name())
+	}
+
+	pub fn variant_name(&self) -> String {
+		self.raw_id.to_string()
+	}
+}
+
+/// Key of a `<<once>>` block or option in the storage's `once` flags.
+pub fn once_key(node_title: &str, raw_id: &str) -> String {
+	format!("{node_title}.{raw_id}")
+}
+
+/// Key of a `when: once` group member in the storage's `once` flags.
+///
+/// Members are renamed by their order in the group, so the key hashes the member's `when:` conditions instead.
+/// Members of the same group with the same conditions share the flag,
+/// and editing a member's conditions resets its flag.
+pub fn when_once_key(group_title: &str, when: &[(LineNumber, WhenCondition)]) -> String {
+	// The key is saved, it must not depend on the compiler version like `DefaultHasher` does (FNV-1a).
+	let conditions = when.iter().map(|(_, condition)| condition).collect::<Vec<_>>();
+	let hash =
+		serde_json::to_vec(&conditions)
+			.expect("`when:` conditions are serializable")
+			.into_iter()
+			.fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+
+	format!("{group_title}.when_{hash:016x}")
+}
+
 pub fn enum_type_title(node_title: &str) -> String {
 	format!("NodeTitle::{node_title}")
 }
\ No newline at end of file
@@ -1,25 +1,29 @@
 use crate::expressions::yarn_expr::YarnExpr;
-use crate::parsing::grouping::options::OptionsFork;
 use crate::parsing::grouping::scope::{FlatLine, Flow, YarnScope};
 use crate::parsing::raw::branches::if_statement::{ElseIf_, Else_, If_};
+use crate::parsing::raw::branches::once_statement::Once_;
 use crate::parsing::raw::command::{CommandVariant, SetOperation};
 use crate::parsing::raw::speech::{Speaker, Speech};
 use crate::parsing::YarnNode;
+use crate::quoting::quotable_types::enums;
 use crate::quoting::quotable_types::enums::LineEnum;
 use crate::quoting::quotable_types::node::IDNode;
 use crate::quoting::quotable_types::scope::IDScope;
-use crate::LineNumber;
-use anyhow::{anyhow, Result};
+use crate::diagnostic::Span;
+use anyhow::Result;
 use houtamelo_utils::prelude::CountOrMore;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IDOptionLine {
-	pub line_number: LineNumber,
+	pub span: Span,
 	pub line_id: String,
 	pub text: (String, Vec<YarnExpr>),
 	pub if_condition: Option<YarnExpr>,
+	/// `Some` for `<<once>>` options, their key in the storage's `once` flags.
+	pub once_key: Option<String>,
 	pub tags: Vec<String>,
 	pub fork_qualified: Rc<str>,
 	pub index_on_fork: usize,
@@ -31,11 +35,24 @@ pub struct IDOptionsFork {
 	pub options: CountOrMore<1, (IDOptionLine, Option<Box<IDScope>>)>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IDGroupLine {
+	pub speech: IDSpeech,
+	pub condition: Option<YarnExpr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IDLineGroup {
+	pub lines: CountOrMore<1, (IDGroupLine, Option<Box<IDScope>>)>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum IDFlow {
 	Flat(Vec<IDFlatLine>),
 	OptionsFork(IDOptionsFork),
+	LineGroup(IDLineGroup),
 	IfBranch(IDIfBranch),
+	OnceBranch(IDOnceBranch),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -47,7 +64,7 @@ pub enum IDFlatLine {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IDSpeech {
-	pub line_number: LineNumber,
+	pub span: Span,
 	pub line_id: String,
 	pub speaker: Option<Speaker>,
 	pub text: (String, Vec<YarnExpr>),
@@ -56,7 +73,7 @@ pub struct IDSpeech {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IDCustomCommand {
-	pub line_number: LineNumber,
+	pub span: Span,
 	pub line_id: String,
 	pub variant: String,
 	pub args: Vec<YarnExpr>,
@@ -64,9 +81,12 @@ pub struct IDCustomCommand {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BuiltInCommand {
-	Set { line_number: LineNumber, var_name: String, op: SetOperation, value: YarnExpr },
-	Jump { line_number: LineNumber, node_destination_title: String },
-	Stop { line_number: LineNumber },
+	Set { span: Span, var_name: String, op: SetOperation, value: YarnExpr },
+	Jump { span: Span, node_destination_title: String },
+	/// `virtual_id` names the continuation that resumes at the line after the detour, `D{n}` for the node's n-th detour.
+	Detour { span: Span, node_destination_title: String, virtual_id: String },
+	Return { span: Span },
+	Stop { span: Span },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -76,6 +96,14 @@ pub struct IDIfBranch {
 	pub else_: Option<(Else_, Option<Box<IDScope>>)>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IDOnceBranch {
+	/// Identifies the block in the storage's `once` flags.
+	pub once_key: String,
+	pub once_: (Once_, Option<Box<IDScope>>),
+	pub else_: Option<(Else_, Option<Box<IDScope>>)>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum InstructionKind {
 	Speech,
@@ -83,88 +111,14 @@ pub enum InstructionKind {
 	OptionsFork,
 }
 
-fn insert_flat_ids(flat_lines: &[FlatLine], taken_ids: &mut HashSet<String>) -> Result<()> {
-	let line_ids =
-		flat_lines
-			.iter()
-			.filter_map(|flat|
-				match flat {
-					FlatLine::Speech(speech) => speech.line_id.as_deref(),
-					FlatLine::Command(_) => None,
-				});
-
-	for id in line_ids {
-		if taken_ids.contains(id) {
-			return Err(anyhow!( "Duplicate line id found: {}" , id ));
-		} else {
-			taken_ids.insert(id.to_owned());
-		}
-	}
-
-	Ok(())
-}
-
-fn insert_options_ids(options_fork: &OptionsFork, taken_ids: &mut HashSet<String>) -> Result<()> {
-	for (option, scope_option) in options_fork.iter_options() {
-		if let Some(id) = &(option.line_id) {
-			if taken_ids.contains(id.as_str()) {
-				return Err(anyhow!( "Duplicate line id found: {}" , id ));
-			} else {
-				taken_ids.insert(id.to_owned());
-			}
-		}
-
-		if let Some(scope) = scope_option {
-			insert_scope_ids(scope, taken_ids)?;
-		}
-	}
-
-	Ok(())
-}
-
-fn insert_scope_ids(scope: &YarnScope, taken_ids: &mut HashSet<String>) -> Result<()> {
-	for flow in scope.flows() {
-		match flow {
-			Flow::Flat(flat_lines) => {
-				insert_flat_ids(flat_lines, taken_ids)?;
-			}
-			Flow::OptionsFork(options_fork) => {
-				insert_options_ids(options_fork, taken_ids)?;
-			}
-			Flow::IfBranch(if_branch) => {
-				if_branch
-					.if_.1
-					.as_ref()
-					.map(|if_scope|
-						insert_scope_ids(if_scope, taken_ids));
-
-				if_branch
-					.else_ifs
-					.iter()
-					.try_for_each(|(_, scope_option)| {
-						if let Some(else_if_scope) = scope_option {
-							insert_scope_ids(else_if_scope, taken_ids)?;
-						}
-
-						Result::<_, anyhow::Error>::Ok(())
-					})?;
-
-				if let Some((_, Some(else_scope))) = &if_branch.else_ {
-					insert_scope_ids(else_scope, taken_ids)?;
-				}
-			}
-		}
-	}
-
-	Ok(())
-}
-
-fn fill_existing_ids(node: &YarnNode, taken_ids: &mut HashSet<String>) -> Result<()> {
-	node.contents
-	    .iter()
-	    .try_for_each(|scope| {
-		    insert_scope_ids(scope, taken_ids)
-	    })
+/// Counts what a node saves in the storage, so the saved keys don't change when other nodes are edited,
+/// unlike the generated line ids.
+#[derive(Default)]
+struct NodeOrdinals {
+	once_blocks: usize,
+	once_options: usize,
+	/// The continuations are saved in the storage's detour stack.
+	detours: usize,
 }
 
 macro_rules! gen_id {
@@ -176,10 +130,12 @@ macro_rules! gen_id {
 }
 
 fn convert_to_id_scope(
+	file: &Path,
 	node_title: &str,
 	scope: YarnScope,
 	id_prefix: &str,
 	id_counter: &mut usize,
+	ordinals: &mut NodeOrdinals,
 ) -> Result<IDScope> {
 	let indent = scope.indent();
 
@@ -200,13 +156,13 @@ fn convert_to_id_scope(
 									tags,
 								}) => {
 									let line_id = line_id.unwrap_or_else(|| gen_id!(id_prefix, id_counter));
-									IDFlatLine::Speech(IDSpeech { line_number, line_id, speaker, text, tags })
+									IDFlatLine::Speech(IDSpeech { span: Span::line(file, line_number), line_id, speaker, text, tags })
 								}
 								FlatLine::Command(command) => {
 									match command.variant {
 										CommandVariant::Set { var_name, op, value } => {
 											IDFlatLine::BuiltInCommand(BuiltInCommand::Set {
-												line_number: command.line_number,
+												span: Span::line(file, command.line_number),
 												var_name,
 												op,
 												value,
@@ -214,18 +170,32 @@ fn convert_to_id_scope(
 										}
 										CommandVariant::Jump { node_name } => {
 											IDFlatLine::BuiltInCommand(BuiltInCommand::Jump {
-												line_number: command.line_number,
+												span: Span::line(file, command.line_number),
+												node_destination_title: node_name,
+											})
+										}
+										CommandVariant::Detour { node_name } => {
+											ordinals.detours += 1;
+
+											IDFlatLine::BuiltInCommand(BuiltInCommand::Detour {
+												span: Span::line(file, command.line_number),
 												node_destination_title: node_name,
+												virtual_id: format!("D{}", ordinals.detours),
+											})
+										}
+										CommandVariant::Return => {
+											IDFlatLine::BuiltInCommand(BuiltInCommand::Return {
+												span: Span::line(file, command.line_number),
 											})
 										}
 										CommandVariant::Stop => {
 											IDFlatLine::BuiltInCommand(BuiltInCommand::Stop {
-												line_number: command.line_number,
+												span: Span::line(file, command.line_number),
 											})
 										}
 										CommandVariant::Other { variant, args } => {
 											IDFlatLine::CustomCommand(IDCustomCommand {
-												line_number: command.line_number,
+												span: Span::line(file, command.line_number),
 												line_id: gen_id!(id_prefix, id_counter),
 												variant,
 												args,
@@ -254,21 +224,33 @@ fn convert_to_id_scope(
 							.into_iter()
 							.enumerate()
 							.map(|(index_on_fork, (line, scope))| {
+								// Explicit line ids are kept when lines move, so they make the best keys.
+								let once_key = line.once.then(|| {
+									match &line.line_id {
+										Some(line_id) => enums::once_key(node_title, line_id),
+										None => {
+											ordinals.once_options += 1;
+											enums::once_key(node_title, &format!("option{}", ordinals.once_options))
+										},
+									}
+								});
+
 								let line_id = line
 									.line_id
 									.unwrap_or_else(|| gen_id!(id_prefix, id_counter));
 
 								let id_scope = scope
 									.map(|scope|
-										convert_to_id_scope(node_title, *scope, id_prefix, id_counter)
+										convert_to_id_scope(file, node_title, *scope, id_prefix, id_counter, ordinals)
 											.map(Box::from))
 									.transpose()?;
 
 								Result::<_, anyhow::Error>::Ok((IDOptionLine {
-									line_number: line.line_number,
+									span: Span::line(file, line.line_number),
 									line_id,
 									text: line.text,
 									if_condition: line.if_condition,
+									once_key,
 									tags: line.tags,
 									fork_qualified: fork_qualified.clone(),
 									index_on_fork,
@@ -288,12 +270,45 @@ fn convert_to_id_scope(
 						options: CountOrMore::new([first_id_option], other_id_options),
 					}))
 				}
+				Flow::LineGroup(line_group) => {
+					let mut id_lines_iter =
+						line_group
+							.lines
+							.into_iter()
+							.map(|(group_line, scope)| {
+								let Speech { line_number, line_id, speaker, text, tags } = group_line.speech;
+								let line_id = line_id.unwrap_or_else(|| gen_id!(id_prefix, id_counter));
+
+								let id_scope = scope
+									.map(|scope|
+										convert_to_id_scope(file, node_title, *scope, id_prefix, id_counter, ordinals)
+											.map(Box::from))
+									.transpose()?;
+
+								Result::<_, anyhow::Error>::Ok((IDGroupLine {
+									speech: IDSpeech { span: Span::line(file, line_number), line_id, speaker, text, tags },
+									condition: group_line.condition,
+								}, id_scope))
+							});
+
+					let first_id_line =
+						id_lines_iter
+							.next()
+							.unwrap()?;
+
+					let other_id_lines =
+						id_lines_iter.try_collect()?;
+
+					Ok(IDFlow::LineGroup(IDLineGroup {
+						lines: CountOrMore::new([first_id_line], other_id_lines),
+					}))
+				}
 				Flow::IfBranch(if_branch) => {
 					let id_if_scope =
 						if_branch
 							.if_.1
 							.map(|if_scope|
-								convert_to_id_scope(node_title, *if_scope, id_prefix, id_counter)
+								convert_to_id_scope(file, node_title, *if_scope, id_prefix, id_counter, ordinals)
 									.map(Box::from))
 							.transpose()?;
 
@@ -305,7 +320,7 @@ fn convert_to_id_scope(
 								let id_scope =
 									scope_option
 										.map(|scope|
-											convert_to_id_scope(node_title, *scope, id_prefix, id_counter)
+											convert_to_id_scope(file, node_title, *scope, id_prefix, id_counter, ordinals)
 												.map(Box::from))
 										.transpose()?;
 
@@ -318,7 +333,7 @@ fn convert_to_id_scope(
 							let id_scope =
 								scope_option
 									.map(|scope|
-										convert_to_id_scope(node_title, *scope, id_prefix, id_counter)
+										convert_to_id_scope(file, node_title, *scope, id_prefix, id_counter, ordinals)
 											.map(Box::from))
 									.transpose()?;
 
@@ -331,6 +346,37 @@ fn convert_to_id_scope(
 						else_: id_else,
 					}))
 				}
+				Flow::OnceBranch(once_branch) => {
+					ordinals.once_blocks += 1;
+					let once_key = enums::once_key(node_title, &format!("once{}", ordinals.once_blocks));
+
+					let id_once_scope =
+						once_branch
+							.once_.1
+							.map(|once_scope|
+								convert_to_id_scope(file, node_title, *once_scope, id_prefix, id_counter, ordinals)
+									.map(Box::from))
+							.transpose()?;
+
+					let id_else = once_branch
+						.else_
+						.map(|(else_, scope_option)| {
+							let id_scope =
+								scope_option
+									.map(|scope|
+										convert_to_id_scope(file, node_title, *scope, id_prefix, id_counter, ordinals)
+											.map(Box::from))
+									.transpose()?;
+
+							Result::<_, anyhow::Error>::Ok((else_, id_scope))
+						}).transpose()?;
+
+					Ok(IDFlow::OnceBranch(IDOnceBranch {
+						once_key,
+						once_: (once_branch.once_.0, id_once_scope),
+						else_: id_else,
+					}))
+				}
 			}
 		}).try_collect()?;
 
@@ -421,26 +467,87 @@ fn generate_prefixes(nodes: Vec<YarnNode>) -> Vec<(YarnNode, String)> {
 	     }).collect()
 }
 
-pub fn convert_to_id_nodes(nodes: Vec<YarnNode>) -> Result<Vec<IDNode>> {
-	let mut taken_ids = HashSet::new();
-	let mut id_counter = 0;
+/// Nodes with a `when:` header share their title with the rest of their group,
+/// each is renamed to `{title}__{n}` so the group's title is free for the group itself.
+/// Other nodes can't use those titles, see [find_duplicates](crate::parsing::duplicates::find_duplicates).
+///
+/// Returns the group title of each node.
+fn rename_group_members(nodes: &mut [YarnNode]) -> Vec<Option<String>> {
+	let mut member_counts = HashMap::<String, usize>::new();
+
+	nodes.iter_mut()
+	     .map(|node| {
+		     if node.metadata.when.is_empty() {
+			     return None;
+		     }
+
+		     let group_title = node.metadata.title.clone();
+		     let count = member_counts.entry(group_title.clone()).or_default();
+		     *count += 1;
+
+		     node.metadata.title = format!("{group_title}__{count}");
+		     Some(group_title)
+	     }).collect()
+}
 
-	nodes.iter()
-	     .try_for_each(|node|
-		     fill_existing_ids(node, &mut taken_ids))?;
+pub fn convert_to_id_nodes(mut nodes: Vec<YarnNode>) -> Result<Vec<IDNode>> {
+	let groups = rename_group_members(&mut nodes);
+	let mut id_counter = 0;
 
 	generate_prefixes(nodes)
 		.into_iter()
-		.map(|(node, prefix)| {
+		.zip(groups)
+		.map(|((node, prefix), group)| {
+			let mut ordinals = NodeOrdinals::default();
+
 			let id_scopes =
 				node.contents
 				    .into_iter()
-				    .map(|scope| convert_to_id_scope(&node.metadata.title, scope, &prefix, &mut id_counter))
+				    .map(|scope| convert_to_id_scope(&node.source_file, &node.metadata.title, scope, &prefix, &mut id_counter, &mut ordinals))
 				    .try_collect()?;
 
 			Ok(IDNode {
 				metadata: node.metadata,
+				group,
 				scopes: id_scopes,
+				source_file: node.source_file,
+				source_lines: node.source_lines,
 			})
 		}).try_collect()
-}
\ No newline at end of file
+}
+#[test]
+fn test_once_keys_ignore_other_nodes() {
+	use crate::quoting::test_util::parse_id_nodes;
+	use pretty_assertions::assert_eq;
+
+	let story =
+		"title: Story\n\
+		 ---\n\
+		 <<once>>\n\
+		 \x20   First visit\n\
+		 <<endonce>>\n\
+		 -> Ask <<once>>\n\
+		 -> Greet <<once>> #line:greet\n\
+		 -> Leave\n\
+		 <<once>>\n\
+		 \x20   Second block\n\
+		 <<endonce>>\n\
+		 ===";
+
+	let once_keys = |text: &str| {
+		let nodes = parse_id_nodes(text);
+		let story = nodes.iter().find(|node| node.metadata.title == "Story").unwrap();
+
+		story.scopes[0].flows.iter().flat_map(|flow| match flow {
+			IDFlow::OnceBranch(once_branch) => vec![once_branch.once_key.clone()],
+			IDFlow::OptionsFork(fork) => fork.options.iter().filter_map(|(option, _)| option.once_key.clone()).collect(),
+			_ => vec![],
+		}).collect::<Vec<_>>()
+	};
+
+	let before = once_keys(&format!("title: Intro\n---\nHello\n===\n{story}"));
+	assert_eq!(before, ["Story.once1", "Story.option1", "Story.greet", "Story.once2"]);
+
+	let after = once_keys(&format!("title: Intro\n---\nHello\n<<once>>\n    Welcome\n<<endonce>>\n-> Hi <<once>>\n===\n{story}"));
+	assert_eq!(after, before);
+}
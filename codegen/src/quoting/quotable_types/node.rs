@@ -1,22 +1,67 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 use crate::parsing::raw::node_metadata::NodeMetadata;
-use crate::quoting::quotable_types::enums::{LineEnum, OptionLineEnum};
-use crate::quoting::quotable_types::line_ids::{IDCustomCommand, IDOptionLine, IDOptionsFork, IDSpeech, InstructionKind};
+use crate::quoting::quotable_types::enums::{DetourEnum, LineEnum, OptionLineEnum};
+use crate::quoting::quotable_types::line_ids::{BuiltInCommand, IDCustomCommand, IDFlatLine, IDOptionLine, IDOptionsFork, IDSpeech, InstructionKind};
 use crate::quoting::quotable_types::scope::IDScope;
+use crate::LineNumber;
 
 pub struct LinesMap<'a> {
 	pub speeches: Vec<(&'a IDSpeech, LineEnum<'a>)>,
 	pub commands: Vec<(&'a IDCustomCommand, LineEnum<'a>)>,
 	pub options_forks: Vec<(&'a IDOptionsFork, LineEnum<'a>)>,
 	pub option_lines: Vec<(&'a IDOptionLine, OptionLineEnum<'a>)>,
+	pub detours: Vec<DetourEnum<'a>>,
 }
 
 pub struct IDNode {
+	/// For members of a node group, `title` is renamed to `{group}__{n}`.
 	pub metadata: NodeMetadata,
+	/// The shared title, if this node has a `when:` header.
+	pub group: Option<String>,
 	pub scopes: Vec<IDScope>,
+	/// The file this node was declared in.
+	pub source_file: PathBuf,
+	pub source_lines: BTreeMap<LineNumber, String>,
+}
+
+/// Nodes that share a title through `when:` headers, starting the group picks one of them.
+pub struct IDNodeGroup<'a> {
+	pub title: &'a str,
+	pub members: Vec<&'a IDNode>,
+}
+
+/// Groups are in the order their first member was declared.
+pub fn node_groups(nodes: &[IDNode]) -> Vec<IDNodeGroup> {
+	let mut groups: Vec<IDNodeGroup> = vec![];
+
+	for node in nodes {
+		let Some(group_title) = &node.group
+			else { continue };
+
+		match groups.iter_mut().find(|group| group.title == group_title) {
+			Some(group) => group.members.push(node),
+			None => groups.push(IDNodeGroup { title: group_title, members: vec![node] }),
+		}
+	}
+
+	groups
 }
 
 impl IDNode {
+	/// The title `<<jump>>` and `visited()` use to refer to this node, which is the group's for group members.
+	pub fn jump_title(&self) -> &str {
+		self.group.as_deref().unwrap_or(&self.metadata.title)
+	}
+
+	/// The yarn text of the line at `line_number`, empty if it isn't part of this node.
+	pub fn source_line(&self, line_number: LineNumber) -> &str {
+		self.source_lines
+		    .get(&line_number)
+		    .map(String::as_str)
+		    .unwrap_or_default()
+	}
+
 	pub fn map_lines(&self) -> LinesMap {
 		let title = self.metadata.title.as_str();
 		
@@ -29,11 +74,24 @@ impl IDNode {
 			scope.map_lines(title, &mut speeches, &mut commands, &mut options_forks, &mut option_lines);
 		}
 		
+		let detours =
+			self.scopes
+			    .iter()
+			    .flat_map(IDScope::iter_flat_lines)
+			    .filter_map(|line| {
+				    if let IDFlatLine::BuiltInCommand(BuiltInCommand::Detour { virtual_id, .. }) = line {
+					    Some(DetourEnum { node_title: title, raw_id: virtual_id })
+				    } else {
+					    None
+				    }
+			    }).collect();
+		
 		LinesMap {
 			speeches, 
 			commands, 
 			options_forks, 
-			option_lines
+			option_lines,
+			detours,
 		}
 	}
 
@@ -81,4 +139,41 @@ impl IDNode {
 			(InstructionKind::OptionsFork, options_forks),
 		])
 	}
-}
\ No newline at end of file
+}
+#[test]
+fn test_node_groups() {
+	use crate::quoting::test_util::parse_id_nodes;
+	use pretty_assertions::assert_eq;
+
+	let nodes = parse_id_nodes(
+		"title: Intro\n\
+		 ---\n\
+		 Hello\n\
+		 ===\n\
+		 title: Story\n\
+		 when: once\n\
+		 ---\n\
+		 First\n\
+		 ===\n\
+		 title: Extra\n\
+		 when: always\n\
+		 ---\n\
+		 Bonus\n\
+		 ===\n\
+		 title: Story\n\
+		 when: always\n\
+		 ---\n\
+		 Again\n\
+		 ===");
+
+	let titles = nodes.iter().map(|node| (node.metadata.title.as_str(), node.jump_title())).collect::<Vec<_>>();
+	assert_eq!(titles, [("Intro", "Intro"), ("Story__1", "Story"), ("Extra__1", "Extra"), ("Story__2", "Story")]);
+
+	let groups =
+		node_groups(&nodes)
+			.into_iter()
+			.map(|group| (group.title, group.members.iter().map(|member| member.metadata.title.as_str()).collect::<Vec<_>>()))
+			.collect::<Vec<_>>();
+
+	assert_eq!(groups, [("Story", vec!["Story__1", "Story__2"]), ("Extra", vec!["Extra__1"])]);
+}
@@ -34,6 +34,30 @@ impl IDScope {
 							}
 						}
 					}
+					IDFlow::LineGroup(line_group) => {
+						for (_line, scope_option) in line_group.lines.iter() {
+							yield &_line.speech;
+
+							if let Some(scope) = scope_option {
+								for _speech in Box::from(scope.iter_speeches()) {
+									yield _speech;
+								}
+							}
+						}
+					}
+					IDFlow::OnceBranch(once_branch) => {
+						if let Some(once_scope) = &once_branch.once_.1 {
+							for _speech in Box::from(once_scope.iter_speeches()) {
+								yield _speech;
+							}
+						}
+
+						if let Some((_, Some(else_scope))) = &once_branch.else_ {
+							for _speech in Box::from(else_scope.iter_speeches()) {
+								yield _speech;
+							}
+						}
+					}
 					IDFlow::IfBranch(if_branch) => {
 						if let Some(if_scope) = &if_branch.if_.1 {
 							for _speech in Box::from(if_scope.iter_speeches()) {
@@ -90,6 +114,30 @@ impl IDScope {
 							}
 						}
 					}
+					IDFlow::LineGroup(line_group) => {
+						for (_line, scope_option) in line_group.lines.iter() {
+							yield _line.speech.line_id.as_str();
+
+							if let Some(scope) = scope_option {
+								for _line_id in Box::from(scope.iter_line_ids()) {
+									yield _line_id;
+								}
+							}
+						}
+					}
+					IDFlow::OnceBranch(once_branch) => {
+						if let Some(once_scope) = &once_branch.once_.1 {
+							for _line_id in Box::from(once_scope.iter_line_ids()) {
+								yield _line_id;
+							}
+						}
+
+						if let Some((_, Some(else_scope))) = &once_branch.else_ {
+							for _line_id in Box::from(else_scope.iter_line_ids()) {
+								yield _line_id;
+							}
+						}
+					}
 					IDFlow::IfBranch(if_branch) => {
 						if let Some(if_scope) = &if_branch.if_.1 {
 							for _line_id in Box::from(if_scope.iter_line_ids()) {
@@ -144,6 +192,30 @@ impl IDScope {
 							}
 						}
 					}
+					IDFlow::LineGroup(line_group) => {
+						for (_line, scope_option) in line_group.lines.iter() {
+							yield (InstructionKind::Speech, _line.speech.line_id.as_str());
+
+							if let Some(scope) = scope_option {
+								for _mapped_line in Box::from(scope.iter_instructions_kind()) {
+									yield _mapped_line;
+								}
+							}
+						}
+					}
+					IDFlow::OnceBranch(once_branch) => {
+						if let Some(once_scope) = &once_branch.once_.1 {
+							for _mapped_line in Box::from(once_scope.iter_instructions_kind()) {
+								yield _mapped_line;
+							}
+						}
+
+						if let Some((_, Some(else_scope))) = &once_branch.else_ {
+							for _mapped_line in Box::from(else_scope.iter_instructions_kind()) {
+								yield _mapped_line;
+							}
+						}
+					}
 					IDFlow::IfBranch(if_branch) => {
 						if let Some(if_scope) = &if_branch.if_.1 {
 							for _mapped_line in Box::from(if_scope.iter_instructions_kind()) {
@@ -229,6 +301,31 @@ impl IDScope {
 						}
 					}
 				}
+				IDFlow::LineGroup(line_group) => {
+					for (line, scope_option) in line_group.lines.iter() {
+						let line_enum =
+							LineEnum {
+								node_title,
+								raw_id: &line.speech.line_id,
+								instruction_kind: InstructionKind::Speech
+							};
+
+						speeches.push((&line.speech, line_enum));
+
+						if let Some(scope) = scope_option {
+							scope.map_lines(node_title, speeches, commands, options_forks, option_lines);
+						}
+					}
+				}
+				IDFlow::OnceBranch(once_branch) => {
+					if let Some(once_scope) = &once_branch.once_.1 {
+						once_scope.map_lines(node_title, speeches, commands, options_forks, option_lines);
+					}
+
+					if let Some((_, Some(else_scope))) = &once_branch.else_ {
+						else_scope.map_lines(node_title, speeches, commands, options_forks, option_lines);
+					}
+				}
 				IDFlow::IfBranch(if_branch) => {
 					if let Some(if_scope) = &if_branch.if_.1 {
 						if_scope.map_lines(node_title, speeches, commands, options_forks, option_lines);
@@ -296,6 +393,34 @@ impl IDScope {
 							}
 						}
 					}
+					IDFlow::LineGroup(line_group) => {
+						for (line, scope_option) in line_group.lines.iter() {
+							for arg in &line.speech.text.1 {
+								yield_items!(arg.iter_exprs());
+							}
+
+							if let Some(condition) = &line.condition {
+								yield_items!(condition.iter_exprs());
+							}
+
+							if let Some(scope_inside_line) = scope_option {
+								yield_items!(Box::new(scope_inside_line.iter_exprs()));
+							}
+						}
+					}
+					IDFlow::OnceBranch(once_branch) => {
+						if let Some(condition) = &once_branch.once_.0.condition {
+							yield_items!(condition.iter_exprs());
+						}
+
+						if let Some(once_scope) = &once_branch.once_.1 {
+							yield_items!(Box::new(once_scope.iter_exprs()));
+						}
+
+						if let Some((_, Some(else_scope))) = &once_branch.else_ {
+							yield_items!(Box::new(else_scope.iter_exprs()));
+						}
+					}
 					IDFlow::IfBranch(if_branch) => {
 						yield_items!(if_branch.if_.0.condition.iter_exprs());
 
@@ -332,6 +457,28 @@ impl IDScope {
 							}
 						}
 					}
+					IDFlow::LineGroup(line_group) => {
+						for (_, maybe_scope) in line_group.lines.iter() {
+							if let Some(scope) = maybe_scope {
+								for _flow in Box::new(scope.iter_flows_recursive()) {
+									yield _flow;
+								}
+							}
+						}
+					}
+					IDFlow::OnceBranch(once_branch) => {
+						if let Some(once_scope) = &once_branch.once_.1 {
+							for _flow in Box::new(once_scope.iter_flows_recursive()) {
+								yield _flow;
+							}
+						}
+
+						if let Some((_, Some(else_scope))) = &once_branch.else_ {
+							for _flow in Box::new(else_scope.iter_flows_recursive()) {
+								yield _flow;
+							}
+						}
+					}
 					IDFlow::IfBranch(if_branch) => {
 						if let (_, Some(scope)) = &if_branch.if_ {
 							for _flow in Box::new(scope.iter_flows_recursive()) {
@@ -0,0 +1,20 @@
+use crate::io::read::yarn_file_from_text;
+use crate::io::write::util::render_tokens;
+use crate::quoting::quotable_types::line_ids::convert_to_id_nodes;
+use crate::quoting::quotable_types::node::IDNode;
+use genco::lang::rust::Tokens;
+use std::path::PathBuf;
+
+/// Parses `text` as the contents of `test.yarn`, panicking on any error.
+pub fn parse_id_nodes(text: &str) -> Vec<IDNode> {
+	let mut parse_errors = vec![];
+	let (nodes, _) = crate::parsing::parse_nodes(yarn_file_from_text(PathBuf::from("test.yarn"), text), &mut parse_errors);
+	assert_eq!(parse_errors, []);
+
+	convert_to_id_nodes(nodes).unwrap()
+}
+
+/// The code, formatted like the generated files.
+pub fn render(tokens: Tokens) -> String {
+	render_tokens("test.rs", tokens).unwrap().contents
+}
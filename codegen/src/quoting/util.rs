@@ -1,4 +1,9 @@
+use crate::config::YarnConfig;
+use crate::diagnostic::Span;
+use crate::quoting::quotable_types::node::IDNode;
 use genco::lang::Rust;
+use genco::prelude::quoted;
+use genco::quote;
 use genco::tokens::{FormatInto, ItemStr, Tokens};
 
 /// Documentation comments.
@@ -62,4 +67,72 @@ impl<T, TIter> FormatInto<Rust> for SeparatedItems<T, TIter>
 			}
 		}
 	}
-}
\ No newline at end of file
+}
+
+/// A `// file:line` comment followed by the line's yarn text as documentation, 
+/// placed above the code generated from that line.
+pub fn source_comments(cfg: &YarnConfig, node: &IDNode, span: &Span) -> Tokens<Rust> {
+	let yarn_text = node.source_line(span.line);
+	let code =
+		if yarn_text.contains('`') {
+			format!("`` {yarn_text} ``")
+		} else {
+			format!("`{yarn_text}`")
+		};
+
+	let mut tokens = Tokens::new();
+	tokens.append(format!("// {file}:{line}", file = cfg.source_path(&span.file), line = span.line));
+	tokens.append(Comments([code]));
+	tokens
+}
+
+/// Implements `source_location`, which points at the yarn line a generated line came from.
+pub fn source_location_fn(cfg: &YarnConfig, span: &Span) -> Tokens<Rust> {
+	let file = cfg.source_path(&span.file);
+	let line = span.line as u32;
+
+	quote! {
+		fn source_location(&self) -> (&'static str, u32) {
+			($(quoted(file)), $line)
+		}
+	}
+}
+
+#[test]
+fn test_source_comments() {
+	use crate::quoting::test_util::{parse_id_nodes, render};
+	use pretty_assertions::assert_eq;
+	use std::path::{Path, PathBuf};
+
+	let nodes = parse_id_nodes(
+		"title: Intro\n\
+		 ---\n\
+		 Hello there\n\
+		 Press `E` to talk\n\
+		 ===");
+
+	let mut cfg = YarnConfig::for_tests();
+	cfg.config_path = PathBuf::from("game/yarn_project.toml");
+
+	let code = |line| {
+		let span = Span::line(Path::new("game/yarn/intro.yarn"), line);
+		render(quote! {
+			$(source_comments(&cfg, &nodes[0], &span))
+			$(source_location_fn(&cfg, &span))
+		})
+	};
+
+	assert_eq!(code(3),
+		"// yarn/intro.yarn:3\n\
+		 /// `Hello there`\n\
+		 fn source_location(&self) -> (&'static str, u32) {\n\
+		 \x20   (\"yarn/intro.yarn\", 3)\n\
+		 }\n");
+
+	assert_eq!(code(4),
+		"// yarn/intro.yarn:4\n\
+		 /// `` Press `E` to talk ``\n\
+		 fn source_location(&self) -> (&'static str, u32) {\n\
+		 \x20   (\"yarn/intro.yarn\", 4)\n\
+		 }\n");
+}
@@ -0,0 +1,1149 @@
+use crate::diagnostic::{Diagnostic, ErrorCode, Span};
+use crate::expressions::built_in_calls::BuiltInFunctionCall;
+use crate::expressions::declaration_ty::DeclarationTy;
+use crate::expressions::yarn_expr::YarnExpr;
+use crate::expressions::yarn_lit::YarnLit;
+use crate::expressions::yarn_ops::{YarnBinaryOp, YarnUnaryOp};
+use crate::parsing::grouping::scope::{FlatLine, Flow, YarnScope};
+use crate::parsing::raw::command::{CommandVariant, SetOperation};
+use crate::parsing::raw::node_metadata::WhenCondition;
+use crate::parsing::raw::speech::Speaker;
+use crate::parsing::raw::var_declaration::VarDeclaration;
+use crate::parsing::YarnNode;
+use crate::LineNumber;
+use std::collections::{HashMap, HashSet};
+
+/// How an expression, variable or node is used by a line, which decides the types it accepts.
+pub(crate) enum Usage<'a> {
+	/// Interpolated into text or passed to a custom command, any type is accepted.
+	Any(&'a YarnExpr),
+	/// `<<if>>`, `<<elseif>>`, an option's `<<if>>` or a `when:` header, must be `bool`.
+	Condition(&'a YarnExpr),
+	Set {
+		var_name: &'a str,
+		op: SetOperation,
+		value: &'a YarnExpr,
+	},
+	/// Variable speakers are printed as the speaker's name.
+	Speaker(&'a str),
+	/// The title of the node a `<<jump>>` or `<<detour>>` goes to.
+	Jump(&'a str),
+	/// The name of a custom command, its arguments are visited as [Usage::Any].
+	Command(&'a str),
+}
+
+/// Visits the node's `when:` headers, then its contents.
+pub(crate) fn visit_node<'a>(node: &'a YarnNode, visit: &mut impl FnMut(LineNumber, Usage<'a>)) {
+	for (line_number, when) in &node.metadata.when {
+		if let WhenCondition::Condition(condition) = when {
+			visit(*line_number, Usage::Condition(condition));
+		}
+	}
+
+	for scope in &node.contents {
+		visit_scope(scope, visit);
+	}
+}
+
+pub(crate) fn visit_scope<'a>(scope: &'a YarnScope, visit: &mut impl FnMut(LineNumber, Usage<'a>)) {
+	for flow in scope.flows() {
+		match flow {
+			Flow::Flat(lines) => {
+				for line in lines {
+					match line {
+						FlatLine::Speech(speech) => {
+							if let Some(Speaker::Variable(var_name)) = &speech.speaker {
+								visit(speech.line_number, Usage::Speaker(var_name));
+							}
+
+							for arg in &speech.text.1 {
+								visit(speech.line_number, Usage::Any(arg));
+							}
+						},
+						FlatLine::Command(command) => {
+							match &command.variant {
+								CommandVariant::Set { var_name, op, value } => {
+									visit(command.line_number, Usage::Set { var_name, op: *op, value });
+								},
+								CommandVariant::Other { variant, args } => {
+									visit(command.line_number, Usage::Command(variant));
+
+									for arg in args {
+										visit(command.line_number, Usage::Any(arg));
+									}
+								},
+								| CommandVariant::Jump { node_name }
+								| CommandVariant::Detour { node_name } => {
+									visit(command.line_number, Usage::Jump(node_name));
+								},
+								| CommandVariant::Return
+								| CommandVariant::Stop => {},
+							}
+						},
+					}
+				}
+			},
+			Flow::OptionsFork(options_fork) => {
+				for (option, scope_option) in options_fork.iter_options() {
+					for arg in &option.text.1 {
+						visit(option.line_number, Usage::Any(arg));
+					}
+
+					if let Some(condition) = &option.if_condition {
+						visit(option.line_number, Usage::Condition(condition));
+					}
+
+					if let Some(scope) = scope_option {
+						visit_scope(scope, visit);
+					}
+				}
+			},
+			Flow::LineGroup(line_group) => {
+				for (group_line, scope_option) in line_group.iter_lines() {
+					let speech = &group_line.speech;
+
+					if let Some(Speaker::Variable(var_name)) = &speech.speaker {
+						visit(speech.line_number, Usage::Speaker(var_name));
+					}
+
+					for arg in &speech.text.1 {
+						visit(speech.line_number, Usage::Any(arg));
+					}
+
+					if let Some(condition) = &group_line.condition {
+						visit(speech.line_number, Usage::Condition(condition));
+					}
+
+					if let Some(scope) = scope_option {
+						visit_scope(scope, visit);
+					}
+				}
+			},
+			Flow::IfBranch(if_branch) => {
+				let (if_, if_scope) = &if_branch.if_;
+				visit(if_.line_number, Usage::Condition(&if_.condition));
+
+				if let Some(scope) = if_scope {
+					visit_scope(scope, visit);
+				}
+
+				for (else_if, scope_option) in &if_branch.else_ifs {
+					visit(else_if.line_number, Usage::Condition(&else_if.condition));
+
+					if let Some(scope) = scope_option {
+						visit_scope(scope, visit);
+					}
+				}
+
+				if let Some((_, Some(scope))) = &if_branch.else_ {
+					visit_scope(scope, visit);
+				}
+			},
+			Flow::OnceBranch(once_branch) => {
+				let (once_, once_scope) = &once_branch.once_;
+
+				if let Some(condition) = &once_.condition {
+					visit(once_.line_number, Usage::Condition(condition));
+				}
+
+				if let Some(scope) = once_scope {
+					visit_scope(scope, visit);
+				}
+
+				if let Some((_, Some(scope))) = &once_branch.else_ {
+					visit_scope(scope, visit);
+				}
+			},
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Kind {
+	String,
+	Bool,
+	Number,
+}
+
+impl Kind {
+	fn of(ty: DeclarationTy) -> Kind {
+		match ty {
+			DeclarationTy::String => Kind::String,
+			DeclarationTy::bool => Kind::Bool,
+			_ => Kind::Number,
+		}
+	}
+
+	fn describe(self) -> &'static str {
+		match self {
+			Kind::String => "a `String`",
+			Kind::Bool => "a `bool`",
+			Kind::Number => "a number",
+		}
+	}
+}
+
+fn is_integer(ty: DeclarationTy) -> bool {
+	!matches!(ty, DeclarationTy::String | DeclarationTy::bool | DeclarationTy::number | DeclarationTy::f32 | DeclarationTy::f64)
+}
+
+fn is_float(ty: DeclarationTy) -> bool {
+	matches!(ty, DeclarationTy::number | DeclarationTy::f32 | DeclarationTy::f64)
+}
+
+/// The type a usage expects a variable to have.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum UsageTy {
+	Exact(DeclarationTy),
+	/// Integer literals are generated without suffix, so they fit any integer type.
+	Int,
+	/// Float literals fit any float type, but not integers.
+	Float,
+}
+
+impl UsageTy {
+	fn is_number(self) -> bool {
+		match self {
+			UsageTy::Exact(ty) => Kind::of(ty) == Kind::Number,
+			UsageTy::Int | UsageTy::Float => true,
+		}
+	}
+
+	fn is_integer(self) -> bool {
+		match self {
+			UsageTy::Exact(ty) => is_integer(ty),
+			UsageTy::Int => true,
+			UsageTy::Float => false,
+		}
+	}
+
+	fn is_float(self) -> bool {
+		match self {
+			UsageTy::Exact(ty) => is_float(ty),
+			UsageTy::Int => false,
+			UsageTy::Float => true,
+		}
+	}
+
+	fn fits(self, ty: DeclarationTy) -> bool {
+		match self {
+			UsageTy::Exact(exact) => exact == ty,
+			UsageTy::Int => is_integer(ty),
+			UsageTy::Float => is_float(ty),
+		}
+	}
+
+	/// The type fitting both usages, `None` if there isn't one.
+	///
+	/// Integers widen to floats, [widen_numbers] then converts the integers in the generated code.
+	/// Other numbers of different types are never unified.
+	fn unify(self, other: UsageTy) -> Option<UsageTy> {
+		match (self, other) {
+			_ if self == other => Some(self),
+			(UsageTy::Exact(ty), usage) | (usage, UsageTy::Exact(ty)) if usage.fits(ty) => Some(UsageTy::Exact(ty)),
+			_ if self.is_float() && other.is_integer() => Some(self),
+			_ if self.is_integer() && other.is_float() => Some(other),
+			_ => None,
+		}
+	}
+
+	/// The float type integers are widened to, `None` if the usage isn't a float.
+	fn float_ty(self) -> Option<DeclarationTy> {
+		match self {
+			UsageTy::Exact(ty) if is_float(ty) => Some(ty),
+			UsageTy::Float => Some(DeclarationTy::f64),
+			_ => None,
+		}
+	}
+
+	/// Variables only used with literals get the type literals have by default.
+	fn resolve(self) -> DeclarationTy {
+		match self {
+			UsageTy::Exact(ty) => ty,
+			UsageTy::Int => DeclarationTy::isize,
+			UsageTy::Float => DeclarationTy::f64,
+		}
+	}
+}
+
+impl std::fmt::Display for UsageTy {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			UsageTy::Exact(ty) => write!(f, "`{ty}`"),
+			UsageTy::Int => write!(f, "an integer"),
+			UsageTy::Float => write!(f, "a decimal number"),
+		}
+	}
+}
+
+/// A place where an undeclared variable is used, with the type that place expects the variable to have.
+struct VarUsage<'a> {
+	var_name: &'a str,
+	ty: Option<UsageTy>,
+	span: Span,
+}
+
+/// A type error, `help` explains how to fix it.
+struct Mismatch {
+	message: String,
+	help: Option<String>,
+}
+
+impl Mismatch {
+	fn new(message: impl Into<String>) -> Mismatch {
+		Mismatch { message: message.into(), help: None }
+	}
+
+	fn with_help(mut self, help: impl Into<String>) -> Mismatch {
+		self.help = Some(help.into());
+		self
+	}
+}
+
+/// `None` means the type is unknown, like the return of custom functions, expressions with unknown types are not checked.
+type ExprTy = Option<DeclarationTy>;
+
+struct VarTypes<'a> {
+	/// Every declared or used variable, `None` when nothing tells its type.
+	types: HashMap<&'a str, Option<DeclarationTy>>,
+	/// Undeclared variables are typed by unifying their usages, conflicting usages are reported
+	/// by [resolve_var_types] instead.
+	declared: HashSet<&'a str>,
+}
+
+impl VarTypes<'_> {
+	fn expect_kind(&self, expr: &YarnExpr, expected: Kind, context: &str) -> Result<ExprTy, Mismatch> {
+		let ty = self.type_of(expr)?;
+
+		match ty {
+			Some(found) if Kind::of(found) != expected => {
+				Err(Mismatch::new(format!("{context} expects {}, found `{found}`.", expected.describe())))
+			},
+			_ => Ok(ty),
+		}
+	}
+
+	fn type_of(&self, expr: &YarnExpr) -> Result<ExprTy, Mismatch> {
+		match expr {
+			YarnExpr::Lit(_) => Ok(expr.infer_ty()),
+			YarnExpr::GetVar(var_name) => Ok(self.types.get(var_name.as_str()).copied().flatten()),
+			YarnExpr::Parenthesis(inner) => self.type_of(inner),
+			YarnExpr::Identifier(_) => Ok(None),
+			YarnExpr::CustomFunctionCall { args, .. } => {
+				for arg in args {
+					self.type_of(arg)?;
+				}
+
+				Ok(None)
+			},
+			YarnExpr::UnaryOp { yarn_op, right } => {
+				let expected =
+					match yarn_op {
+						YarnUnaryOp::Not => Kind::Bool,
+						YarnUnaryOp::Negate => Kind::Number,
+					};
+
+				self.expect_kind(right, expected, &format!("Operator `{}`", yarn_op.resolve()))
+			},
+			YarnExpr::BinaryOp { yarn_op, left, right } => {
+				let left_ty = self.type_of(left)?;
+				let right_ty = self.type_of(right)?;
+				let ty = binary_op_ty(*yarn_op, left_ty, right_ty)?;
+
+				// Integers are widened to floats, other numbers must have the same type.
+				match (self.usage_ty(left), self.usage_ty(right)) {
+					(Some(left_usage), Some(right_usage)) if left_usage.is_number()
+						&& right_usage.is_number()
+						&& left_usage.unify(right_usage).is_none() => {
+						Err(Mismatch::new(format!(
+							"Operator `{symbol}` cannot be applied to {left_usage} and {right_usage}.", symbol = yarn_op.resolve()))
+							.with_help("Only integers are converted to decimals, cast one of the sides with `as`, like `$gold as f64`."))
+					},
+					_ => Ok(ty),
+				}
+			},
+			YarnExpr::BuiltInFunctionCall(call) => {
+				match call {
+					BuiltInFunctionCall::FormatInvariant(arg) => {
+						self.type_of(arg)?;
+					},
+					BuiltInFunctionCall::RandomRange(first, second) => {
+						self.expect_kind(first, Kind::Number, "Function `random_range`")?;
+						self.expect_kind(second, Kind::Number, "Function `random_range`")?;
+					},
+					BuiltInFunctionCall::RoundPlaces(first, second) => {
+						self.expect_kind(first, Kind::Number, "Function `round_places`")?;
+						self.expect_kind(second, Kind::Number, "Function `round_places`")?;
+					},
+					| BuiltInFunctionCall::Dice(arg)
+					| BuiltInFunctionCall::Round(arg)
+					| BuiltInFunctionCall::Floor(arg)
+					| BuiltInFunctionCall::Ceil(arg)
+					| BuiltInFunctionCall::Inc(arg)
+					| BuiltInFunctionCall::Dec(arg)
+					| BuiltInFunctionCall::Decimal(arg)
+					| BuiltInFunctionCall::Int(arg) => {
+						self.expect_kind(arg, Kind::Number, &format!("Function `{}`", built_in_name(call)))?;
+					},
+					| BuiltInFunctionCall::Random
+					| BuiltInFunctionCall::Visited(_)
+					| BuiltInFunctionCall::VisitedCount(_) => {},
+				}
+
+				Ok(expr.infer_ty())
+			},
+			YarnExpr::Cast { cast_ty, expr: inner } => {
+				let inner_ty = self.type_of(inner)?;
+
+				let valid =
+					match (inner_ty, *cast_ty) {
+						(_, DeclarationTy::String) | (Some(DeclarationTy::String), _) => false,
+						(Some(DeclarationTy::bool), target) => is_integer(target),
+						(_, DeclarationTy::bool) => inner_ty.is_none(),
+						_ => true,
+					};
+
+				if valid {
+					Ok(Some(*cast_ty))
+				} else {
+					let from = inner_ty.map_or_else(|| "value".to_string(), |ty| format!("`{ty}`"));
+					Err(Mismatch::new(format!("Cannot cast {from} as `{cast_ty}`."))
+						.with_help("Only numbers can be cast to other number types, `bool` can only be cast to integers."))
+				}
+			},
+		}
+	}
+}
+
+impl<'a> VarTypes<'a> {
+	/// The type `expr` expects the variables it's compared with or assigned to to have.
+	fn usage_ty(&self, expr: &YarnExpr) -> Option<UsageTy> {
+		match expr {
+			YarnExpr::Lit(YarnLit::Int(_)) => Some(UsageTy::Int),
+			YarnExpr::Lit(YarnLit::Float(_)) => Some(UsageTy::Float),
+			YarnExpr::GetVar(var_name) => self.types.get(var_name.as_str()).copied().flatten().map(UsageTy::Exact),
+			| YarnExpr::Parenthesis(inner)
+			| YarnExpr::UnaryOp { yarn_op: YarnUnaryOp::Negate, right: inner } => self.usage_ty(inner),
+			YarnExpr::BinaryOp { yarn_op, left, right } => {
+				match yarn_op {
+					| YarnBinaryOp::And
+					| YarnBinaryOp::Or
+					| YarnBinaryOp::Eq
+					| YarnBinaryOp::Ne
+					| YarnBinaryOp::Lt
+					| YarnBinaryOp::Le
+					| YarnBinaryOp::Gt
+					| YarnBinaryOp::Ge => Some(UsageTy::Exact(DeclarationTy::bool)),
+					_ => {
+						// Mismatched operands are reported by [check_nodes].
+						match (self.usage_ty(left), self.usage_ty(right)) {
+							(Some(left), Some(right)) => left.unify(right).or(Some(left)),
+							(left, right) => left.or(right),
+						}
+					},
+				}
+			},
+			_ => expr.infer_ty().map(UsageTy::Exact),
+		}
+	}
+
+	/// Pushes every variable inside `expr`, typed by the operand or argument they're used as.
+	///
+	/// `expected` is the type `expr` itself is expected to have.
+	fn insert_expr_usages(
+		&self,
+		expr: &'a YarnExpr,
+		expected: Option<UsageTy>,
+		span: &Span,
+		usages: &mut Vec<VarUsage<'a>>,
+	) {
+		let mut insert = |expr, expected| self.insert_expr_usages(expr, expected, span, usages);
+		let exact = |ty| Some(UsageTy::Exact(ty));
+
+		match expr {
+			YarnExpr::GetVar(var_name) => {
+				usages.push(VarUsage { var_name, ty: expected, span: span.clone() });
+			},
+			| YarnExpr::Parenthesis(inner)
+			| YarnExpr::UnaryOp { yarn_op: YarnUnaryOp::Negate, right: inner } => {
+				insert(inner, expected);
+			},
+			YarnExpr::UnaryOp { yarn_op: YarnUnaryOp::Not, right } => {
+				insert(right, exact(DeclarationTy::bool));
+			},
+			YarnExpr::BinaryOp { yarn_op, left, right } => {
+				match yarn_op {
+					YarnBinaryOp::And | YarnBinaryOp::Or => {
+						insert(left, exact(DeclarationTy::bool));
+						insert(right, exact(DeclarationTy::bool));
+					},
+					// Comparisons return `bool`, so only the other side tells the operand's type.
+					| YarnBinaryOp::Eq
+					| YarnBinaryOp::Ne
+					| YarnBinaryOp::Lt
+					| YarnBinaryOp::Le
+					| YarnBinaryOp::Gt
+					| YarnBinaryOp::Ge => {
+						insert(left, self.usage_ty(right));
+						insert(right, self.usage_ty(left));
+					},
+					_ => {
+						insert(left, self.usage_ty(right).or(expected));
+						insert(right, self.usage_ty(left).or(expected));
+					},
+				}
+			},
+			YarnExpr::CustomFunctionCall { args, .. } => {
+				for arg in args {
+					insert(arg, None);
+				}
+			},
+			YarnExpr::Cast { expr, .. } => {
+				insert(expr, None);
+			},
+			YarnExpr::BuiltInFunctionCall(call) => {
+				match call {
+					BuiltInFunctionCall::FormatInvariant(arg) => {
+						insert(arg, None);
+					},
+					BuiltInFunctionCall::Dice(sides) => {
+						insert(sides, exact(DeclarationTy::usize));
+					},
+					BuiltInFunctionCall::RandomRange(lower, upper) => {
+						insert(lower, exact(DeclarationTy::f64));
+						insert(upper, exact(DeclarationTy::f64));
+					},
+					BuiltInFunctionCall::RoundPlaces(num, places) => {
+						insert(num, exact(DeclarationTy::f64));
+						insert(places, exact(DeclarationTy::i32));
+					},
+					| BuiltInFunctionCall::Round(num)
+					| BuiltInFunctionCall::Floor(num)
+					| BuiltInFunctionCall::Ceil(num)
+					| BuiltInFunctionCall::Inc(num)
+					| BuiltInFunctionCall::Dec(num)
+					| BuiltInFunctionCall::Decimal(num)
+					| BuiltInFunctionCall::Int(num) => {
+						insert(num, exact(DeclarationTy::f64));
+					},
+					| BuiltInFunctionCall::Random
+					| BuiltInFunctionCall::Visited(_)
+					| BuiltInFunctionCall::VisitedCount(_) => {},
+				}
+			},
+			| YarnExpr::Lit(_)
+			| YarnExpr::Identifier(_) => {},
+		}
+	}
+}
+
+fn built_in_name(call: &BuiltInFunctionCall) -> &'static str {
+	match call {
+		BuiltInFunctionCall::Visited(_) => "visited",
+		BuiltInFunctionCall::VisitedCount(_) => "visited_count",
+		BuiltInFunctionCall::FormatInvariant(_) => "format_invariant",
+		BuiltInFunctionCall::Random => "random",
+		BuiltInFunctionCall::RandomRange(_, _) => "random_range",
+		BuiltInFunctionCall::Dice(_) => "dice",
+		BuiltInFunctionCall::Round(_) => "round",
+		BuiltInFunctionCall::RoundPlaces(_, _) => "round_places",
+		BuiltInFunctionCall::Floor(_) => "floor",
+		BuiltInFunctionCall::Ceil(_) => "ceil",
+		BuiltInFunctionCall::Inc(_) => "inc",
+		BuiltInFunctionCall::Dec(_) => "dec",
+		BuiltInFunctionCall::Decimal(_) => "decimal",
+		BuiltInFunctionCall::Int(_) => "int",
+	}
+}
+
+fn binary_op_ty(op: YarnBinaryOp, left: ExprTy, right: ExprTy) -> Result<ExprTy, Mismatch> {
+	let symbol = op.resolve();
+	let known = left.or(right);
+
+	let accepted =
+		match (left, right) {
+			(Some(left), Some(right)) => {
+				let (left_kind, right_kind) = (Kind::of(left), Kind::of(right));
+
+				match op {
+					YarnBinaryOp::Add =>
+						left_kind == right_kind && left_kind != Kind::Bool,
+					| YarnBinaryOp::Sub
+					| YarnBinaryOp::Mul
+					| YarnBinaryOp::Div
+					| YarnBinaryOp::Rem =>
+						left_kind == Kind::Number && right_kind == Kind::Number,
+					| YarnBinaryOp::And
+					| YarnBinaryOp::Or =>
+						left_kind == Kind::Bool && right_kind == Kind::Bool,
+					| YarnBinaryOp::Eq
+					| YarnBinaryOp::Ne
+					| YarnBinaryOp::Lt
+					| YarnBinaryOp::Le
+					| YarnBinaryOp::Gt
+					| YarnBinaryOp::Ge =>
+						left_kind == right_kind,
+					| YarnBinaryOp::BitXor
+					| YarnBinaryOp::BitAnd
+					| YarnBinaryOp::BitOr =>
+						(left_kind == Kind::Bool && right_kind == Kind::Bool) || (is_integer(left) && is_integer(right)),
+				}
+			},
+			_ => {
+				// Only one side is known, it must at least be accepted by the operator.
+				match (op, known.map(Kind::of)) {
+					(YarnBinaryOp::Add, Some(Kind::Bool)) => false,
+					(YarnBinaryOp::Sub | YarnBinaryOp::Mul | YarnBinaryOp::Div | YarnBinaryOp::Rem, Some(kind)) => kind == Kind::Number,
+					(YarnBinaryOp::And | YarnBinaryOp::Or, Some(kind)) => kind == Kind::Bool,
+					_ => true,
+				}
+			},
+		};
+
+	if !accepted {
+		let describe = |ty: ExprTy| ty.map_or_else(|| "unknown".to_string(), |ty| format!("`{ty}`"));
+
+		return Err(Mismatch::new(format!(
+			"Operator `{symbol}` cannot be applied to {} and {}.", describe(left), describe(right)))
+			.with_help(match op {
+				YarnBinaryOp::Add => "`+` adds two numbers or concatenates two strings.",
+				YarnBinaryOp::Sub | YarnBinaryOp::Mul | YarnBinaryOp::Div | YarnBinaryOp::Rem => "Arithmetic operators only accept numbers.",
+				YarnBinaryOp::And | YarnBinaryOp::Or => "Logical operators only accept `bool`s.",
+				YarnBinaryOp::Eq | YarnBinaryOp::Ne | YarnBinaryOp::Lt | YarnBinaryOp::Le | YarnBinaryOp::Gt | YarnBinaryOp::Ge =>
+					"Both sides of a comparison must have the same type, you can convert numbers to text with `format_invariant`.",
+				YarnBinaryOp::BitXor | YarnBinaryOp::BitAnd | YarnBinaryOp::BitOr => "Bitwise operators only accept two `bool`s or two integers.",
+			}));
+	}
+
+	Ok(match op {
+		| YarnBinaryOp::And
+		| YarnBinaryOp::Or
+		| YarnBinaryOp::Eq
+		| YarnBinaryOp::Ne
+		| YarnBinaryOp::Lt
+		| YarnBinaryOp::Le
+		| YarnBinaryOp::Gt
+		| YarnBinaryOp::Ge => Some(DeclarationTy::bool),
+		_ => known,
+	})
+}
+
+fn set_op_accepts(op: SetOperation, var_ty: DeclarationTy, value_ty: DeclarationTy) -> bool {
+	let (var_kind, value_kind) = (Kind::of(var_ty), Kind::of(value_ty));
+
+	match op {
+		SetOperation::Assign => var_kind == value_kind,
+		SetOperation::Add => var_kind == value_kind && var_kind != Kind::Bool,
+		| SetOperation::Sub
+		| SetOperation::Mul
+		| SetOperation::Div
+		| SetOperation::Rem => var_kind == Kind::Number && value_kind == Kind::Number,
+	}
+}
+
+fn set_op_symbol(op: SetOperation) -> &'static str {
+	match op {
+		SetOperation::Assign => "=",
+		SetOperation::Add => "+=",
+		SetOperation::Sub => "-=",
+		SetOperation::Mul => "*=",
+		SetOperation::Div => "/=",
+		SetOperation::Rem => "%=",
+	}
+}
+
+/// Declared variables use [VarDeclaration::infer_ty], undeclared ones are typed by unifying all their usages,
+/// see [UsageTy::unify].
+///
+/// Undeclared variables with usages that can't be unified are reported listing every usage,
+/// their type is left unknown so the conflict isn't reported again by [check_nodes].
+fn resolve_var_types<'a>(
+	nodes: &'a [YarnNode],
+	var_declarations: &'a [VarDeclaration],
+	diagnostics: &mut Vec<Diagnostic>,
+) -> VarTypes<'a> {
+	let mut vars = VarTypes {
+		types: var_declarations.iter().map(|declaration| (declaration.var_name.as_str(), declaration.infer_ty())).collect(),
+		declared: var_declarations.iter().map(|declaration| declaration.var_name.as_str()).collect(),
+	};
+
+	let mut usages = vec![];
+
+	for node in nodes {
+		visit_node(node, &mut |line_number, usage| {
+			let span = Span::line(&node.source_file, line_number);
+
+			match usage {
+				Usage::Any(expr) => vars.insert_expr_usages(expr, None, &span, &mut usages),
+				Usage::Condition(expr) => vars.insert_expr_usages(expr, Some(UsageTy::Exact(DeclarationTy::bool)), &span, &mut usages),
+				Usage::Set { var_name, op: _, value } => {
+					usages.push(VarUsage { var_name, ty: vars.usage_ty(value), span: span.clone() });
+					vars.insert_expr_usages(value, None, &span, &mut usages);
+				},
+				Usage::Speaker(var_name) => {
+					usages.push(VarUsage { var_name, ty: Some(UsageTy::Exact(DeclarationTy::String)), span });
+				},
+				| Usage::Jump(_)
+				| Usage::Command(_) => {},
+			}
+		});
+	}
+
+	// Keeps the order of first usage, so errors are reported in a stable order.
+	let mut by_var: Vec<(&str, Vec<VarUsage>)> = vec![];
+
+	for usage in usages {
+		if vars.declared.contains(usage.var_name) {
+			continue;
+		}
+
+		match by_var.iter_mut().find(|(var_name, _)| *var_name == usage.var_name) {
+			Some((_, var_usages)) => var_usages.push(usage),
+			None => by_var.push((usage.var_name, vec![usage])),
+		}
+	}
+
+	for (var_name, var_usages) in by_var {
+		let mut unified: Option<UsageTy> = None;
+
+		let conflict = var_usages.iter().find(|usage| {
+			let Some(ty) = usage.ty
+				else { return false };
+
+			match unified.map_or(Some(ty), |unified| unified.unify(ty)) {
+				Some(ty) => {
+					unified = Some(ty);
+					false
+				},
+				None => true,
+			}
+		});
+
+		let (Some(conflict), Some(unified)) = (conflict, unified)
+			else {
+				vars.types.insert(var_name, unified.map(UsageTy::resolve));
+				continue;
+			};
+
+		vars.types.insert(var_name, None);
+
+		let mut diagnostic =
+			Diagnostic::error(ErrorCode::ConflictingVarTypes, format!(
+				"Cannot infer the type of variable `${var_name}`, it's used as {ty} here, \
+				 but as {unified} elsewhere.", ty = conflict.ty.unwrap()))
+				.with_span(conflict.span.clone());
+
+		for usage in &var_usages {
+			if let Some(ty) = usage.ty {
+				diagnostic = diagnostic.with_note(format!("Used as {ty} at {span}", span = usage.span));
+			}
+		}
+
+		let ty = unified.resolve();
+		diagnostic = diagnostic.with_help(format!("Declare the variable with an explicit type, like: `<<declare ${var_name} = ... as {ty}>>`"));
+
+		// Only integers are widened to floats, other numbers need a cast to fit the declared type.
+		if unified.is_number() && conflict.ty.is_some_and(UsageTy::is_number) {
+			diagnostic = diagnostic.with_help(format!("Then cast the values of other number types, like: `<<set ${var_name} to ... as {ty}>>`"));
+		}
+
+		diagnostics.push(diagnostic);
+	}
+
+	vars
+}
+
+/// Checks the types of every expression in `nodes`, before any Rust code is generated,
+/// so type mistakes are reported with their yarn location instead of as errors in the generated code.
+///
+/// Also returns the type of every declared or used variable, which the generated storage uses.
+pub fn check_nodes(
+	nodes: &[YarnNode],
+	var_declarations: &[VarDeclaration],
+) -> (HashMap<String, Option<DeclarationTy>>, Vec<Diagnostic>) {
+	let mut diagnostics = vec![];
+	let vars = resolve_var_types(nodes, var_declarations, &mut diagnostics);
+
+	for node in nodes {
+		visit_node(node, &mut |line_number, usage| {
+			let result =
+				match usage {
+					Usage::Any(expr) => vars.type_of(expr).map(|_| ()),
+					Usage::Condition(expr) => vars.expect_kind(expr, Kind::Bool, "Condition").map(|_| ()),
+					Usage::Speaker(var_name) => {
+						match vars.types.get(var_name) {
+							Some(&Some(ty)) if ty != DeclarationTy::String =>
+								Err(Mismatch::new(format!("Speaker variable `${var_name}` must be a `String`, found `{ty}`."))),
+							_ => Ok(()),
+						}
+					},
+					| Usage::Jump(_)
+					| Usage::Command(_) => Ok(()),
+					Usage::Set { var_name, op, value } => {
+						vars.type_of(value)
+							.and_then(|value_ty| {
+								match (vars.types.get(var_name), value_ty) {
+									_ if op == SetOperation::Assign && !vars.declared.contains(var_name) => Ok(()),
+									(Some(&Some(var_ty)), Some(value_ty)) if !set_op_accepts(op, var_ty, value_ty) =>
+										Err(Mismatch::new(format!(
+											"Cannot apply `{symbol}` to variable `${var_name}` of type `{var_ty}` with a value of type `{value_ty}`.",
+											symbol = set_op_symbol(op)))
+											.with_help("`=` requires a value of the same type, `+=` also accepts strings, \
+											            other operations only accept numbers.")),
+									(Some(&Some(var_ty)), Some(_)) if let Some(usage) = vars.usage_ty(value)
+										&& Kind::of(var_ty) == Kind::Number
+										&& usage.unify(UsageTy::Exact(var_ty)) != Some(UsageTy::Exact(var_ty)) =>
+										Err(Mismatch::new(format!(
+											"Cannot apply `{symbol}` to variable `${var_name}` of type `{var_ty}` with {usage}.",
+											symbol = set_op_symbol(op)))
+											.with_help(format!("Only integers are converted to decimals, cast the value with `as {var_ty}`."))),
+									_ => Ok(()),
+								}
+							})
+					},
+				};
+
+			if let Err(mismatch) = result {
+				let mut diagnostic =
+					Diagnostic::error(ErrorCode::TypeMismatch, mismatch.message)
+						.with_span(Span::line(&node.source_file, line_number));
+
+				if let Some(help) = mismatch.help {
+					diagnostic = diagnostic.with_help(help);
+				}
+
+				diagnostics.push(diagnostic);
+			}
+		});
+	}
+
+	// Both passes report errors, sorting keeps them in the order they appear in the files.
+	diagnostics.sort_by_key(|diagnostic| diagnostic.span.as_ref().map(|span| (span.file.clone(), span.line)));
+
+	let var_types =
+		vars.types
+			.into_iter()
+			.map(|(var_name, ty)| (var_name.to_string(), ty))
+			.collect();
+
+	(var_types, diagnostics)
+}
+
+/// Visits every expression of `scope`, with the name of the variable it's assigned to, if it's a `<<set>>` value.
+fn visit_scope_exprs_mut(scope: &mut YarnScope, visit: &mut impl FnMut(Option<&str>, &mut YarnExpr)) {
+	let visit_scope = |scope_option: &mut Option<Box<YarnScope>>, visit: &mut _| {
+		if let Some(scope) = scope_option {
+			visit_scope_exprs_mut(scope, visit);
+		}
+	};
+
+	for flow in scope.flows_mut() {
+		match flow {
+			Flow::Flat(lines) => {
+				for line in lines {
+					match line {
+						FlatLine::Speech(speech) => speech.text.1.iter_mut().for_each(|arg| visit(None, arg)),
+						FlatLine::Command(command) => {
+							match &mut command.variant {
+								CommandVariant::Set { var_name, value, .. } => visit(Some(var_name), value),
+								CommandVariant::Other { args, .. } => args.iter_mut().for_each(|arg| visit(None, arg)),
+								| CommandVariant::Jump { .. }
+								| CommandVariant::Detour { .. }
+								| CommandVariant::Return
+								| CommandVariant::Stop => {},
+							}
+						},
+					}
+				}
+			},
+			Flow::OptionsFork(options_fork) => {
+				for (option, scope_option) in options_fork.options.iter_mut() {
+					option.text.1.iter_mut().chain(&mut option.if_condition).for_each(|expr| visit(None, expr));
+					visit_scope(scope_option, visit);
+				}
+			},
+			Flow::LineGroup(line_group) => {
+				for (group_line, scope_option) in line_group.lines.iter_mut() {
+					group_line.speech.text.1.iter_mut().chain(&mut group_line.condition).for_each(|expr| visit(None, expr));
+					visit_scope(scope_option, visit);
+				}
+			},
+			Flow::IfBranch(if_branch) => {
+				visit(None, &mut if_branch.if_.0.condition);
+				visit_scope(&mut if_branch.if_.1, visit);
+
+				for (else_if, scope_option) in &mut if_branch.else_ifs {
+					visit(None, &mut else_if.condition);
+					visit_scope(scope_option, visit);
+				}
+
+				if let Some((_, scope_option)) = &mut if_branch.else_ {
+					visit_scope(scope_option, visit);
+				}
+			},
+			Flow::OnceBranch(once_branch) => {
+				if let Some(condition) = &mut once_branch.once_.0.condition {
+					visit(None, condition);
+				}
+
+				visit_scope(&mut once_branch.once_.1, visit);
+
+				if let Some((_, scope_option)) = &mut once_branch.else_ {
+					visit_scope(scope_option, visit);
+				}
+			},
+		}
+	}
+}
+
+impl VarTypes<'_> {
+	/// Converts the integers inside `expr` that are used as floats, `float_ty` is the float type `expr` must have.
+	fn widen_expr(&self, expr: &mut YarnExpr, float_ty: Option<DeclarationTy>) {
+		let usage = self.usage_ty(expr);
+
+		if let Some(float_ty) = float_ty
+			&& usage.is_some_and(UsageTy::is_integer) {
+			self.widen_expr(expr, None);
+
+			let integer = std::mem::replace(expr, YarnExpr::Identifier(String::new()));
+
+			*expr =
+				match integer {
+					YarnExpr::Lit(YarnLit::Int(int)) => YarnExpr::Lit(YarnLit::Float(int as f64)),
+					integer => {
+						let integer =
+							match integer {
+								atom @ (YarnExpr::GetVar(_) | YarnExpr::Parenthesis(_)) => atom,
+								other => YarnExpr::Parenthesis(Box::new(other)),
+							};
+
+						// Without parenthesis, `x as f64 < y` would be parsed as generics.
+						YarnExpr::Parenthesis(Box::new(YarnExpr::Cast { cast_ty: float_ty, expr: Box::new(integer) }))
+					},
+				};
+
+			return;
+		}
+
+		match expr {
+			| YarnExpr::Parenthesis(inner)
+			| YarnExpr::UnaryOp { yarn_op: YarnUnaryOp::Negate, right: inner } => {
+				self.widen_expr(inner, float_ty);
+			},
+			YarnExpr::BinaryOp { yarn_op, left, right } => {
+				let operands_float_ty =
+					match yarn_op {
+						| YarnBinaryOp::Add
+						| YarnBinaryOp::Sub
+						| YarnBinaryOp::Mul
+						| YarnBinaryOp::Div
+						| YarnBinaryOp::Rem => float_ty.or_else(|| usage.and_then(UsageTy::float_ty)),
+						| YarnBinaryOp::Eq
+						| YarnBinaryOp::Ne
+						| YarnBinaryOp::Lt
+						| YarnBinaryOp::Le
+						| YarnBinaryOp::Gt
+						| YarnBinaryOp::Ge => {
+							self.usage_ty(left)
+							    .zip(self.usage_ty(right))
+							    .and_then(|(left, right)| left.unify(right))
+							    .and_then(UsageTy::float_ty)
+						},
+						_ => None,
+					};
+
+				self.widen_expr(left, operands_float_ty);
+				self.widen_expr(right, operands_float_ty);
+			},
+			_ => {
+				// Arguments of functions and casts are not widened.
+			},
+		}
+	}
+}
+
+/// Converts integers used as floats, which [UsageTy::unify] allows: integer literals become decimal literals,
+/// other integers are cast to the float type.
+///
+/// Must only run on nodes without errors from [check_nodes], `var_types` being the types it returned.
+pub fn widen_numbers(nodes: &mut [YarnNode], var_types: &HashMap<String, Option<DeclarationTy>>) {
+	let vars = VarTypes {
+		types: var_types.iter().map(|(var_name, ty)| (var_name.as_str(), *ty)).collect(),
+		declared: HashSet::new(),
+	};
+
+	for node in nodes {
+		for (_, when) in &mut node.metadata.when {
+			if let WhenCondition::Condition(condition) = when {
+				vars.widen_expr(condition, None);
+			}
+		}
+
+		for scope in &mut node.contents {
+			visit_scope_exprs_mut(scope, &mut |var_name, expr| {
+				let float_ty = var_name.and_then(|var_name| vars.types.get(var_name).copied().flatten()).filter(|ty| is_float(*ty));
+				vars.widen_expr(expr, float_ty);
+			});
+		}
+	}
+}
+
+#[test]
+fn test_check_nodes() {
+	use crate::io::read::yarn_file_from_text;
+	use pretty_assertions::assert_eq;
+	use std::path::PathBuf;
+
+	fn check(text: &str) -> (Vec<YarnNode>, HashMap<String, Option<DeclarationTy>>, Vec<Diagnostic>) {
+		let mut parse_errors = vec![];
+		let (nodes, var_declarations) = crate::parsing::parse_nodes(yarn_file_from_text(PathBuf::from("test.yarn"), text), &mut parse_errors);
+		assert_eq!(parse_errors, []);
+
+		let (var_types, diagnostics) = check_nodes(&nodes, &var_declarations);
+		(nodes, var_types, diagnostics)
+	}
+
+	fn errors(text: &str) -> Vec<(LineNumber, ErrorCode)> {
+		check(text).2
+			.into_iter()
+			.map(|diagnostic| (diagnostic.span.unwrap().line, diagnostic.code.unwrap()))
+			.collect()
+	}
+
+	assert_eq!(errors(
+		"title: Valid\n\
+		 ---\n\
+		 <<set $name to \"Bob\">>\n\
+		 <<set $gold to 5>>\n\
+		 <<if $gold > 3 and not visited(\"Valid\")>>\n\
+		 \x20   Hello {$name}, you have {$gold + 1} gold.\n\
+		 <<endif>>\n\
+		 <<set $gold += round(1.5)>>\n\
+		 <<set $name += \" the Great\">>\n\
+		 ==="), []);
+
+	assert_eq!(errors(
+		"title: Invalid\n\
+		 ---\n\
+		 <<declare $name = \"Bob\">>\n\
+		 <<declare $gold = 5>>\n\
+		 <<if $name > 5>>\n\
+		 \x20   Hello\n\
+		 <<endif>>\n\
+		 <<if $gold>>\n\
+		 \x20   Hello\n\
+		 <<endif>>\n\
+		 <<set $gold to \"a lot\">>\n\
+		 <<set $name -= 1>>\n\
+		 Hello {round(\"x\")}\n\
+		 <<set $gold to 2.5>>\n\
+		 <<if $gold > 2.5>>\n\
+		 \x20   Hello\n\
+		 <<endif>>\n\
+		 <<set $gold to 2.5 as isize>>\n\
+		 <<set $ratio to 0.5 as f32>>\n\
+		 <<set $ratio to 2.5 as f64>>\n\
+		 ==="), [
+		(5, ErrorCode::TypeMismatch),
+		(8, ErrorCode::TypeMismatch),
+		(11, ErrorCode::TypeMismatch),
+		(12, ErrorCode::TypeMismatch),
+		(13, ErrorCode::TypeMismatch),
+		(14, ErrorCode::TypeMismatch),
+		(20, ErrorCode::ConflictingVarTypes),
+	]);
+
+	let (_, var_types, diagnostics) = check(
+		"title: Inferred\n\
+		 ---\n\
+		 <<set $gold to 5>>\n\
+		 <<if $gold > 2 and $ready>>\n\
+		 \x20   Hello {$unknown}\n\
+		 <<endif>>\n\
+		 <<set $sides to 6>>\n\
+		 <<set $count to dice($sides)>>\n\
+		 <<set $ratio to 0.5>>\n\
+		 <<set $ratio *= random()>>\n\
+		 ===");
+	assert_eq!(diagnostics, []);
+
+	let mut var_types = var_types.into_iter().collect::<Vec<_>>();
+	var_types.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+	assert_eq!(var_types, [
+		("count".to_string(), Some(DeclarationTy::isize)),
+		("gold".to_string(), Some(DeclarationTy::isize)),
+		("ratio".to_string(), Some(DeclarationTy::f64)),
+		("ready".to_string(), Some(DeclarationTy::bool)),
+		("sides".to_string(), Some(DeclarationTy::usize)),
+		("unknown".to_string(), None),
+	]);
+
+	// Integers widen to decimals, the generated code converts them.
+	let (mut nodes, var_types, diagnostics) = check(
+		"title: Widened\n\
+		 ---\n\
+		 <<declare $count = 3>>\n\
+		 <<set $gold to 5>>\n\
+		 <<set $gold += 2.5>>\n\
+		 <<if $count < $gold and $gold > $count + 1>>\n\
+		 \x20   <<set $gold to -2>>\n\
+		 <<endif>>\n\
+		 Hello {$gold * $count} {$count * 2}\n\
+		 ===");
+	assert_eq!(diagnostics, []);
+	assert_eq!(var_types["gold"], Some(DeclarationTy::f64));
+	assert_eq!(var_types["count"], Some(DeclarationTy::isize));
+
+	widen_numbers(&mut nodes, &var_types);
+
+	let mut widened = vec![];
+
+	for scope in &mut nodes[0].contents {
+		visit_scope_exprs_mut(scope, &mut |_, expr| {
+			widened.push(crate::quoting::test_util::render(genco::quote!($(&*expr))).trim().to_string());
+		});
+	}
+
+	assert_eq!(widened, [
+		"5.0",
+		"2.5",
+		"(storage.get_var::<count>() as f64) < storage.get_var::<gold>() && storage.get_var::<gold>() > ((storage.get_var::<count>() + 1) as f64)",
+		"-2.0",
+		"storage.get_var::<gold>() * (storage.get_var::<count>() as f64)",
+		"storage.get_var::<count>() * 2",
+	]);
+
+	// Only integers are widened, other numbers of different types need casts.
+	let (_, var_types, diagnostics) = check(
+		"title: Conflict\n\
+		 ---\n\
+		 <<declare $small = 1 as u8>>\n\
+		 <<declare $big = 1 as i32>>\n\
+		 <<set $total to $big>>\n\
+		 <<set $total to $small>>\n\
+		 <<if $name == \"Bob\">>\n\
+		 \x20   Hello\n\
+		 <<endif>>\n\
+		 <<if $name > 5>>\n\
+		 \x20   Hello\n\
+		 <<endif>>\n\
+		 ===");
+
+	assert_eq!(var_types.get("total"), Some(&None));
+
+	let [total, name] = diagnostics.as_slice()
+		else { panic!("Expected exactly two diagnostics, got: {diagnostics:?}") };
+
+	assert_eq!(total.code, Some(ErrorCode::ConflictingVarTypes));
+	assert_eq!(total.span.as_ref().unwrap().line, 6);
+	assert_eq!(total.message, "Cannot infer the type of variable `$total`, it's used as `u8` here, but as `i32` elsewhere.");
+	assert_eq!(total.help, [
+		"Declare the variable with an explicit type, like: `<<declare $total = ... as i32>>`",
+		"Then cast the values of other number types, like: `<<set $total to ... as i32>>`",
+	]);
+
+	assert_eq!(name.span.as_ref().unwrap().line, 10);
+	assert_eq!(name.notes, ["Used as `String` at test.yarn:7:1", "Used as an integer at test.yarn:10:1"]);
+	assert_eq!(name.help, ["Declare the variable with an explicit type, like: `<<declare $name = ... as String>>`"]);
+}
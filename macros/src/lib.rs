@@ -0,0 +1,212 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
+use std::path::PathBuf;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{bracketed, parse_macro_input, Ident, LitStr, Path, Token};
+use yarn_spire_codegen::{ConfigOverrides, DeserializableConfig, YarnConfig};
+
+struct IncludeYarnArgs {
+	yarn_root_folder: LitStr,
+	storage: Path,
+	command: Path,
+	module: Path,
+	vars: Option<Path>,
+	exclude: Vec<LitStr>,
+}
+
+impl Parse for IncludeYarnArgs {
+	fn parse(input: ParseStream) -> syn::Result<Self> {
+		let yarn_root_folder = input.parse::<LitStr>()?;
+
+		let mut storage = None;
+		let mut command = None;
+		let mut module = None;
+		let mut vars = None;
+		let mut exclude = None;
+
+		while !input.is_empty() {
+			input.parse::<Token![,]>()?;
+
+			if input.is_empty() {
+				break;
+			}
+
+			let key = input.parse::<Ident>()?;
+			input.parse::<Token![=]>()?;
+
+			let already_set =
+				match key.to_string().as_str() {
+					"storage" => storage.replace(input.parse::<Path>()?).is_some(),
+					"command" => command.replace(input.parse::<Path>()?).is_some(),
+					"module" => module.replace(input.parse::<Path>()?).is_some(),
+					"vars" => vars.replace(input.parse::<Path>()?).is_some(),
+					"exclude" => {
+						let content;
+						bracketed!(content in input);
+						let folders = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+						exclude.replace(folders.into_iter().collect()).is_some()
+					},
+					_ => {
+						return Err(syn::Error::new(key.span(), format!(
+							"Unknown argument `{key}`.\n\
+							 Help: Expected one of: `storage`, `command`, `module`, `vars`, `exclude`.")));
+					},
+				};
+
+			if already_set {
+				return Err(syn::Error::new(key.span(), format!("Argument `{key}` was provided more than once.")));
+			}
+		}
+
+		let missing = |name: &str, example: &str| {
+			syn::Error::new(Span::call_site(), format!(
+				"Missing argument `{name}`.\n\
+				 Help: You can provide it like this: `{name} = {example}`"))
+		};
+
+		Ok(IncludeYarnArgs {
+			yarn_root_folder,
+			storage: storage.ok_or_else(|| missing("storage", "MyVariablesStorage"))?,
+			command: command.ok_or_else(|| missing("command", "crate::yarn_commands::MyYarnCommand"))?,
+			module: module.ok_or_else(|| missing("module", "crate::dialogue"))?,
+			vars,
+			exclude: exclude.unwrap_or_default(),
+		})
+	}
+}
+
+fn path_to_string(path: &Path) -> String {
+	path.to_token_stream()
+	    .to_string()
+	    .replace(' ', "")
+}
+
+/// Splits `crate::module::Type` into (`crate::module`, `Type`).
+fn split_type_path(path: &Path, arg_name: &str) -> syn::Result<(String, String)> {
+	let full = path_to_string(path);
+
+	full.rsplit_once("::")
+	    .map(|(module, type_name)| (module.to_string(), type_name.to_string()))
+	    .ok_or_else(|| syn::Error::new_spanned(path, format!(
+		    "Argument `{arg_name}` must be the fully qualified path of a type.\n\
+		     Help: Like this: `{arg_name} = crate::my_module::{full}`")))
+}
+
+fn build_config(args: &IncludeYarnArgs) -> syn::Result<YarnConfig> {
+	let (command_module_path, command_type_name) = split_type_path(&args.command, "command")?;
+
+	// Without `vars`, the storage is generated inside the module, so only its name is needed.
+	let (storage_module_path, storage_type_name, vars_module_path, generate_storage) =
+		if let Some(vars) = &args.vars {
+			let (module_path, type_name) = split_type_path(&args.storage, "storage")?;
+			(module_path, type_name, path_to_string(vars), false)
+		} else if let Some(type_name) = args.storage.get_ident() {
+			(String::new(), type_name.to_string(), String::new(), true)
+		} else {
+			return Err(syn::Error::new_spanned(&args.storage,
+				"Argument `storage` must be a single identifier when `vars` is not provided, \
+				 since the storage type will be generated for you.\n\
+				 Help: Provide `vars = path::to::vars_module` to use your own storage type."));
+		};
+
+	let manifest_dir =
+		std::env::var("CARGO_MANIFEST_DIR")
+			.map(PathBuf::from)
+			.map_err(|err| syn::Error::new(Span::call_site(), format!(
+				"Could not read environment variable `CARGO_MANIFEST_DIR`.\n\
+				 Error: {err}")))?;
+
+	let fields = DeserializableConfig {
+		storage_module_path,
+		storage_type_name,
+		command_module_path,
+		command_type_name,
+		vars_module_path,
+		allow_overwrite: false,
+		generate_storage,
+		destination_os_path: String::new(),
+		destination_module_path: path_to_string(&args.module),
+		yarn_root_folder: args.yarn_root_folder.value(),
+		exclude_yarn_folders: args.exclude.iter().map(LitStr::value).collect(),
+		..DeserializableConfig::default()
+	};
+
+	// Nothing is written to disk, so `destination_os_path` is irrelevant.
+	let overrides = ConfigOverrides {
+		destination_os_path: Some(PathBuf::new()),
+		yarn_root_folder: None,
+	};
+
+	YarnConfig::from_fields(fields, &manifest_dir.join("Cargo.toml"), &overrides)
+		.map_err(|err| syn::Error::new(args.yarn_root_folder.span(), err))
+}
+
+fn expand(args: IncludeYarnArgs) -> syn::Result<TokenStream> {
+	let module_name =
+		args.module
+		    .segments
+		    .last()
+		    .map(|segment| segment.ident.to_string())
+		    .ok_or_else(|| syn::Error::new_spanned(&args.module, "Argument `module` cannot be empty."))?;
+
+	let config = build_config(&args)?;
+
+	let output =
+		yarn_spire_codegen::generate(&config)
+			.map_err(|err| syn::Error::new(args.yarn_root_folder.span(), err))?;
+
+	let module =
+		output
+			.to_module_tokens(&module_name)
+			.map_err(|err| syn::Error::new(args.yarn_root_folder.span(), err))?;
+
+	// Makes Cargo recompile the invoking crate whenever one of the yarn files changes.
+	let tracked_files =
+		output
+			.yarn_files
+			.iter()
+			.map(|path| path.display().to_string());
+
+	Ok(quote! {
+		#module
+
+		const _: () = {
+			#( const _: &[u8] = include_bytes!(#tracked_files); )*
+		};
+	})
+}
+
+/// Generates the dialogue code for all yarn files inside a folder, expanding it in-place as a module.
+///
+/// The folder path is relative to the invoking crate's `Cargo.toml`.
+/// The generated module is named after the last segment of `module`, so the macro must be invoked
+/// inside the parent of that path.
+///
+/// ```ignore
+/// // Inside `src/lib.rs`, generates `pub mod dialogue { ... }`:
+/// yarn_spire_macros::include_yarn!("dialogue/",
+///     storage = MyStorage,
+///     command = crate::yarn_commands::MyCommand,
+///     module = crate::dialogue,
+/// );
+/// ```
+///
+/// Arguments:
+/// - `storage`: The storage type's name, generated for you unless `vars` is provided,
+///   in that case it must be the fully qualified path of your own storage type.
+/// - `command`: The fully qualified path of your command type.
+/// - `module`: The module path the generated code will be located at.
+/// - `vars`: (optional) The module path of the structs that implement `IVar`.
+/// - `exclude`: (optional) Folders inside the yarn folder to ignore, like `exclude = ["prototype"]`.
+///
+/// Editing existing yarn files triggers a recompilation,
+/// but newly created files are only picked up after the invoking crate is rebuilt for another reason.
+#[proc_macro]
+pub fn include_yarn(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let args = parse_macro_input!(input as IncludeYarnArgs);
+
+	expand(args)
+		.unwrap_or_else(syn::Error::into_compile_error)
+		.into()
+}